@@ -0,0 +1,427 @@
+pub mod noise;
+pub mod recorder;
+pub mod square;
+pub mod wave;
+
+use std::io;
+
+use noise::Noise;
+use recorder::Recorder;
+use square::Square;
+use wave::Wave;
+
+// 一秒对应的 M-cycle 数
+const CYCLES_PER_SECOND: u64 = 1 << 20;
+
+// 帧序列器由 DIV 第 4 位（内部分频计数器第 12 位）的下降沿驱动，频率 512 Hz；
+// CGB 倍速模式下分频计数器快一倍，改用第 13 位
+const FRAME_SEQUENCER_BIT: u16 = 1 << 12;
+
+// 默认输出采样率
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+// 最高采样率：每个 M-cycle 至多产生一个采样
+pub const MAX_SAMPLE_RATE: u32 = CYCLES_PER_SECOND as u32;
+
+// 未被取走的采样最多保留的秒数，超出后丢弃最旧的采样
+const MAX_BUFFERED_SECONDS: usize = 4;
+
+// 高通滤波电容每个 T-cycle 的保持系数（模拟输出端隔直电容）
+const HIGH_PASS_CHARGE: f64 = 0.999958;
+
+// 0xFF10-0xFF26 读出时恒为 1 的位
+const READ_MASK: [u8; 0x17] = [
+    0x80, 0x3f, 0x00, 0xff, 0xbf,
+    0xff, 0x3f, 0x00, 0xff, 0xbf,
+    0x7f, 0xff, 0x9f, 0xff, 0xbf,
+    0xff, 0xff, 0x00, 0x00, 0xbf,
+    0x00, 0x00, 0x70,
+];
+
+// 音量包络（NRx2）
+#[derive(Debug, Default, Clone)]
+pub struct Envelope {
+    initial: u8,
+    up: bool,
+    period: u8,
+    pub volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, data: u8) {
+        self.initial = data >> 4;
+        self.up = data & 0x08 != 0;
+        self.period = data & 0x07;
+    }
+
+    // NRx2 高 5 位全为 0 时 DAC 关闭
+    pub fn dac(&self) -> bool {
+        self.initial != 0 || self.up
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial;
+        self.timer = self.period;
+    }
+
+    // 帧序列器第 7 步
+    pub fn clock(&mut self) {
+        if self.period == 0 {return;}
+        if self.timer > 0 {self.timer -= 1;}
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.up && self.volume < 15 {self.volume += 1;}
+            if !self.up && self.volume > 0 {self.volume -= 1;}
+        }
+    }
+}
+
+// 长度计数器：方波与噪声为 64，波形为 256
+#[derive(Debug, Clone)]
+pub struct Length {
+    max: u16,
+    counter: u16,
+    pub enabled: bool,
+}
+
+impl Length {
+    pub fn new(max: u16) -> Self {
+        Self { max, counter: 0, enabled: false }
+    }
+
+    pub fn load(&mut self, value: u16) {
+        self.counter = self.max - value;
+    }
+
+    pub fn trigger(&mut self) {
+        if self.counter == 0 {self.counter = self.max;}
+    }
+
+    // 帧序列器偶数步；计数减到 0 时返回 true，通道随之关闭
+    pub fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 {return false;}
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
+
+// DAC：数字量 0-15 映射到 -1.0..1.0，DAC 关闭时输出 0
+fn dac_output(sample: Option<u8>) -> f32 {
+    match sample {
+        Some(sample) => sample as f32 / 7.5 - 1.0,
+        None => 0.0,
+    }
+}
+
+// APU：0xFF10-0xFF26 声音寄存器与 0xFF30-0xFF3F 波形 RAM
+//
+// 各通道按 T-cycle 推进，每个 M-cycle 混音一次；输出时对一个采样周期内的混音结果取平均，
+// 重采样到 sample_rate，经高通滤波后以左右声道交错的 f32 存入 samples
+pub struct Apu {
+    pub power: bool,
+    pub square1: Square,
+    pub square2: Square,
+    pub wave: Wave,
+    pub noise: Noise,
+    pub wave_ram: [u8; 0x10],
+    regs: [u8; 0x17],
+    frame_step: u8,
+    div_bit: bool,
+    pub sample_rate: u32,
+    sample_clock: u64,
+    acc: [f32; 2],
+    acc_channels: [f32; 4],
+    acc_count: u32,
+    high_pass: [f32; 2],
+    pub samples: Vec<f32>,
+    pub recorder: Option<Recorder>,
+    // 录音写文件失败时的错误，录音随之停止，在 stop_audio_recording 时报告
+    pub record_error: Option<io::Error>,
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            power: false,
+            square1: Square::new(true),
+            square2: Square::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            wave_ram: [0; 0x10],
+            regs: [0; 0x17],
+            frame_step: 0,
+            div_bit: false,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            sample_clock: 0,
+            acc: [0.0; 2],
+            acc_channels: [0.0; 4],
+            acc_count: 0,
+            high_pass: [0.0; 2],
+            samples: Vec::new(),
+            recorder: None,
+            record_error: None,
+        }
+    }
+
+    // 推进一个 M-cycle；div 为定时器内部分频计数器，倍速模式下一个 M-cycle 只有 2 个 T-cycle
+    pub fn step(&mut self, div: u16, double_speed: bool) {
+        let div_bit = div & (FRAME_SEQUENCER_BIT << double_speed as u32) != 0;
+        if self.power && self.div_bit && !div_bit {self.clock_frame_sequencer();}
+        self.div_bit = div_bit;
+
+        if self.power {
+            let t_cycles = if double_speed {2} else {4};
+            self.square1.tick(t_cycles);
+            self.square2.tick(t_cycles);
+            self.wave.tick(t_cycles, &self.wave_ram);
+            self.noise.tick(t_cycles);
+        }
+
+        let outputs = if self.power {self.channel_outputs()} else {[0.0; 4]};
+        let [left, right] = self.mix(&outputs);
+        self.acc[0] += left;
+        self.acc[1] += right;
+        // 分通道录音不经高通滤波，已关闭的通道记为 0，避免 DAC 仍开启时留下 -1.0 的直流
+        let enabled = self.channels_enabled();
+        for ((acc, output), on) in self.acc_channels.iter_mut().zip(outputs).zip(enabled) {
+            if on {*acc += output;}
+        }
+        self.acc_count += 1;
+        self.sample_clock += self.sample_rate as u64;
+        let cycles_per_second = CYCLES_PER_SECOND << double_speed as u32;
+        if self.sample_clock >= cycles_per_second {
+            self.sample_clock -= cycles_per_second;
+            self.push_sample();
+        }
+    }
+
+    // 帧序列器：0/2/4/6 步长度计数，2/6 步频率扫描，7 步音量包络
+    fn clock_frame_sequencer(&mut self) {
+        if self.frame_step.is_multiple_of(2) {
+            if self.square1.length.clock() {self.square1.enabled = false;}
+            if self.square2.length.clock() {self.square2.enabled = false;}
+            if self.wave.length.clock() {self.wave.enabled = false;}
+            if self.noise.length.clock() {self.noise.enabled = false;}
+        }
+        if self.frame_step == 2 || self.frame_step == 6 {self.square1.clock_sweep();}
+        if self.frame_step == 7 {
+            self.square1.envelope.clock();
+            self.square2.envelope.clock();
+            self.noise.envelope.clock();
+        }
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    // 四个通道的 DAC 输出
+    pub fn channel_outputs(&self) -> [f32; 4] {
+        [
+            dac_output(self.square1.output()),
+            dac_output(self.square2.output()),
+            dac_output(self.wave.output()),
+            dac_output(self.noise.output()),
+        ]
+    }
+
+    // 四个通道是否在发声（NR52 低 4 位）
+    fn channels_enabled(&self) -> [bool; 4] {
+        [self.square1.enabled, self.square2.enabled, self.wave.enabled, self.noise.enabled]
+    }
+
+    // NR51 声道选择与 NR50 主音量
+    fn mix(&self, outputs: &[f32; 4]) -> [f32; 2] {
+        if !self.power {return [0.0; 2];}
+        let (nr50, nr51) = (self.regs[0x14], self.regs[0x15]);
+        let mut left = 0.0;
+        let mut right = 0.0;
+        for (i, output) in outputs.iter().enumerate() {
+            if nr51 & (0x10 << i) != 0 {left += output;}
+            if nr51 & (0x01 << i) != 0 {right += output;}
+        }
+        let left_volume = ((nr50 >> 4) & 0x07) as f32 + 1.0;
+        let right_volume = (nr50 & 0x07) as f32 + 1.0;
+        [left / 4.0 * left_volume / 8.0, right / 4.0 * right_volume / 8.0]
+    }
+
+    fn push_sample(&mut self) {
+        let count = self.acc_count.max(1) as f32;
+        let charge = HIGH_PASS_CHARGE.powf(4.0 * CYCLES_PER_SECOND as f64 / self.sample_rate as f64) as f32;
+        let mut stereo = [0.0; 2];
+        for (channel, output) in stereo.iter_mut().enumerate() {
+            let input = self.acc[channel] / count;
+            *output = input - self.high_pass[channel];
+            self.high_pass[channel] = input - *output * charge;
+            self.samples.push(*output);
+        }
+        let channels = self.acc_channels.map(|acc| acc / count);
+        self.acc = [0.0; 2];
+        self.acc_channels = [0.0; 4];
+        self.acc_count = 0;
+
+        if let Some(recorder) = &mut self.recorder
+            && let Err(err) = recorder.write(stereo, channels) {
+            self.record_error = Some(err);
+            self.recorder = None;
+        }
+
+        let max = self.sample_rate as usize * 2 * MAX_BUFFERED_SECONDS;
+        if self.samples.len() > max {
+            // 多丢弃 1/4 秒，避免每个采样都移动整个缓冲区
+            let keep = max - self.sample_rate as usize / 2;
+            self.samples.drain(..self.samples.len() - keep);
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, rate: u32) {
+        self.sample_rate = rate;
+        self.sample_clock = 0;
+        self.acc = [0.0; 2];
+        self.acc_channels = [0.0; 4];
+        self.acc_count = 0;
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff30..=0xff3f => self.wave_ram[(addr - 0xff30) as usize],
+            0xff26 => {
+                let status = self.channels_enabled().iter().enumerate().fold(0, |acc, (i, &on)| acc | ((on as u8) << i));
+                0x70 | ((self.power as u8) << 7) | status
+            }
+            0xff10..=0xff25 => {
+                let index = (addr - 0xff10) as usize;
+                self.regs[index] | READ_MASK[index]
+            }
+            _ => 0xff,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xff30..=0xff3f => self.wave_ram[(addr - 0xff30) as usize] = data,
+            0xff26 => {
+                let power = data & 0x80 != 0;
+                if self.power && !power {
+                    // 关闭电源：清空全部声音寄存器（波形 RAM 保留）
+                    self.regs = [0; 0x17];
+                    self.square1 = Square::new(true);
+                    self.square2 = Square::new(false);
+                    self.wave = Wave::new();
+                    self.noise = Noise::new();
+                } else if !self.power && power {
+                    self.frame_step = 0;
+                }
+                self.power = power;
+            }
+            // 电源关闭时写入无效
+            0xff10..=0xff25 if !self.power => {},
+            0xff10..=0xff25 => {
+                let index = (addr - 0xff10) as usize;
+                self.regs[index] = data;
+                let reg = (index % 5) as u8;
+                match index / 5 {
+                    0 => self.square1.write(reg, data),
+                    1 => self.square2.write(reg, data),
+                    2 => self.wave.write(reg, data),
+                    3 => self.noise.write(reg, data),
+                    _ => {},
+                }
+            }
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按正常速度推进 cycles 个 M-cycle，div 与定时器的分频计数器同步递增
+    fn run(apu: &mut Apu, div: &mut u16, cycles: u64) {
+        for _ in 0..cycles {
+            *div = div.wrapping_add(4);
+            apu.step(*div, false);
+        }
+    }
+
+    // 帧序列器每 2048 个 M-cycle 前进一步
+    const FRAME_STEP_CYCLES: u64 = 2048;
+
+    fn powered() -> (Apu, u16) {
+        let mut apu = Apu::new();
+        apu.write(0xff26, 0x80);
+        (apu, 0)
+    }
+
+    #[test]
+    fn length_counter_disables_channel() {
+        let (mut apu, mut div) = powered();
+        apu.write(0xff12, 0xf0);
+        apu.write(0xff11, 0x3e);
+        apu.write(0xff14, 0xc0);
+        assert_eq!(apu.read(0xff26), 0xf1);
+        // 第 0、2 步各计数一次
+        run(&mut apu, &mut div, FRAME_STEP_CYCLES * 2);
+        assert!(apu.square1.enabled);
+        run(&mut apu, &mut div, FRAME_STEP_CYCLES);
+        assert_eq!(apu.read(0xff26), 0xf0);
+    }
+
+    #[test]
+    fn envelope_steps_on_frame_seven() {
+        let (mut apu, mut div) = powered();
+        apu.write(0xff17, 0xa1);
+        apu.write(0xff19, 0x80);
+        assert_eq!(apu.square2.envelope.volume, 10);
+        run(&mut apu, &mut div, FRAME_STEP_CYCLES * 7);
+        assert_eq!(apu.square2.envelope.volume, 10);
+        run(&mut apu, &mut div, FRAME_STEP_CYCLES);
+        assert_eq!(apu.square2.envelope.volume, 9);
+        run(&mut apu, &mut div, FRAME_STEP_CYCLES * 8);
+        assert_eq!(apu.square2.envelope.volume, 8);
+    }
+
+    #[test]
+    fn dac_off_disables_channel() {
+        let (mut apu, _) = powered();
+        apu.write(0xff21, 0xf0);
+        apu.write(0xff23, 0x80);
+        assert_eq!(apu.read(0xff26) & 0x08, 0x08);
+        apu.write(0xff21, 0x00);
+        assert_eq!(apu.read(0xff26) & 0x08, 0x00);
+        assert_eq!(apu.channel_outputs()[3], 0.0);
+    }
+
+    #[test]
+    fn disabled_channel_records_silence() {
+        let (mut apu, mut div) = powered();
+        apu.write(0xff12, 0xf0);
+        run(&mut apu, &mut div, 10);
+        // 未触发的通道 DAC 输出 -1.0，分通道录音记为 0
+        assert_eq!(apu.channel_outputs()[0], -1.0);
+        assert_eq!(apu.acc_channels[0], 0.0);
+    }
+
+    #[test]
+    fn power_off_clears_registers() {
+        let (mut apu, _) = powered();
+        apu.write(0xff24, 0x77);
+        apu.write(0xff30, 0x12);
+        apu.write(0xff26, 0x00);
+        assert_eq!(apu.read(0xff24), 0x00);
+        assert_eq!(apu.read(0xff26), 0x70);
+        apu.write(0xff24, 0x77);
+        assert_eq!(apu.read(0xff24), 0x00);
+        assert_eq!(apu.read(0xff30), 0x12);
+    }
+
+    #[test]
+    fn sample_rate_and_buffer_limit() {
+        let (mut apu, mut div) = powered();
+        apu.set_sample_rate(1000);
+        run(&mut apu, &mut div, CYCLES_PER_SECOND);
+        assert_eq!(apu.samples.len(), 2000);
+        run(&mut apu, &mut div, CYCLES_PER_SECOND * 4);
+        let max = 2000 * MAX_BUFFERED_SECONDS;
+        assert!(apu.samples.len() <= max && apu.samples.len() > max - 2000, "{}", apu.samples.len());
+    }
+}
@@ -0,0 +1,81 @@
+use super::{Envelope, Length};
+
+// NR43 低 3 位对应的分频基数（T-cycle）
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+// 噪声通道：15 位（或 7 位）线性反馈移位寄存器
+pub struct Noise {
+    pub enabled: bool,
+    pub length: Length,
+    pub envelope: Envelope,
+    clock_shift: u8,
+    width7: bool,
+    divisor_code: u8,
+    lfsr: u16,
+    timer: u32,
+}
+
+impl Noise {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            length: Length::new(64),
+            envelope: Envelope::default(),
+            clock_shift: 0,
+            width7: false,
+            divisor_code: 0,
+            lfsr: 0x7fff,
+            timer: 0,
+        }
+    }
+
+    // LFSR 每次移位的 T-cycle 数
+    fn period(&self) -> u32 {
+        DIVISORS[self.divisor_code as usize] << self.clock_shift
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+            self.lfsr = (self.lfsr >> 1) | (bit << 14);
+            if self.width7 {self.lfsr = (self.lfsr & !0x40) | (bit << 6);}
+        }
+        self.timer -= cycles;
+    }
+
+    // 数字输出 0-15；DAC 关闭时为 None
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac() {return None;}
+        Some(if self.enabled && self.lfsr & 1 == 0 {self.envelope.volume} else {0})
+    }
+
+    // 写 NR40-NR44（NR40 不存在）
+    pub fn write(&mut self, reg: u8, data: u8) {
+        match reg {
+            0 => {},
+            1 => self.length.load((data & 0x3f) as u16),
+            2 => {
+                self.envelope.write(data);
+                if !self.envelope.dac() {self.enabled = false;}
+            }
+            3 => {
+                self.clock_shift = data >> 4;
+                self.width7 = data & 0x08 != 0;
+                self.divisor_code = data & 0x07;
+            }
+            _ => {
+                self.length.enabled = data & 0x40 != 0;
+                if data & 0x80 != 0 {
+                    self.enabled = self.envelope.dac();
+                    self.length.trigger();
+                    self.timer = self.period();
+                    self.envelope.trigger();
+                    self.lfsr = 0x7fff;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use pyo3::prelude::*;
+
+// 录音文件格式
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    // 16 位有符号整数 PCM 的 WAV 文件
+    Wav,
+    // 无文件头的小端 f32 PCM，声道交错
+    RawFloat,
+}
+
+// WAV 文件头长度
+const WAV_HEADER_SIZE: u64 = 44;
+
+// WAV 文件头中的长度字段为 32 位，文件不能超过 4 GiB
+fn wav_too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::FileTooLarge, "WAV 文件超过 4 GiB 上限，录音停止")
+}
+
+// 一个输出文件
+struct Track {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    channels: u16,
+    frames: u64,
+}
+
+impl Track {
+    fn create(path: PathBuf, format: AudioFormat, channels: u16, sample_rate: u32) -> io::Result<Self> {
+        let mut track = Self { writer: BufWriter::new(File::create(&path)?), path, channels, frames: 0 };
+        if format == AudioFormat::Wav {track.write_wav_header(sample_rate)?;}
+        Ok(track)
+    }
+
+    // frames 帧 16 位 PCM 的字节数
+    fn wav_data_size(&self, frames: u64) -> u64 {
+        frames * self.channels as u64 * 2
+    }
+
+    // WAV 文件头；数据长度在结束录音时回填
+    fn write_wav_header(&mut self, sample_rate: u32) -> io::Result<()> {
+        let data_size = self.wav_data_size(self.frames);
+        let riff_size = u32::try_from(WAV_HEADER_SIZE - 8 + data_size).map_err(|_| wav_too_large())?;
+        let block_align = self.channels * 2;
+        let w = &mut self.writer;
+        w.write_all(b"RIFF")?;
+        w.write_all(&riff_size.to_le_bytes())?;
+        w.write_all(b"WAVEfmt ")?;
+        w.write_all(&16u32.to_le_bytes())?;
+        w.write_all(&1u16.to_le_bytes())?;
+        w.write_all(&self.channels.to_le_bytes())?;
+        w.write_all(&sample_rate.to_le_bytes())?;
+        w.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        w.write_all(&block_align.to_le_bytes())?;
+        w.write_all(&16u16.to_le_bytes())?;
+        w.write_all(b"data")?;
+        w.write_all(&(data_size as u32).to_le_bytes())
+    }
+
+    // 写一帧；WAV 再写一帧就超过 4 GiB 时返回错误，已写的部分仍是完整的 WAV 文件
+    fn write(&mut self, format: AudioFormat, samples: &[f32]) -> io::Result<()> {
+        if format == AudioFormat::Wav && WAV_HEADER_SIZE - 8 + self.wav_data_size(self.frames + 1) > u32::MAX as u64 {
+            return Err(wav_too_large());
+        }
+        for &sample in samples {
+            match format {
+                AudioFormat::Wav => self.writer.write_all(&((sample.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())?,
+                AudioFormat::RawFloat => self.writer.write_all(&sample.to_le_bytes())?,
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self, format: AudioFormat, sample_rate: u32) -> io::Result<()> {
+        if format == AudioFormat::Wav {
+            self.writer.seek(SeekFrom::Start(0))?;
+            self.write_wav_header(sample_rate)?;
+            self.writer.seek(SeekFrom::End(0))?;
+        }
+        self.writer.flush()
+    }
+}
+
+// 分通道录音的文件名：out.wav -> out.ch1.wav
+fn channel_path(path: &Path, channel: usize) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".ch{channel}"));
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    path.with_file_name(name)
+}
+
+// 把 APU 输出的采样写入文件：混音后的立体声，以及可选的四个单声道分通道文件（DAC 原始输出，不经声道选择与主音量）
+pub struct Recorder {
+    format: AudioFormat,
+    sample_rate: u32,
+    mix: Track,
+    channels: Vec<Track>,
+    finished: bool,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, format: AudioFormat, sample_rate: u32, per_channel: bool) -> io::Result<Self> {
+        let mix = Track::create(path.to_path_buf(), format, 2, sample_rate)?;
+        let channels = if per_channel {
+            (1..=4).map(|n| Track::create(channel_path(path, n), format, 1, sample_rate)).collect::<io::Result<_>>()?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { format, sample_rate, mix, channels, finished: false })
+    }
+
+    pub fn write(&mut self, stereo: [f32; 2], channels: [f32; 4]) -> io::Result<()> {
+        self.mix.write(self.format, &stereo)?;
+        for (track, sample) in self.channels.iter_mut().zip(channels) {
+            track.write(self.format, &[sample])?;
+        }
+        Ok(())
+    }
+
+    // 回填 WAV 文件头并写出缓冲，返回写入的全部文件
+    pub fn finish(&mut self) -> io::Result<Vec<PathBuf>> {
+        self.finished = true;
+        self.mix.finish(self.format, self.sample_rate)?;
+        for track in &mut self.channels {
+            track.finish(self.format, self.sample_rate)?;
+        }
+        Ok(std::iter::once(&self.mix).chain(&self.channels).map(|track| track.path.clone()).collect())
+    }
+}
+
+// 未调用 stop_audio_recording 就销毁时也补全文件头
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if !self.finished {let _ = self.finish();}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_stops_at_4_gib() {
+        let path = std::env::temp_dir().join(format!("simu83-recorder-{}.wav", std::process::id()));
+        let mut track = Track::create(path.clone(), AudioFormat::Wav, 2, 48000).unwrap();
+        // 假装已经写了接近上限的帧数，RIFF 长度 36 + 帧数 * 4 不能超过 u32
+        let max_frames = (u32::MAX as u64 - 36) / 4;
+        track.frames = max_frames - 1;
+        track.write(AudioFormat::Wav, &[0.0, 0.0]).unwrap();
+        let err = track.write(AudioFormat::Wav, &[0.0, 0.0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+        track.finish(AudioFormat::Wav, 48000).unwrap();
+        let header = std::fs::read(&path).unwrap();
+        let field = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap()) as u64;
+        assert_eq!((field(4), field(40)), (36 + max_frames * 4, max_frames * 4));
+        // 超过上限的帧数不会在文件头中回绕
+        track.frames = max_frames + 1;
+        assert_eq!(track.finish(AudioFormat::Wav, 48000).unwrap_err().kind(), io::ErrorKind::FileTooLarge);
+        drop(track);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use super::{Envelope, Length};
+
+// 四种占空比的 8 步波形
+const DUTY: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+// 方波通道；通道 1 另有频率扫描（NR10）
+pub struct Square {
+    pub enabled: bool,
+    pub length: Length,
+    pub envelope: Envelope,
+    duty: u8,
+    duty_pos: u8,
+    freq: u16,
+    timer: u32,
+    has_sweep: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_timer: u8,
+    sweep_enabled: bool,
+    shadow_freq: u16,
+}
+
+impl Square {
+    pub fn new(has_sweep: bool) -> Self {
+        Self {
+            enabled: false,
+            length: Length::new(64),
+            envelope: Envelope::default(),
+            duty: 0,
+            duty_pos: 0,
+            freq: 0,
+            timer: 0,
+            has_sweep,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_timer: 0,
+            sweep_enabled: false,
+            shadow_freq: 0,
+        }
+    }
+
+    // 占空比每一步的 T-cycle 数
+    fn period(&self) -> u32 {
+        (2048 - self.freq as u32) * 4
+    }
+
+    pub fn tick(&mut self, cycles: u32) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.duty_pos = (self.duty_pos + 1) % 8;
+        }
+        self.timer -= cycles;
+    }
+
+    // 数字输出 0-15；DAC 关闭时为 None
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac() {return None;}
+        let high = DUTY[self.duty as usize] >> (7 - self.duty_pos) & 1 != 0;
+        Some(if self.enabled && high {self.envelope.volume} else {0})
+    }
+
+    // 扫描后的新频率；超过 2047 时关闭通道
+    fn sweep_calc(&mut self) -> u16 {
+        let delta = self.shadow_freq >> self.sweep_shift;
+        let freq = if self.sweep_negate {self.shadow_freq - delta} else {self.shadow_freq + delta};
+        if freq > 2047 {self.enabled = false;}
+        freq
+    }
+
+    // 帧序列器第 2、6 步
+    pub fn clock_sweep(&mut self) {
+        if self.sweep_timer > 0 {self.sweep_timer -= 1;}
+        if self.sweep_timer != 0 {return;}
+        self.sweep_timer = if self.sweep_period == 0 {8} else {self.sweep_period};
+        if !self.sweep_enabled || self.sweep_period == 0 {return;}
+        let freq = self.sweep_calc();
+        if freq <= 2047 && self.sweep_shift != 0 {
+            self.shadow_freq = freq;
+            self.freq = freq;
+            self.sweep_calc();
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac();
+        self.length.trigger();
+        self.timer = self.period();
+        self.envelope.trigger();
+        if self.has_sweep {
+            self.shadow_freq = self.freq;
+            self.sweep_timer = if self.sweep_period == 0 {8} else {self.sweep_period};
+            self.sweep_enabled = self.sweep_period != 0 || self.sweep_shift != 0;
+            if self.sweep_shift != 0 {self.sweep_calc();}
+        }
+    }
+
+    // 写 NRx0-NRx4
+    pub fn write(&mut self, reg: u8, data: u8) {
+        match reg {
+            0 => {
+                self.sweep_period = (data >> 4) & 0x07;
+                self.sweep_negate = data & 0x08 != 0;
+                self.sweep_shift = data & 0x07;
+            }
+            1 => {
+                self.duty = data >> 6;
+                self.length.load((data & 0x3f) as u16);
+            }
+            2 => {
+                self.envelope.write(data);
+                if !self.envelope.dac() {self.enabled = false;}
+            }
+            3 => self.freq = (self.freq & 0x700) | data as u16,
+            _ => {
+                self.freq = (self.freq & 0xff) | (((data & 0x07) as u16) << 8);
+                self.length.enabled = data & 0x40 != 0;
+                if data & 0x80 != 0 {self.trigger();}
+            }
+        }
+    }
+}
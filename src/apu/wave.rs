@@ -0,0 +1,71 @@
+use super::Length;
+
+// 波形通道：按 NR33/NR34 的频率依次播放波形 RAM 中的 32 个 4 位采样
+pub struct Wave {
+    pub enabled: bool,
+    pub length: Length,
+    dac: bool,
+    volume_code: u8,
+    freq: u16,
+    timer: u32,
+    pos: u8,
+    sample: u8,
+}
+
+impl Wave {
+    pub fn new() -> Self {
+        Self { enabled: false, length: Length::new(256), dac: false, volume_code: 0, freq: 0, timer: 0, pos: 0, sample: 0 }
+    }
+
+    // 每个采样的 T-cycle 数
+    fn period(&self) -> u32 {
+        (2048 - self.freq as u32) * 2
+    }
+
+    pub fn tick(&mut self, cycles: u32, wave_ram: &[u8; 0x10]) {
+        let mut cycles = cycles;
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.pos = (self.pos + 1) % 32;
+            let byte = wave_ram[self.pos as usize / 2];
+            self.sample = if self.pos.is_multiple_of(2) {byte >> 4} else {byte & 0x0f};
+        }
+        self.timer -= cycles;
+    }
+
+    // 数字输出 0-15（NR32 音量：静音、100%、50%、25%）；DAC 关闭时为 None
+    pub fn output(&self) -> Option<u8> {
+        if !self.dac {return None;}
+        if !self.enabled {return Some(0);}
+        Some(match self.volume_code {
+            0 => 0,
+            1 => self.sample,
+            2 => self.sample >> 1,
+            _ => self.sample >> 2,
+        })
+    }
+
+    // 写 NR30-NR34
+    pub fn write(&mut self, reg: u8, data: u8) {
+        match reg {
+            0 => {
+                self.dac = data & 0x80 != 0;
+                if !self.dac {self.enabled = false;}
+            }
+            1 => self.length.load(data as u16),
+            2 => self.volume_code = (data >> 5) & 0x03,
+            3 => self.freq = (self.freq & 0x700) | data as u16,
+            _ => {
+                self.freq = (self.freq & 0xff) | (((data & 0x07) as u16) << 8);
+                self.length.enabled = data & 0x40 != 0;
+                if data & 0x80 != 0 {
+                    self.enabled = self.dac;
+                    self.length.trigger();
+                    self.timer = self.period();
+                    self.pos = 0;
+                }
+            }
+        }
+    }
+}
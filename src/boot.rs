@@ -0,0 +1,69 @@
+use pyo3::prelude::*;
+
+// 硬件型号
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    #[pyo3(name = "DMG")] Dmg,
+    #[pyo3(name = "MGB")] Mgb,
+    #[pyo3(name = "SGB")] Sgb,
+    #[pyo3(name = "CGB")] Cgb,
+}
+
+// Boot ROM 大小：DMG/MGB/SGB 为 256 字节，CGB 为 2304 字节（0x0100-0x01FF 留给卡带头部）
+pub const BOOT_ROM_SIZE: usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+// boot ROM 结束时内部分频计数器的值（DIV 读出 0xCF，与实际运行 DMG boot ROM 一致）
+pub const POST_BOOT_DIV: u16 = 0xcf44;
+
+impl Model {
+    // 按卡带头部 0x0143 选择型号：bit7 置位（支持或仅限 CGB）时为 CGB
+    pub fn from_cgb_flag(cgb_flag: u8) -> Self {
+        if cgb_flag & 0x80 != 0 {Model::Cgb} else {Model::Dmg}
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Model::Dmg => "DMG",
+            Model::Mgb => "MGB",
+            Model::Sgb => "SGB",
+            Model::Cgb => "CGB",
+        }
+    }
+
+    pub fn boot_rom_size(self) -> usize {
+        if self == Model::Cgb {CGB_BOOT_ROM_SIZE} else {BOOT_ROM_SIZE}
+    }
+
+    // 各型号 boot ROM 结束时留下的 AF、BC、DE、HL；
+    // DMG/MGB 的 H、C flag 在头部校验和非 0 时置位
+    pub fn post_boot_registers(self, header_checksum: u8) -> [u16; 4] {
+        let hc = if header_checksum == 0 {0x00} else {0x30};
+        match self {
+            Model::Dmg => [0x0180 | hc, 0x0013, 0x00d8, 0x014d],
+            Model::Mgb => [0xff80 | hc, 0x0013, 0x00d8, 0x014d],
+            Model::Sgb => [0x0100, 0x0014, 0x0000, 0xc060],
+            Model::Cgb => [0x1180, 0x0000, 0xff56, 0x000d],
+        }
+    }
+
+    // 各型号 boot ROM 结束时的 IO 寄存器；NRx4 去掉 bit7，写入时不触发通道
+    pub fn post_boot_io(self) -> Vec<(u16, u8)> {
+        let sc = if self == Model::Cgb {0x7f} else {0x7e};
+        let nr52 = if self == Model::Sgb {0xf0} else {0xf1};
+        vec![
+            (0xff00, 0xcf), (0xff01, 0x00), (0xff02, sc),
+            (0xff05, 0x00), (0xff06, 0x00), (0xff07, 0xf8), (0xff0f, 0xe1),
+            (0xff26, nr52),
+            (0xff10, 0x80), (0xff11, 0xbf), (0xff12, 0xf3), (0xff13, 0xff), (0xff14, 0x3f),
+            (0xff16, 0x3f), (0xff17, 0x00), (0xff18, 0xff), (0xff19, 0x3f),
+            (0xff1a, 0x7f), (0xff1b, 0xff), (0xff1c, 0x9f), (0xff1d, 0xff), (0xff1e, 0x3f),
+            (0xff20, 0xff), (0xff21, 0x00), (0xff22, 0x00), (0xff23, 0x3f),
+            (0xff24, 0x77), (0xff25, 0xf3),
+            (0xff40, 0x91), (0xff41, 0x85), (0xff42, 0x00), (0xff43, 0x00), (0xff44, 0x00), (0xff45, 0x00),
+            (0xff47, 0xfc), (0xff48, 0xff), (0xff49, 0xff), (0xff4a, 0x00), (0xff4b, 0x00),
+            (0xffff, 0x00),
+        ]
+    }
+}
@@ -0,0 +1,475 @@
+use crate::apu::Apu;
+use crate::boot::CGB_BOOT_ROM_SIZE;
+use crate::cartridge::Cartridge;
+use crate::interrupt::Interrupt;
+use crate::joypad::Joypad;
+use crate::ppu::Ppu;
+use crate::serial::Serial;
+use crate::timer::Timer;
+
+// OAM DMA：从 source 起的 160 字节每个 M-cycle 复制一个到 OAM，写入 0xFF46 后延迟一个 M-cycle 开始
+pub struct Dma {
+    source: u16,
+    index: u8,
+    delay: u8,
+}
+
+// 0xFF46 写入后传输的字节数
+const DMA_LENGTH: u8 = 0xa0;
+
+// STOP 切换 CPU 速度时 CPU 暂停的 M-cycle 数
+const SPEED_SWITCH_CYCLES: u64 = 2050;
+
+// CGB VRAM DMA（0xFF51-0xFF55）：以 16 字节为一块，从 source 复制到当前 VRAM bank 的 dest；
+// 通用 DMA 在写入 HDMA5 时一次复制完，HBlank DMA 在每个可见行的 HBlank 复制一块
+#[derive(Default)]
+pub struct Hdma {
+    source: u16,
+    dest: u16,
+    // 剩余块数
+    blocks: u8,
+    // HBlank DMA 进行中
+    active: bool,
+}
+
+impl Hdma {
+    // HDMA1-HDMA4：源地址低 4 位与目标地址高 3 位、低 4 位被忽略
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xff51 => self.source = (self.source & 0x00ff) | ((data as u16) << 8),
+            0xff52 => self.source = (self.source & 0xff00) | (data & 0xf0) as u16,
+            0xff53 => self.dest = (self.dest & 0x00ff) | (((data & 0x1f) as u16) << 8),
+            _      => self.dest = (self.dest & 0xff00) | (data & 0xf0) as u16,
+        }
+    }
+
+    // HDMA5：进行中时 bit7 为 0、低 7 位为剩余块数减 1；结束或被取消时 bit7 为 1
+    fn control(&self) -> u8 {
+        (self.blocks.wrapping_sub(1) & 0x7f) | if self.active {0x00} else {0x80}
+    }
+}
+
+// 内存总线：取指与数据访问统一经过此处，按 Game Boy 的地址空间划分
+//
+// 0x0000-0x7FFF  卡带 ROM（boot ROM 映射期间 0x0000-0x00FF 被覆盖，CGB 另覆盖 0x0200-0x08FF）
+// 0x8000-0x9FFF  VRAM（CGB 由 VBK 选择 bank 0/1）
+// 0xA000-0xBFFF  卡带外部 RAM
+// 0xC000-0xDFFF  WRAM（CGB 的 0xD000-0xDFFF 由 SVBK 选择 bank 1-7）
+// 0xE000-0xFDFF  Echo RAM（镜像 0xC000-0xDDFF）
+// 0xFE00-0xFE9F  OAM
+// 0xFEA0-0xFEFF  禁用区域（读 0，写忽略）
+// 0xFF00-0xFF7F  IO 寄存器（0xFF00 为按键，0xFF01-0xFF02 为串口，0xFF04-0xFF07 为定时器，0xFF0F 为 IF，0xFF10-0xFF3F 为声音，0xFF40-0xFF4B 为 LCD，0xFF46 为 OAM DMA，写 0xFF50 解除 boot ROM 映射；
+//                CGB 模式另有 0xFF4C KEY0（仅 boot ROM 映射期间可写）、0xFF4D KEY1、0xFF4F VBK、0xFF51-0xFF55 VRAM DMA、0xFF56 RP、0xFF68-0xFF6C 调色板与 OPRI、0xFF70 SVBK）
+// 0xFF80-0xFFFE  HRAM
+// 0xFFFF         IE 寄存器
+pub struct Bus {
+    pub cart: Cartridge,
+    pub boot_rom: Option<Vec<u8>>,
+    pub wram: [u8; 0x8000],
+    pub io: [u8; 0x80],
+    pub joypad: Joypad,
+    pub serial: Serial,
+    pub timer: Timer,
+    pub ppu: Ppu,
+    pub apu: Apu,
+    pub hram: [u8; 0x7f],
+    pub int_flag: u8,
+    pub ie: u8,
+    pub dma: Option<Dma>,
+    // 0xFF46 最近写入的值，以及 DMA 最近复制的字节（传输期间 CPU 读 HRAM 以外的地址得到该字节）
+    pub dma_reg: u8,
+    dma_byte: u8,
+    // CGB 模式：KEY1 的切换准备位与当前是否倍速、SVBK、VRAM DMA、RP
+    pub cgb: bool,
+    pub double_speed: bool,
+    key1: u8,
+    svbk: u8,
+    hdma: Hdma,
+    rp: u8,
+    // 倍速模式下 RTC 尚未计入的半个 M-cycle
+    half_cycle: bool,
+    // VRAM DMA 与速度切换让 CPU 暂停的 M-cycle 数，由 CPU 在指令结束后补上
+    pub stall: u64,
+    // CPU 处于 HALT：HBlank DMA 暂停，唤醒后在之后的 HBlank 继续
+    pub halted: bool,
+}
+
+impl Bus {
+    pub fn new(cart: Cartridge) -> Self {
+        Self {
+            cart,
+            boot_rom: None,
+            wram: [0; 0x8000],
+            io: [0; 0x80],
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            timer: Timer::new(),
+            ppu: Ppu::new(),
+            apu: Apu::new(),
+            hram: [0; 0x7f],
+            int_flag: 0,
+            ie: 0,
+            dma: None,
+            dma_reg: 0,
+            dma_byte: 0xff,
+            cgb: false,
+            double_speed: false,
+            key1: 0,
+            svbk: 0,
+            hdma: Hdma::default(),
+            rp: 0,
+            half_cycle: false,
+            stall: 0,
+            halted: false,
+        }
+    }
+
+    // 切换 CGB 模式；进入 DMG 模式时 VRAM 回到 bank 0，WRAM 回到 bank 1
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+        self.ppu.cgb = cgb;
+        self.serial.cgb = cgb;
+        if !cgb {
+            self.ppu.vbk = 0;
+            self.svbk = 1;
+        }
+    }
+
+    // STOP 指令：只有 CGB 模式下 KEY1 bit0 置位时才切换 CPU 速度，同时复位 DIV，CPU 暂停约 2050 个 M-cycle
+    pub fn switch_speed(&mut self) {
+        if !self.cgb || self.key1 & 0x01 == 0 {return;}
+        self.double_speed = !self.double_speed;
+        self.key1 = 0;
+        self.timer.write(0xff04, 0);
+        self.stall += SPEED_SWITCH_CYCLES;
+    }
+
+    // WRAM 中的下标：0xC000/0xE000 起的 4KB 为 bank 0，其后 4KB 为 SVBK 选择的 bank（写 0 视为 1）
+    fn wram_index(&self, addr: u16) -> usize {
+        let offset = (addr & 0x0fff) as usize;
+        if addr & 0x1000 == 0 {return offset;}
+        let bank = if self.cgb {(self.svbk as usize).max(1)} else {1};
+        bank * 0x1000 + offset
+    }
+
+    // VRAM 中的下标
+    fn vram_index(&self, addr: u16) -> usize {
+        self.ppu.vbk as usize * 0x2000 + (addr & 0x1fff) as usize
+    }
+
+    // boot ROM 映射期间覆盖卡带 ROM 的字节
+    fn boot_rom_byte(&self, addr: u16) -> Option<u8> {
+        let boot = self.boot_rom.as_ref()?;
+        match addr {
+            0x0000..=0x00ff => Some(boot[addr as usize]),
+            0x0200..=0x08ff if boot.len() == CGB_BOOT_ROM_SIZE => Some(boot[addr as usize]),
+            _ => None,
+        }
+    }
+
+    // OAM DMA 正在复制时 CPU 只能访问 0xFF00 以上的 IO 寄存器、HRAM 与 IE
+    fn dma_conflict(&self, addr: u16) -> bool {
+        matches!(self.dma, Some(Dma { delay: 0, .. })) && addr < 0xff00
+    }
+
+    // CPU 读一个字节
+    pub fn read(&self, addr: u16) -> u8 {
+        if self.dma_conflict(addr) {self.dma_byte} else {self.peek(addr)}
+    }
+
+    // CPU 写一个字节
+    pub fn write(&mut self, addr: u16, data: u8) {
+        if !self.dma_conflict(addr) {self.poke(addr, data);}
+    }
+
+    // 不经 DMA 冲突直接读一个字节
+    fn peek(&self, addr: u16) -> u8 {
+        if let Some(data) = self.boot_rom_byte(addr) {return data;}
+        match addr {
+            0x0000..=0x7fff => self.cart.read_rom(addr),
+            0x8000..=0x9fff => self.ppu.vram[self.vram_index(addr)],
+            0xa000..=0xbfff => self.cart.read_ram(addr),
+            0xc000..=0xfdff => self.wram[self.wram_index(addr)],
+            0xfe00..=0xfe9f => self.ppu.oam[(addr - 0xfe00) as usize],
+            0xfea0..=0xfeff => 0x00,
+            0xff00 => self.joypad.read(),
+            0xff01..=0xff02 => self.serial.read(addr),
+            0xff04..=0xff07 => self.timer.read(addr),
+            0xff0f => self.int_flag | 0xe0,
+            0xff10..=0xff3f => self.apu.read(addr),
+            0xff46 => self.dma_reg,
+            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.read(addr),
+            0xff50 => 0xff,
+            0xff4d if self.cgb => 0x7e | ((self.double_speed as u8) << 7) | self.key1,
+            0xff4f | 0xff68..=0xff6c if self.cgb => self.ppu.read(addr),
+            0xff51..=0xff54 if self.cgb => 0xff,
+            0xff55 if self.cgb => self.hdma.control(),
+            // RP bit1 为 1 表示没有收到红外信号
+            0xff56 if self.cgb => self.rp | 0x3e,
+            0xff70 if self.cgb => 0xf8 | self.svbk,
+            0xff75 if self.cgb => self.io[0x75] | 0x8f,
+            0xff03..=0xff7f => self.io[(addr - 0xff00) as usize],
+            0xff80..=0xfffe => self.hram[(addr - 0xff80) as usize],
+            0xffff => self.ie,
+        }
+    }
+
+    // 不经 DMA 冲突直接写一个字节
+    fn poke(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x0000..=0x7fff => self.cart.write_rom(addr, data),
+            0x8000..=0x9fff => self.ppu.vram[self.vram_index(addr)] = data,
+            0xa000..=0xbfff => self.cart.write_ram(addr, data),
+            0xc000..=0xfdff => self.wram[self.wram_index(addr)] = data,
+            0xfe00..=0xfe9f => self.ppu.oam[(addr - 0xfe00) as usize] = data,
+            0xfea0..=0xfeff => {},
+            0xff00 => if self.joypad.write(data) {self.request_interrupt(Interrupt::Joypad);},
+            0xff01..=0xff02 => self.serial.write(addr, data),
+            0xff04..=0xff07 => self.timer.write(addr, data),
+            0xff0f => self.int_flag = data & 0x1f,
+            0xff10..=0xff3f => self.apu.write(addr, data),
+            0xff46 => {
+                self.dma_reg = data;
+                self.dma = Some(Dma { source: (data as u16) << 8, index: 0, delay: 1 });
+            }
+            0xff40..=0xff45 | 0xff47..=0xff4b => self.ppu.write(addr, data),
+            0xff50 => if data != 0 {self.boot_rom = None;},
+            // CGB boot ROM 在运行 DMG 卡带前写 KEY0 bit2，进入 DMG 兼容模式
+            0xff4c if self.cgb && self.boot_rom.is_some() => if data & 0x04 != 0 {self.set_cgb(false);},
+            0xff4d if self.cgb => self.key1 = data & 0x01,
+            0xff4f | 0xff68..=0xff6c if self.cgb => self.ppu.write(addr, data),
+            0xff51..=0xff54 if self.cgb => self.hdma.write(addr, data),
+            0xff55 if self.cgb => self.hdma_start(data),
+            0xff56 if self.cgb => self.rp = data & 0xc1,
+            0xff70 if self.cgb => self.svbk = data & 0x07,
+            0xff03..=0xff7f => self.io[(addr - 0xff00) as usize] = data,
+            0xff80..=0xfffe => self.hram[(addr - 0xff80) as usize] = data,
+            0xffff => self.ie = data,
+        }
+    }
+
+    // 按 M-cycle 推进总线上的硬件；倍速模式下定时器、串口与 OAM DMA 随 CPU 加快，PPU、APU 与 RTC 不变
+    pub fn tick(&mut self, cycles: u64) {
+        let rtc_cycles = if self.double_speed {
+            let total = cycles + self.half_cycle as u64;
+            self.half_cycle = !total.is_multiple_of(2);
+            total / 2
+        } else {
+            cycles
+        };
+        self.cart.tick(cycles, rtc_cycles);
+        let dots = if self.double_speed {2} else {4};
+        for _ in 0..cycles {
+            self.dma_step();
+            if self.timer.step() {self.request_interrupt(Interrupt::Timer);}
+            if self.serial.step() {self.request_interrupt(Interrupt::Serial);}
+            self.apu.step(self.timer.div, self.double_speed);
+            self.int_flag |= self.ppu.step(dots);
+            if std::mem::take(&mut self.ppu.hblank) && self.hdma.active && !self.halted {self.hdma_block();}
+        }
+    }
+
+    // 写 HDMA5：bit7 为 0 时执行通用 DMA（HBlank DMA 进行中则改为取消），为 1 时开始 HBlank DMA；
+    // LCD 关闭时 HBlank DMA 立即复制第一块
+    fn hdma_start(&mut self, data: u8) {
+        if self.hdma.active && data & 0x80 == 0 {
+            self.hdma.active = false;
+            return;
+        }
+        self.hdma.blocks = (data & 0x7f) + 1;
+        if data & 0x80 == 0 {
+            while self.hdma.blocks > 0 {self.hdma_block();}
+        } else {
+            self.hdma.active = true;
+            if !self.ppu.lcd_on() {self.hdma_block();}
+        }
+    }
+
+    // 复制一块 16 字节，CPU 暂停 8 个 M-cycle（倍速模式下 16 个）
+    fn hdma_block(&mut self) {
+        for _ in 0..16 {
+            let data = self.peek(self.hdma.source);
+            self.ppu.vram[self.ppu.vbk as usize * 0x2000 + self.hdma.dest as usize] = data;
+            self.hdma.source = self.hdma.source.wrapping_add(1);
+            self.hdma.dest = (self.hdma.dest + 1) & 0x1fff;
+        }
+        self.hdma.blocks -= 1;
+        if self.hdma.blocks == 0 {self.hdma.active = false;}
+        self.stall += if self.double_speed {16} else {8};
+    }
+
+    // OAM DMA 推进一个 M-cycle；0xE000 以上的源地址映射到 WRAM
+    fn dma_step(&mut self) {
+        let Some(dma) = &mut self.dma else {return;};
+        if dma.delay > 0 {
+            dma.delay -= 1;
+            return;
+        }
+        let (source, index) = (dma.source, dma.index);
+        dma.index += 1;
+        if dma.index == DMA_LENGTH {self.dma = None;}
+        let addr = source + index as u16;
+        let data = self.peek(if addr >= 0xe000 {addr - 0x2000} else {addr});
+        self.ppu.oam[index as usize] = data;
+        self.dma_byte = data;
+    }
+
+    // 设置按下的按键（位序同 Button）
+    pub fn set_buttons(&mut self, pressed: u8) {
+        if self.joypad.set_pressed(pressed) {self.request_interrupt(Interrupt::Joypad);}
+    }
+
+    // 置位 IF 中的中断请求
+    pub fn request_interrupt(&mut self, int: Interrupt) {
+        self.int_flag |= int.bit();
+    }
+
+    // 已使能且已请求的中断（IE & IF）
+    pub fn pending_interrupts(&self) -> u8 {
+        self.ie & self.int_flag & 0x1f
+    }
+
+    // 整个地址空间的快照
+    pub fn snapshot(&self) -> [u8; 65536] {
+        let mut data = [0; 65536];
+        for (addr, cell) in data.iter_mut().enumerate() {
+            *cell = self.peek(addr as u16);
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cgb_bus() -> Bus {
+        let mut bus = Bus::new(Cartridge::new(vec![0; 0x8000]));
+        bus.set_cgb(true);
+        bus
+    }
+
+    #[test]
+    fn svbk_selects_wram_bank() {
+        let mut bus = cgb_bus();
+        bus.write(0xff70, 0x00);
+        bus.write(0xd000, 0x11);
+        // SVBK 写 0 映射到 bank 1
+        bus.write(0xff70, 0x01);
+        assert_eq!(bus.read(0xd000), 0x11);
+        bus.write(0xff70, 0x07);
+        bus.write(0xd000, 0x77);
+        bus.write(0xc000, 0x55);
+        assert_eq!((bus.wram[0x1000], bus.wram[0x7000], bus.wram[0x0000]), (0x11, 0x77, 0x55));
+        assert_eq!(bus.read(0xf000), 0x77);
+        assert_eq!(bus.read(0xff70), 0xff);
+    }
+
+    #[test]
+    fn dmg_mode_ignores_cgb_registers() {
+        let mut bus = Bus::new(Cartridge::new(vec![0; 0x8000]));
+        bus.write(0xff70, 0x03);
+        bus.write(0xd000, 0x11);
+        assert_eq!(bus.wram[0x1000], 0x11);
+        bus.write(0xff4f, 0x01);
+        bus.write(0x8000, 0x22);
+        assert_eq!((bus.ppu.vram[0x0000], bus.ppu.vram[0x2000]), (0x22, 0x00));
+        assert_eq!(bus.read(0xff75), 0x00);
+    }
+
+    #[test]
+    fn vbk_selects_vram_bank() {
+        let mut bus = cgb_bus();
+        bus.write(0xff4f, 0x01);
+        bus.write(0x9800, 0x08);
+        assert_eq!(bus.read(0xff4f), 0xff);
+        bus.write(0xff4f, 0x00);
+        assert_eq!(bus.read(0x9800), 0x00);
+        assert_eq!(bus.ppu.vram[0x3800], 0x08);
+    }
+
+    #[test]
+    fn oam_dma_leaves_io_and_hram_accessible() {
+        let mut bus = Bus::new(Cartridge::new(vec![0; 0x8000]));
+        bus.write(0xc000, 0x12);
+        bus.write(0xc001, 0x34);
+        bus.write(0xff46, 0xc0);
+        bus.tick(2);
+        // 其余地址读到 DMA 刚复制的字节，写入被忽略
+        assert_eq!(bus.read(0xc001), 0x12);
+        bus.write(0xc001, 0x56);
+        bus.write(0xff47, 0xe4);
+        bus.write(0xff80, 0x77);
+        bus.write(0xffff, 0x1f);
+        assert_eq!((bus.read(0xff47), bus.read(0xff80), bus.read(0xffff)), (0xe4, 0x77, 0x1f));
+        bus.tick(DMA_LENGTH as u64);
+        assert!(bus.dma.is_none());
+        assert_eq!((bus.read(0xc001), bus.ppu.oam[1]), (0x34, 0x34));
+    }
+
+    #[test]
+    fn general_dma_copies_at_once() {
+        let mut bus = cgb_bus();
+        for i in 0..0x20 {bus.write(0xc100 + i, i as u8);}
+        bus.write(0xff51, 0xc1);
+        bus.write(0xff52, 0x0f);
+        bus.write(0xff53, 0x81);
+        bus.write(0xff54, 0x00);
+        bus.write(0xff55, 0x01);
+        assert_eq!(&bus.ppu.vram[0x100..0x120], &bus.wram[0x100..0x120]);
+        assert_eq!(bus.read(0xff55), 0xff);
+        assert_eq!(bus.stall, 16);
+    }
+
+    #[test]
+    fn hblank_dma_pauses_while_halted() {
+        let mut bus = cgb_bus();
+        bus.write(0xc000, 0xab);
+        bus.write(0xff51, 0xc0);
+        bus.write(0xff52, 0x00);
+        bus.write(0xff53, 0x80);
+        bus.write(0xff54, 0x00);
+        bus.write(0xff40, 0x80);
+        bus.write(0xff55, 0x81);
+        // 一行 114 个 M-cycle
+        bus.halted = true;
+        bus.tick(114 * 2);
+        assert_eq!((bus.ppu.vram[0], bus.read(0xff55)), (0x00, 0x01));
+        bus.halted = false;
+        bus.tick(114);
+        assert_eq!((bus.ppu.vram[0], bus.read(0xff55)), (0xab, 0x00));
+        bus.tick(114);
+        assert_eq!(bus.read(0xff55), 0xff);
+    }
+
+    #[test]
+    fn speed_switch_needs_armed_key1() {
+        let mut bus = cgb_bus();
+        bus.switch_speed();
+        assert!(!bus.double_speed);
+        assert_eq!(bus.stall, 0);
+        bus.write(0xff4d, 0x01);
+        assert_eq!(bus.read(0xff4d), 0x7f);
+        bus.switch_speed();
+        assert!(bus.double_speed);
+        assert_eq!(bus.read(0xff4d), 0xfe);
+        assert_eq!(bus.stall, SPEED_SWITCH_CYCLES);
+    }
+
+    #[test]
+    fn key0_enters_dmg_compatibility() {
+        let mut bus = cgb_bus();
+        bus.boot_rom = Some(vec![0; CGB_BOOT_ROM_SIZE]);
+        // boot ROM 切换过的 bank 在进入兼容模式时复位
+        bus.write(0xff4f, 0x01);
+        bus.write(0xff70, 0x02);
+        bus.write(0xff4c, 0x04);
+        assert!(!bus.cgb && !bus.ppu.cgb);
+        assert_eq!(bus.ppu.vbk, 0);
+        bus.write(0x8000, 0x44);
+        bus.write(0xff70, 0x03);
+        bus.write(0xd000, 0x33);
+        assert_eq!((bus.ppu.vram[0x0000], bus.ppu.vram[0x2000]), (0x44, 0x00));
+        assert_eq!((bus.wram[0x1000], bus.wram[0x2000], bus.wram[0x3000]), (0x33, 0x00, 0x00));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::header::{CartridgeHeader, MbcKind};
+use crate::mbc::{Mbc, mbc1::Mbc1, mbc2::{Mbc2, MBC2_RAM_SIZE}, mbc3::{Mbc3, RTC_FOOTER_SIZE, RTC_FOOTER_SIZE_SHORT}, mbc5::Mbc5};
+
+// 卡带：0x0000-0x7FFF 的 ROM 与 0xA000-0xBFFF 的外部 RAM，由 MBC 负责 bank 切换
+pub struct Cartridge {
+    pub header: CartridgeHeader,
+    pub rom: Vec<u8>,
+    pub ram: Vec<u8>,
+    pub mbc: Mbc,
+}
+
+impl Cartridge {
+    pub fn new(rom: Vec<u8>) -> Self {
+        let header = CartridgeHeader::parse(&rom);
+        let mbc = match header.mbc_kind {
+            MbcKind::Mbc1 => Mbc::Mbc1(Mbc1::new(Mbc1::detect_multicart(&rom))),
+            MbcKind::Mbc2 => Mbc::Mbc2(Mbc2::new()),
+            MbcKind::Mbc3 => Mbc::Mbc3(Mbc3::new(header.has_timer)),
+            MbcKind::Mbc5 => Mbc::Mbc5(Mbc5::new(header.has_rumble)),
+            _             => Mbc::None,
+        };
+        let ram_size = match &mbc {
+            Mbc::Mbc2(_) => MBC2_RAM_SIZE,
+            _ if header.has_ram => header.ram_size,
+            _ => 0,
+        };
+        Self { header, rom, ram: vec![0; ram_size], mbc }
+    }
+
+    // 按 16 KiB bank 号与 bank 内偏移取 ROM 字节（bank 号超出 ROM 大小时回绕）
+    fn rom_byte(&self, bank: usize, offset: u16) -> u8 {
+        if self.rom.is_empty() {return 0xff;}
+        self.rom[(bank * 0x4000 + offset as usize) % self.rom.len()]
+    }
+
+    // 按 8 KiB bank 号与 bank 内偏移计算外部 RAM 下标
+    fn ram_index(&self, bank: usize, offset: u16) -> Option<usize> {
+        if self.ram.is_empty() {return None;}
+        Some((bank * 0x2000 + offset as usize) % self.ram.len())
+    }
+
+    // 读 ROM 区（超出 ROM 长度的地址视为开路总线）
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        match &self.mbc {
+            Mbc::None => self.rom.get(addr as usize).copied().unwrap_or(0xff),
+            Mbc::Mbc1(mbc1) => match addr {
+                0x0000..=0x3fff => self.rom_byte(mbc1.rom_bank_lo(), addr),
+                _               => self.rom_byte(mbc1.rom_bank_hi(), addr - 0x4000),
+            },
+            Mbc::Mbc2(mbc2) => match addr {
+                0x0000..=0x3fff => self.rom_byte(0, addr),
+                _               => self.rom_byte(mbc2.rom_bank(), addr - 0x4000),
+            },
+            Mbc::Mbc3(mbc3) => match addr {
+                0x0000..=0x3fff => self.rom_byte(0, addr),
+                _               => self.rom_byte(mbc3.rom_bank(), addr - 0x4000),
+            },
+            Mbc::Mbc5(mbc5) => match addr {
+                0x0000..=0x3fff => self.rom_byte(0, addr),
+                _               => self.rom_byte(mbc5.rom_bank(), addr - 0x4000),
+            },
+        }
+    }
+
+    // 写 ROM 区：写入 MBC 寄存器（无 MBC 的卡带忽略写入）
+    pub fn write_rom(&mut self, addr: u16, data: u8) {
+        match &mut self.mbc {
+            Mbc::None => {},
+            Mbc::Mbc1(mbc1) => mbc1.write_register(addr, data),
+            Mbc::Mbc2(mbc2) => mbc2.write_register(addr, data),
+            Mbc::Mbc3(mbc3) => mbc3.write_register(addr, data),
+            Mbc::Mbc5(mbc5) => mbc5.write_register(addr, data),
+        }
+    }
+
+    // 当前映射到 0xA000-0xBFFF 的外部 RAM 下标；RAM 未使能或不存在时为 None
+    fn ram_addr(&self, addr: u16) -> Option<usize> {
+        let offset = addr - 0xa000;
+        match &self.mbc {
+            Mbc::None => self.ram_index(0, offset),
+            Mbc::Mbc1(mbc1) => if mbc1.ram_enable {self.ram_index(mbc1.ram_bank(), offset)} else {None},
+            Mbc::Mbc2(mbc2) => mbc2.ram_index(addr),
+            Mbc::Mbc3(mbc3) => match mbc3.ram_bank() {
+                Some(bank) if mbc3.ram_enable => self.ram_index(bank, offset),
+                _ => None,
+            },
+            Mbc::Mbc5(mbc5) => if mbc5.ram_enable {self.ram_index(mbc5.ram_bank(), offset)} else {None},
+        }
+    }
+
+    // 读外部 RAM（MBC3 选中 RTC 寄存器时读锁存值，MBC2 的高 4 位为开路总线）
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        if let Mbc::Mbc3(mbc3) = &self.mbc
+            && let (true, Some(select), Some(rtc)) = (mbc3.ram_enable, mbc3.rtc_select(), &mbc3.rtc) {
+            return rtc.read(select);
+        }
+        match self.ram_addr(addr) {
+            Some(index) if matches!(self.mbc, Mbc::Mbc2(_)) => self.ram[index] | 0xf0,
+            Some(index) => self.ram[index],
+            None => 0xff,
+        }
+    }
+
+    // 写外部 RAM（MBC3 选中 RTC 寄存器时写入时钟）
+    pub fn write_ram(&mut self, addr: u16, data: u8) {
+        if let Mbc::Mbc3(mbc3) = &mut self.mbc
+            && let (true, Some(select)) = (mbc3.ram_enable, mbc3.rtc_select()) {
+            if let Some(rtc) = &mut mbc3.rtc {rtc.write(select, data);}
+            return;
+        }
+        if let Some(index) = self.ram_addr(addr) {
+            self.ram[index] = if matches!(self.mbc, Mbc::Mbc2(_)) {data & 0x0f} else {data};
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u64, rtc_cycles: u64) {
+        self.mbc.tick(cycles, rtc_cycles);
+    }
+
+    // 导出 .sav：外部 RAM 原样输出，带 RTC 的 MBC3 在末尾追加 RTC 数据
+    pub fn export_save(&mut self) -> Vec<u8> {
+        let mut save = self.ram.clone();
+        if let Mbc::Mbc3(Mbc3 { rtc: Some(rtc), .. }) = &mut self.mbc {
+            save.extend(rtc.footer());
+        }
+        save
+    }
+
+    // 载入 .sav；长度必须等于外部 RAM 大小，带 RTC 时可再附带 44/48 字节的 RTC 数据
+    pub fn load_save(&mut self, save: &[u8]) -> Result<(), String> {
+        let ram_size = self.ram.len();
+        let footer = match (&mut self.mbc, save.len().checked_sub(ram_size)) {
+            (_, Some(0)) => None,
+            (Mbc::Mbc3(Mbc3 { rtc: Some(rtc), .. }), Some(RTC_FOOTER_SIZE | RTC_FOOTER_SIZE_SHORT)) => Some(rtc),
+            _ => return Err(format!("存档长度 {} 字节与卡带的外部 RAM（{} 字节）不符", save.len(), ram_size)),
+        };
+        if let Some(rtc) = footer {rtc.load_footer(&save[ram_size..]);}
+        self.ram.copy_from_slice(&save[..ram_size]);
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use pyo3::{create_exception, exceptions::PyException, prelude::*, PyTypeInfo};
+
+// 异常层次：全部派生自 Simu83Error，出错的值挂在异常的属性上
+create_exception!(simu83, Simu83Error, PyException, "simu83 所有异常的基类");
+create_exception!(simu83, InvalidRegisterError, Simu83Error, "非法的寄存器位置（属性 value）");
+create_exception!(simu83, InvalidFlagError, Simu83Error, "非法的 flag 位（属性 value）");
+create_exception!(simu83, InvalidBitError, Simu83Error, "非法的 bit 位置（属性 value）");
+create_exception!(simu83, RomOutOfRangeError, Simu83Error, "ROM 索引越界（属性 addr）");
+create_exception!(simu83, IllegalOpcodeError, Simu83Error, "执行了未定义的操作码（属性 pc、opcode）");
+
+// 构造异常并把出错的值写入属性
+fn error_with_attrs<T: PyTypeInfo>(msg: String, attrs: &[(&str, u32)]) -> PyErr {
+    let err = PyErr::new::<T, _>(msg);
+    Python::with_gil(|py| {
+        let value = err.value(py);
+        for (name, data) in attrs {
+            let _ = value.setattr(*name, *data);
+        }
+    });
+    err
+}
+
+pub fn invalid_register(r8pos: u8) -> PyErr {
+    error_with_attrs::<InvalidRegisterError>(format!("非法的寄存器位置：{r8pos}"), &[("value", r8pos as u32)])
+}
+
+pub fn invalid_flag(flag_bit: u8) -> PyErr {
+    error_with_attrs::<InvalidFlagError>(format!("非法的 flag 位：{flag_bit}"), &[("value", flag_bit as u32)])
+}
+
+pub fn invalid_bit(b3: u8) -> PyErr {
+    error_with_attrs::<InvalidBitError>(format!("非法的 bit 位置：{b3}"), &[("value", b3 as u32)])
+}
+
+pub fn rom_out_of_range(addr: u16) -> PyErr {
+    error_with_attrs::<RomOutOfRangeError>(format!("ROM 索引越界！您索引的地址：{addr}"), &[("addr", addr as u32)])
+}
+
+pub fn illegal_opcode(pc: u16, opcode: u8) -> PyErr {
+    error_with_attrs::<IllegalOpcodeError>(
+        format!("非法的操作码 0x{opcode:02x}，位于 0x{pc:04x}"),
+        &[("pc", pc as u32), ("opcode", opcode as u32)],
+    )
+}
+
+// 参数校验
+pub fn check_r8pos(r8pos: u8) -> PyResult<u8> {
+    if r8pos <= 7 {Ok(r8pos)} else {Err(invalid_register(r8pos))}
+}
+
+pub fn check_r16pos(r16pos: u8) -> PyResult<u8> {
+    if r16pos <= 3 {Ok(r16pos)} else {Err(invalid_register(r16pos))}
+}
+
+pub fn check_flag_bit(flag_bit: u8) -> PyResult<u8> {
+    if (4..=7).contains(&flag_bit) {Ok(flag_bit)} else {Err(invalid_flag(flag_bit))}
+}
+
+pub fn check_b3(b3: u8) -> PyResult<u8> {
+    if b3 <= 7 {Ok(b3)} else {Err(invalid_bit(b3))}
+}
+
+// 在模块中注册全部异常
+pub fn register(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("Simu83Error", py.get_type::<Simu83Error>())?;
+    m.add("InvalidRegisterError", py.get_type::<InvalidRegisterError>())?;
+    m.add("InvalidFlagError", py.get_type::<InvalidFlagError>())?;
+    m.add("InvalidBitError", py.get_type::<InvalidBitError>())?;
+    m.add("RomOutOfRangeError", py.get_type::<RomOutOfRangeError>())?;
+    m.add("IllegalOpcodeError", py.get_type::<IllegalOpcodeError>())?;
+    Ok(())
+}
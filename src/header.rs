@@ -0,0 +1,288 @@
+use pyo3::prelude::*;
+
+// 卡带类型字节（0x147）中的 MBC 种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MbcKind {None, Mbc1, Mbc2, Mmm01, Mbc3, Mbc5, Mbc6, Mbc7, PocketCamera, Tama5, HuC3, HuC1, Unknown}
+
+impl MbcKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MbcKind::None         => "ROM",
+            MbcKind::Mbc1         => "MBC1",
+            MbcKind::Mbc2         => "MBC2",
+            MbcKind::Mmm01        => "MMM01",
+            MbcKind::Mbc3         => "MBC3",
+            MbcKind::Mbc5         => "MBC5",
+            MbcKind::Mbc6         => "MBC6",
+            MbcKind::Mbc7         => "MBC7",
+            MbcKind::PocketCamera => "POCKET CAMERA",
+            MbcKind::Tama5        => "TAMA5",
+            MbcKind::HuC3         => "HuC3",
+            MbcKind::HuC1         => "HuC1",
+            MbcKind::Unknown      => "UNKNOWN",
+        }
+    }
+}
+
+// 卡带类型字节解码为 (MBC, RAM, 电池, 时钟, 震动)
+fn decode_cartridge_type(code: u8) -> (MbcKind, bool, bool, bool, bool) {
+    match code {
+        0x00 => (MbcKind::None,         false, false, false, false),
+        0x01 => (MbcKind::Mbc1,         false, false, false, false),
+        0x02 => (MbcKind::Mbc1,         true,  false, false, false),
+        0x03 => (MbcKind::Mbc1,         true,  true,  false, false),
+        0x05 => (MbcKind::Mbc2,         false, false, false, false),
+        0x06 => (MbcKind::Mbc2,         false, true,  false, false),
+        0x08 => (MbcKind::None,         true,  false, false, false),
+        0x09 => (MbcKind::None,         true,  true,  false, false),
+        0x0B => (MbcKind::Mmm01,        false, false, false, false),
+        0x0C => (MbcKind::Mmm01,        true,  false, false, false),
+        0x0D => (MbcKind::Mmm01,        true,  true,  false, false),
+        0x0F => (MbcKind::Mbc3,         false, true,  true,  false),
+        0x10 => (MbcKind::Mbc3,         true,  true,  true,  false),
+        0x11 => (MbcKind::Mbc3,         false, false, false, false),
+        0x12 => (MbcKind::Mbc3,         true,  false, false, false),
+        0x13 => (MbcKind::Mbc3,         true,  true,  false, false),
+        0x19 => (MbcKind::Mbc5,         false, false, false, false),
+        0x1A => (MbcKind::Mbc5,         true,  false, false, false),
+        0x1B => (MbcKind::Mbc5,         true,  true,  false, false),
+        0x1C => (MbcKind::Mbc5,         false, false, false, true ),
+        0x1D => (MbcKind::Mbc5,         true,  false, false, true ),
+        0x1E => (MbcKind::Mbc5,         true,  true,  false, true ),
+        0x20 => (MbcKind::Mbc6,         true,  true,  false, false),
+        0x22 => (MbcKind::Mbc7,         true,  true,  false, true ),
+        0xFC => (MbcKind::PocketCamera, true,  true,  false, false),
+        0xFD => (MbcKind::Tama5,        true,  true,  true,  false),
+        0xFE => (MbcKind::HuC3,         true,  true,  true,  false),
+        0xFF => (MbcKind::HuC1,         true,  true,  false, false),
+        _    => (MbcKind::Unknown,      false, false, false, false),
+    }
+}
+
+// 0x0100-0x014F 卡带头部
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct CartridgeHeader {
+    #[pyo3(get)] pub title: String,
+    #[pyo3(get)] pub manufacturer_code: String,
+    #[pyo3(get)] pub cgb_flag: u8,
+    #[pyo3(get)] pub sgb_flag: bool,
+    #[pyo3(get)] pub new_licensee_code: String,
+    #[pyo3(get)] pub old_licensee_code: u8,
+    #[pyo3(get)] pub cartridge_type: u8,
+    #[pyo3(get)] pub has_ram: bool,
+    #[pyo3(get)] pub has_battery: bool,
+    #[pyo3(get)] pub has_timer: bool,
+    #[pyo3(get)] pub has_rumble: bool,
+    #[pyo3(get)] pub rom_size_code: u8,
+    #[pyo3(get)] pub rom_size: usize,
+    #[pyo3(get)] pub ram_size_code: u8,
+    #[pyo3(get)] pub ram_size: usize,
+    #[pyo3(get)] pub destination: u8,
+    #[pyo3(get)] pub version: u8,
+    #[pyo3(get)] pub header_checksum: u8,
+    #[pyo3(get)] pub computed_header_checksum: u8,
+    #[pyo3(get)] pub global_checksum: u16,
+    #[pyo3(get)] pub computed_global_checksum: u16,
+    #[pyo3(get)] pub warnings: Vec<String>,
+    pub mbc_kind: MbcKind,
+}
+
+impl CartridgeHeader {
+    // 解析头部；头部字段异常或校验和不符时只记录警告，不视为失败。
+    // 不足 0x150 字节的 ROM（如测试用的小程序）视为没有头部，缺失的字节按 0 处理且不产生警告
+    pub fn parse(rom: &[u8]) -> Self {
+        let byte = |addr: usize| rom.get(addr).copied().unwrap_or(0);
+        let ascii = |range: std::ops::Range<usize>| -> String {
+            range.map(byte)
+                 .take_while(|&b| b != 0)
+                 .filter(|b| b.is_ascii_graphic() || *b == b' ')
+                 .map(char::from)
+                 .collect()
+        };
+        let complete = rom.len() >= 0x150;
+        let mut warnings = Vec::new();
+
+        let cgb_flag = byte(0x143);
+        // CGB 卡带的标题区缩短为 11 字节，其后 4 字节为厂商代码
+        let (title, manufacturer_code) = if cgb_flag & 0x80 != 0 {
+            (ascii(0x134..0x13f), ascii(0x13f..0x143))
+        } else {
+            (ascii(0x134..0x144), String::new())
+        };
+
+        let cartridge_type = byte(0x147);
+        let (mbc_kind, has_ram, has_battery, has_timer, has_rumble) = decode_cartridge_type(cartridge_type);
+        if complete && mbc_kind == MbcKind::Unknown {
+            warnings.push(format!("未知的卡带类型：0x{cartridge_type:02x}"));
+        }
+
+        let rom_size_code = byte(0x148);
+        let rom_size = if rom_size_code <= 8 {0x8000 << rom_size_code} else {
+            if complete {warnings.push(format!("未知的 ROM 大小代码：0x{rom_size_code:02x}"));}
+            rom.len()
+        };
+        if rom_size != rom.len() && rom.len() >= 0x8000 {
+            warnings.push(format!("头部声明的 ROM 大小为 {rom_size} 字节，实际为 {} 字节", rom.len()));
+        }
+
+        let ram_size_code = byte(0x149);
+        let ram_size = match ram_size_code {
+            0x00 => 0,
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            _ => {
+                if complete {warnings.push(format!("未知的 RAM 大小代码：0x{ram_size_code:02x}"));}
+                0
+            }
+        };
+
+        let header_checksum = byte(0x14d);
+        let computed_header_checksum = (0x134..=0x14c)
+            .fold(0u8, |x, addr| x.wrapping_sub(byte(addr)).wrapping_sub(1));
+        if complete && header_checksum != computed_header_checksum {
+            warnings.push(format!("头部校验和不符：记录 0x{header_checksum:02x}，计算 0x{computed_header_checksum:02x}"));
+        }
+
+        let global_checksum = ((byte(0x14e) as u16) << 8) + byte(0x14f) as u16;
+        let computed_global_checksum = rom.iter()
+            .enumerate()
+            .filter(|(addr, _)| *addr != 0x14e && *addr != 0x14f)
+            .fold(0u16, |sum, (_, &b)| sum.wrapping_add(b as u16));
+        if complete && global_checksum != computed_global_checksum {
+            warnings.push(format!("全局校验和不符：记录 0x{global_checksum:04x}，计算 0x{computed_global_checksum:04x}"));
+        }
+
+        Self {
+            title,
+            manufacturer_code,
+            cgb_flag,
+            sgb_flag: byte(0x146) == 0x03,
+            new_licensee_code: ascii(0x144..0x146),
+            old_licensee_code: byte(0x14b),
+            cartridge_type,
+            has_ram,
+            has_battery,
+            has_timer,
+            has_rumble,
+            rom_size_code,
+            rom_size,
+            ram_size_code,
+            ram_size,
+            destination: byte(0x14a),
+            version: byte(0x14c),
+            header_checksum,
+            computed_header_checksum,
+            global_checksum,
+            computed_global_checksum,
+            warnings,
+            mbc_kind,
+        }
+    }
+}
+
+#[pymethods]
+impl CartridgeHeader {
+    /// 从 ROM 数据解析卡带头部
+    #[staticmethod]
+    #[pyo3(name = "parse", text_signature = "(data)")]
+    fn py_parse(data: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self::parse(&crate::rom_bytes(data)?))
+    }
+    // MBC 种类名称
+    #[getter]
+    fn mbc(&self) -> &'static str {
+        self.mbc_kind.name()
+    }
+    // ROM bank 数（16 KiB 为一个 bank）
+    #[getter]
+    fn rom_banks(&self) -> usize {
+        self.rom_size / 0x4000
+    }
+    // 外部 RAM bank 数（8 KiB 为一个 bank）
+    #[getter]
+    fn ram_banks(&self) -> usize {
+        self.ram_size.div_ceil(0x2000)
+    }
+    // CGB 支持：0x80 兼容，0xC0 仅限 CGB
+    #[getter]
+    fn cgb_support(&self) -> &'static str {
+        match self.cgb_flag {
+            0xc0 => "only",
+            0x80 => "supported",
+            _ => "none",
+        }
+    }
+    // 是否为日本版
+    #[getter]
+    fn is_japanese(&self) -> bool {
+        self.destination == 0x00
+    }
+    #[getter]
+    fn header_checksum_ok(&self) -> bool {
+        self.header_checksum == self.computed_header_checksum
+    }
+    #[getter]
+    fn global_checksum_ok(&self) -> bool {
+        self.global_checksum == self.computed_global_checksum
+    }
+    fn __repr__(&self) -> String {
+        format!("CartridgeHeader(title={:?}, mbc={:?}, rom_size={}, ram_size={}, cgb_flag=0x{:02x})",
+                self.title, self.mbc_kind.name(), self.rom_size, self.ram_size, self.cgb_flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 32 KiB 的 MBC1 卡带，标题为 TEST，校验和正确
+    fn rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x138].copy_from_slice(b"TEST");
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+        rom[0x14d] = (0x134..=0x14c).fold(0u8, |x, addr| x.wrapping_sub(rom[addr]).wrapping_sub(1));
+        let sum = rom.iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+        rom[0x14e] = (sum >> 8) as u8;
+        rom[0x14f] = sum as u8;
+        rom
+    }
+
+    #[test]
+    fn parse_fields() {
+        let header = CartridgeHeader::parse(&rom());
+        assert_eq!(header.title, "TEST");
+        assert_eq!(header.mbc_kind, MbcKind::Mbc1);
+        assert!(header.has_ram && header.has_battery && !header.has_timer);
+        assert_eq!((header.rom_size, header.ram_size), (0x8000, 0x2000));
+        assert!(header.warnings.is_empty(), "{:?}", header.warnings);
+    }
+
+    #[test]
+    fn cgb_title_is_shortened() {
+        let mut rom = rom();
+        rom[0x134..0x143].copy_from_slice(b"COLORGAMEABCD\0\0");
+        rom[0x143] = 0x80;
+        let header = CartridgeHeader::parse(&rom);
+        assert_eq!((header.title.as_str(), header.manufacturer_code.as_str()), ("COLORGAMEAB", "CD"));
+    }
+
+    #[test]
+    fn bad_checksums_warn() {
+        let mut rom = rom();
+        rom[0x14d] ^= 1;
+        let header = CartridgeHeader::parse(&rom);
+        assert_eq!(header.warnings.len(), 2);
+    }
+
+    #[test]
+    fn short_rom_has_no_header() {
+        let header = CartridgeHeader::parse(&[0x3c, 0x76]);
+        assert_eq!(header.mbc_kind, MbcKind::None);
+        assert!(header.warnings.is_empty());
+    }
+}
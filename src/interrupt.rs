@@ -0,0 +1,27 @@
+use pyo3::prelude::*;
+
+// 中断源，按优先级从高到低排列
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {VBlank, Stat, Timer, Serial, Joypad}
+
+impl Interrupt {
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank, Interrupt::Stat, Interrupt::Timer, Interrupt::Serial, Interrupt::Joypad,
+    ];
+
+    // 在 IE/IF 中对应的位
+    pub fn bit(self) -> u8 {
+        1 << (self as u8)
+    }
+
+    // 中断向量地址
+    pub fn vector(self) -> u16 {
+        0x40 + 8 * (self as u16)
+    }
+
+    // 取 IE & IF 中优先级最高的中断
+    pub fn highest(pending: u8) -> Option<Interrupt> {
+        Self::ALL.into_iter().find(|int| pending & int.bit() != 0)
+    }
+}
@@ -0,0 +1,82 @@
+use pyo3::prelude::*;
+
+// 按键；set_buttons 的位掩码中每个按键占 1 << 序号
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {Right, Left, Up, Down, A, B, Select, Start}
+
+impl Button {
+    pub fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// 0xFF00 P1/JOYP：bit4 为 0 时选中方向键，bit5 为 0 时选中功能键；
+// 低 4 位读出选中按键的状态，按下为 0。低 4 位任一位由 1 变 0 时请求 Joypad 中断
+pub struct Joypad {
+    select: u8,
+    // 按下的按键，位序同 Button
+    pub pressed: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self { select: 0x00, pressed: 0 }
+    }
+
+    fn lines(&self) -> u8 {
+        let mut lines = 0x0f;
+        if self.select & 0x10 == 0 {lines &= !self.pressed & 0x0f;}
+        if self.select & 0x20 == 0 {lines &= !(self.pressed >> 4) & 0x0f;}
+        lines
+    }
+
+    pub fn read(&self) -> u8 {
+        0xc0 | self.select | self.lines()
+    }
+
+    // 写选择位，返回是否请求 Joypad 中断
+    pub fn write(&mut self, data: u8) -> bool {
+        let before = self.lines();
+        self.select = data & 0x30;
+        before & !self.lines() != 0
+    }
+
+    // 设置按下的按键，返回是否请求 Joypad 中断
+    pub fn set_pressed(&mut self, pressed: u8) -> bool {
+        let before = self.lines();
+        self.pressed = pressed;
+        before & !self.lines() != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_lines_pick_button_group() {
+        let mut joypad = Joypad::new();
+        joypad.set_pressed(Button::Right.bit() | Button::Up.bit() | Button::A.bit() | Button::Start.bit());
+        // (选择位, 低 4 位)：方向键与功能键都选中时两组相与
+        for (select, lines) in [(0x30, 0x0f), (0x20, 0x0a), (0x10, 0x06), (0x00, 0x02)] {
+            joypad.write(select);
+            assert_eq!(joypad.read(), 0xc0 | select | lines, "P1={select:#04x}");
+        }
+    }
+
+    #[test]
+    fn press_on_selected_line_requests_interrupt() {
+        let mut joypad = Joypad::new();
+        joypad.write(0x20);
+        // 功能键未被选中，按 A 不请求中断
+        assert!(!joypad.set_pressed(Button::A.bit()));
+        assert!(joypad.set_pressed(Button::A.bit() | Button::Down.bit()));
+        // 已按住的键保持按下、松开按键都不请求中断
+        assert!(!joypad.set_pressed(Button::Down.bit()));
+        assert!(!joypad.set_pressed(0));
+        // 按住 A 时改为选中功能键，对应的线被拉低，同样请求中断
+        joypad.set_pressed(Button::A.bit());
+        assert!(joypad.write(0x10));
+    }
+}
@@ -1,1602 +1,2384 @@
-use pyo3::{prelude::*,};
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-
-// 定义数据处理对象
-struct Register {a: u8, b: u8, c: u8, d: u8, e: u8, f: u8, h: u8, l: u8}
-
-#[pyclass]
-// 构建 Game Boy SoC 结构（含全部寄存器与内存）
-struct SoC {reg: Register, pc: u16, sp: u16, cyc: u128, ime: bool, ram: [u8; 65536], rom: Vec<u8>}
-
-// 全部的实例化方法
-#[pymethods]
-impl SoC {
-    /// 初始化 SoC 对象，含全部寄存器与内存
-    #[new]
-    #[pyo3(text_signature = "(rom_data)")]
-    fn new(_py: Python, rom_data: Vec<PyObject>) -> PyResult<Self> {
-        let mut rom_data_u8: Vec<u8> = rom_data
-            .into_iter()
-            .map(|item| item.extract::<u8>(_py))
-            .collect::<PyResult<_>>()?;
-
-        rom_data_u8.extend(&[0, 0]);
-
-        Ok(Self {
-            reg: Register { a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0 },
-            pc: 0,
-            sp: 0,
-            cyc: 0,
-            ime: false,
-            ram: [0; 65536],
-            rom: rom_data_u8,
-        })
-    }
-
-    // 取寄存器 r8
-    #[pyo3(text_signature = "(r8pos)")]
-    fn get_r8(&self, r8pos: u8) -> u8 {
-        match r8pos {
-            0 => self.reg.b,
-            1 => self.reg.c,
-            2 => self.reg.d,
-            3 => self.reg.e,
-            4 => self.reg.h,
-            5 => self.reg.l,
-            6 => self.reg.f,
-            7 => self.reg.a,
-            _ => panic!("非法的寄存器位置：{r8pos}")
-        }
-    }
-    // 写寄存器 r8
-    #[pyo3(text_signature = "(r8pos, new_r8)")]
-    fn set_r8(&mut self, r8pos: u8, new_r8: u8) {
-        match r8pos {
-            0 => {self.reg.b = new_r8},
-            1 => {self.reg.c = new_r8},
-            2 => {self.reg.d = new_r8},
-            3 => {self.reg.e = new_r8},
-            4 => {self.reg.h = new_r8},
-            5 => {self.reg.l = new_r8},
-            6 => {self.reg.f = new_r8},
-            7 => {self.reg.a = new_r8},
-            _ => panic!("非法的寄存器位置: {}", r8pos),
-        }
-    }
-    // 取寄存器 r16
-    #[pyo3(text_signature = "(r16pos)")]
-    fn get_r16(&self, r16pos: u8) -> u16 {
-        match r16pos {
-            0 => ((self.reg.b as u16) << 8) + (self.reg.c as u16),
-            1 => ((self.reg.d as u16) << 8) + (self.reg.e as u16),
-            2 => ((self.reg.h as u16) << 8) + (self.reg.l as u16),
-            3 => ((self.reg.a as u16) << 8) + (self.reg.f as u16),
-            _ => panic!("非法的寄存器位置: {}", r16pos),
-        }
-    }
-    // 写寄存器 r16
-    #[pyo3(text_signature = "(r16pos, new_r16)")]
-    fn set_r16(&mut self, r16pos: u8, new_r16: u16) {
-        match r16pos {
-            0 => {
-                self.reg.b = (new_r16 >> 8) as u8;
-                self.reg.c = (new_r16 & 0xff) as u8;
-            },
-            1 => {
-                self.reg.d = (new_r16 >> 8) as u8;
-                self.reg.e = (new_r16 & 0xff) as u8;
-            },
-            2 => {
-                self.reg.h = (new_r16 >> 8) as u8;
-                self.reg.l = (new_r16 & 0xff) as u8;
-            },
-            3 => {
-                self.reg.a = (new_r16 >> 8) as u8;
-                self.reg.f = (new_r16 & 0xff) as u8;
-            },
-            _ => panic!("非法的寄存器位置: {}", r16pos),
-        }
-    }
-    // 自增 r8
-    #[pyo3(text_signature = "(r8pos)")]
-    fn r8_inc(&mut self, r8pos: u8) {
-        match r8pos {
-            0 => self.reg.b += 1,
-            1 => self.reg.c += 1,
-            2 => self.reg.d += 1,
-            3 => self.reg.e += 1,
-            4 => self.reg.h += 1,
-            5 => self.reg.l += 1,
-            6 => self.reg.f += 1,
-            7 => self.reg.a += 1,
-            _ => panic!("非法的寄存器位置: {}", r8pos),
-        }
-    }
-    // 自减 r8
-    #[pyo3(text_signature = "(r8pos)")]
-    fn r8_dec(&mut self, r8pos: u8) {
-        match r8pos {
-            0 => self.reg.b -= 1,
-            1 => self.reg.c -= 1,
-            2 => self.reg.d -= 1,
-            3 => self.reg.e -= 1,
-            4 => self.reg.h -= 1,
-            5 => self.reg.l -= 1,
-            6 => self.reg.f -= 1,
-            7 => self.reg.a -= 1,
-            _ => panic!("非法的寄存器位置: {}", r8pos),
-        }
-    }
-    // 自增 r16
-    #[pyo3(text_signature = "(r16pos)")]
-    fn r16_inc(&mut self, r16pos: u8) {
-        match r16pos {
-               0 => {self.set_r16(0, self.get_r16(0) + 1);},
-               1 => {self.set_r16(1, self.get_r16(1) + 1);},
-               2 => {self.set_r16(2, self.get_r16(2) + 1);},
-               3 => {self.set_r16(3, self.get_r16(3) + 1);},
-               _ => panic!("非法的寄存器位置: {}", r16pos),
-        }
-    }
-    // 自减 r16
-    #[pyo3(text_signature = "(r16pos)")]
-    fn r16_dec(&mut self, r16pos: u8) {
-        match r16pos {
-               0 => {self.set_r16(0, self.get_r16(0) - 1);},
-               1 => {self.set_r16(1, self.get_r16(1) - 1);},
-               2 => {self.set_r16(2, self.get_r16(2) - 1);},
-               3 => {self.set_r16(3, self.get_r16(3) - 1);},
-               _ => panic!("非法的寄存器位置: {}", r16pos),
-        }
-    }
-    // 设置 bit
-    #[pyo3(text_signature = "(r8pos, b3)")]
-    fn r8_set(&mut self, r8pos: u8, b3: u8) {
-        match r8pos {
-            0 => {self.reg.b = self.reg.b | (1 << b3);},
-            1 => {self.reg.c = self.reg.c | (1 << b3);},
-            2 => {self.reg.d = self.reg.d | (1 << b3);},
-            3 => {self.reg.e = self.reg.e | (1 << b3);},
-            4 => {self.reg.h = self.reg.h | (1 << b3);},
-            5 => {self.reg.l = self.reg.l | (1 << b3);},
-            6 => {self.reg.f = self.reg.f | (1 << b3);},
-            7 => {self.reg.a = self.reg.a | (1 << b3);},
-            _ => panic!("非法的寄存器位置: {}", r8pos),
-        }
-    }
-    // 清除 bit
-    #[pyo3(text_signature = "(r8pos, b3)")]
-    fn r8_res(&mut self, r8pos: u8, b3: u8) {
-        match r8pos {
-            0 => {self.reg.b = self.reg.b & (0xff - (1 << b3));},
-            1 => {self.reg.c = self.reg.c & (0xff - (1 << b3));},
-            2 => {self.reg.d = self.reg.d & (0xff - (1 << b3));},
-            3 => {self.reg.e = self.reg.e & (0xff - (1 << b3));},
-            4 => {self.reg.h = self.reg.h & (0xff - (1 << b3));},
-            5 => {self.reg.l = self.reg.l & (0xff - (1 << b3));},
-            6 => {self.reg.f = self.reg.f & (0xff - (1 << b3));},
-            7 => {self.reg.a = self.reg.a & (0xff - (1 << b3));},
-            _ => panic!("非法的寄存器位置: {}", r8pos),
-        }
-    }
-    // 取 flag
-    #[pyo3(text_signature = "(flag_bit)")]
-    fn get_flag(&self, flag_bit: u8) -> u8 {
-        match flag_bit {
-            4 => {(self.reg.f >> 4) & 1},
-            5 => {(self.reg.f >> 5) & 1},
-            6 => {(self.reg.f >> 6) & 1},
-            7 => {(self.reg.f >> 7) & 1},
-            _ => panic!("非法的 flag 位: {}", flag_bit),
-        }
-    }
-    // 设 flag
-    #[pyo3(text_signature = "(flag_bit)")]
-    fn set_flag(&mut self, flag_bit: u8) {
-        match flag_bit {
-            4 => {self.reg.f = self.reg.f | (1 << 4)},
-            5 => {self.reg.f = self.reg.f | (1 << 5)},
-            6 => {self.reg.f = self.reg.f | (1 << 6)},
-            7 => {self.reg.f = self.reg.f | (1 << 7)},
-            _ => panic!("非法的 flag 位: {}", flag_bit),
-        }
-    }
-    // 清 flag
-    #[pyo3(text_signature = "(flag_bit)")]
-    fn res_flag(&mut self, flag_bit: u8) {
-        match flag_bit {
-            4 => self.reg.f = self.reg.f & (0xff - (1 << 4)),
-            5 => self.reg.f = self.reg.f & (0xff - (1 << 5)),
-            6 => self.reg.f = self.reg.f & (0xff - (1 << 6)),
-            7 => self.reg.f = self.reg.f & (0xff - (1 << 7)),
-            _ => panic!("非法的 flag 位: {}", flag_bit),
-        }
-    }
-    // 取 SP
-    fn get_sp(&self) -> u16 {
-        self.sp
-    }
-    // 设 SP
-    #[pyo3(text_signature = "(new_sp)")]
-    fn set_sp(&mut self, new_sp: u16) {
-        self.sp = new_sp;
-    }
-    // 取 PC
-    fn get_pc(&self) -> u16 {
-        self.pc
-    }
-    // 设 PC
-    #[pyo3(text_signature = "(new_pc)")]
-    fn set_pc(&mut self, new_pc: u16) {
-        self.pc = new_pc;
-    }
-    // 增加 PC
-    fn pc_inc(&mut self, n: u16) {
-        self.pc += n;
-    }
-    // 减少 PC
-    fn pc_dec(&mut self, n: u16) {
-        self.pc -= n;
-    }
-    // 取 CYC
-    fn get_cyc(&self) -> u128 {
-        self.cyc
-    }
-    // 增加 CYC
-    fn cyc_inc(&mut self, n: u128) {
-        self.cyc += n;
-    }
-    // 取 RAM
-    #[pyo3(text_signature = "(addr)")]
-    fn ram_read(&self, addr: u16) -> u8 {
-        if let Some(&data) = self.ram.get(addr as usize) {
-            data
-        } else {
-            panic!("内存索引越界！合法范围：0-65535，您索引的地址：{}", addr)
-        }
-    }
-    // 写 RAM
-    #[pyo3(text_signature = "(addr, data)")]
-    fn ram_write(&mut self, addr: u16, data: u8) {
-        if self.ram.get(addr as usize).is_some() {
-            self.ram[addr as usize] = data;
-        } else {
-            panic!("内存索引越界！合法范围：0-65535，您索引的地址：{}", addr)
-        }
-    }
-    // 读取全部 RAM
-    fn ram_data(&self) -> [u8; 65536] {
-        self.ram
-    }
-    // 取 ROM
-    #[pyo3(text_signature = "(addr)")]
-    fn read_rom(&self, addr: u16) -> u8 {
-        if let Some(&data) = self.rom.get(addr as usize) {
-            data
-        } else {
-            panic!("ROM 索引越界！您索引的地址：{}", addr)
-        }
-    }
-    // halt 信号发出
-    fn halt(&self) -> bool {
-        self.read_rom(self.get_pc()) == 0x76
-    }
-    // 启用 IME
-    fn set_ime(&mut self) {
-        self.ime = true;
-    }
-    // 禁止 IME
-    fn res_ime(&mut self) {
-        self.ime = false;
-    }
-    // 智能 flag
-    #[pyo3(text_signature = "(num1, num2, n, u8_mode)")]
-    fn smart_flag(&mut self, num1: u16, num2: u16, n: bool, u8_mode: bool) {
-        match u8_mode {
-            true => {
-                let num1_lo4: u8 = (num1 & 0xf) as u8;
-                let num2_lo4: u8 = (num2 & 0xf) as u8;
-
-                if n == false {
-                    if (num1 + num2) & 0xff == 0 { self.set_flag(7) } else { self.res_flag(7) };
-                    self.res_flag(6);
-                    if num1_lo4 + num2_lo4 > 0xf { self.set_flag(5) } else { self.res_flag(5) };
-                    if num1+ num2 > 0xff { self.set_flag(4) } else { self.res_flag(4) };
-                } else {
-                    if (num1 as i16) - (num2 as i16) == 0 { self.set_flag(7) } else { self.res_flag(7) };
-                    self.set_flag(6);
-                    if (num1_lo4 as i8) - (num2_lo4 as i8) < 0 { self.set_flag(5) } else { self.res_flag(5) };
-                    if (num1 as i16) - (num2 as i16) < 0 { self.set_flag(4) } else { self.res_flag(4) };
-                }
-            },
-            false => {
-                let num1: u32 = num1 as u32;
-                let num2: u32 = num2 as u32;
-                let num1_lo12: u32 = num1 & 0xfff;
-                let num2_lo12: u32 = num2 & 0xfff;
-
-                if n == false {
-                    self.res_flag(6);
-                    if num1_lo12 + num2_lo12 > 0xfff { self.set_flag(5) } else { self.res_flag(5) };
-                    if num1 + num2 > 0xffff { self.set_flag(4) } else { self.res_flag(4) };
-                } else {
-                    self.set_flag(6);
-                    if (num1_lo12 as i32) - (num2_lo12 as i32) < 0 { self.set_flag(5) } else { self.res_flag(5) };
-                    if (num1 as i32) - (num2 as i32) < 0 { self.set_flag(4) } else { self.res_flag(4) };
-                }
-            }
-        }
-    }
-    // 根据区间提供ROM数据
-    #[pyo3(text_signature = "(length)")]
-    fn give_opt_code(&self, length: u8) -> Vec<u8> {
-        return_instruction(self, length)
-    }
-
-    // 查看寄存器状况
-    fn disp(&self) {
-        println!("+----------- GameBoy SM83 Register Display -----------+");
-        println!("|Flag Register: {} - {} - {} - {}               IME: {}|", 
-        if (self.get_flag(7)) == 1 {"Z"} else {"0"},
-        if (self.get_flag(6)) == 1 {"N"} else {"0"},
-        if (self.get_flag(5)) == 1 {"H"} else {"0"},
-        if (self.get_flag(4)) == 1 {"C"} else {"0"},
-        if (self.ime) == true {"TRUE "} else {"FALSE"});
-        println!("|R16:        {:5},      {:5},      {:5},      {:5}|",
-                 self.get_r16(3), self.get_r16(0), self.get_r16(1), self.get_r16(2));
-        println!("|R8_10: {:4}, {:4}, {:4}, {:4}, {:4}, {:4}, {:4}, {:4}|", 
-                 self.reg.a, self.reg.f, self.reg.b, self.reg.c,
-                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
-        println!("|R8_16: 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}|", 
-                 self.reg.a, self.reg.f, self.reg.b, self.reg.c,
-                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
-        println!("|A: {0:08b},  F: {1:08b},  B: {2:08b},  C: {3:08b}|",
-                 self.reg.a, self.reg.f, self.reg.b, self.reg.c);
-        println!("|D: {0:08b},  E: {1:08b},  H: {2:08b},  L: {3:08b}|",
-                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
-        println!("|PC:{:08},  SP:0x{:04x},    INST_CYC:{:16}|",
-                 self.pc, self.sp, self.cyc);
-        println!("+----------ROM Next 3 Bytes [0x{0:02x} 0x{1:02x} 0x{2:02x}]----------+\n",
-                 self.read_rom(self.get_pc()), self.read_rom(self.get_pc()+1), self.read_rom(self.get_pc()+2),)
-    }
-    // 显示寄存器 r8
-    fn disp_r8(&self) {
-        println!("============ GameBoy SM83 R8B Display ============");
-        println!("Flag Register: {} - {} - {} - {}", 
-                 if (self.get_flag(7)) == 1 {"Z"} else {"0"},
-                 if (self.get_flag(6)) == 1 {"N"} else {"0"},
-                 if (self.get_flag(5)) == 1 {"H"} else {"0"},
-                 if (self.get_flag(4)) == 1 {"C"} else {"0"});
-        println!("A: {0:08b}, F: {1:08b}, B: {2:08b}, C: {3:08b}", self.reg.a, self.reg.f, self.reg.b, self.reg.c);
-        println!("D: {0:08b}, E: {1:08b}, H: {2:08b}, L: {3:08b}\n", self.reg.d, self.reg.e, self.reg.h, self.reg.l);
-    }
-
-    // 执行下一个指令
-    fn one_step(&mut self) {process_by_step(self);}
-}
-
-
-// 调配函数
-fn return_instruction(soc: &SoC, length: u8) -> Vec<u8> {
-    let mut opt_code = Vec::new();
-    let start_point = soc.get_pc();
-
-    for i in start_point..(start_point + length as u16) {
-        opt_code.push(soc.read_rom(i));
-    }
-    opt_code
-}
-
-
-fn process_by_step(soc: &mut SoC) {
-    let code = [
-        soc.read_rom(soc.get_pc()) as u16,
-        soc.read_rom(soc.get_pc() + 1) as u16,
-        soc.read_rom(soc.get_pc() + 2) as u16,
-    ];
-    match code[0] as u8 {
-        0x00 => {nop(soc)},
-        0x01 => {ld_r16_n16(soc, R16::BC, b8x2_le(code[1], code[2]))},
-        0x02 => {ld_r16ram_a(soc, R16RAM::BC)},
-        0x03 => {inc_r16(soc, R16::BC)},
-        0x04 => {inc_r8(soc, R8::B)},
-        0x05 => {dec_r8(soc, R8::B)},
-        0x06 => {ld_r8_n8(soc, R8::B, code[1])},
-        0x07 => {rlca(soc)},
-        0x08 => {ld_a16_sp(soc, b8x2_le(code[1], code[2]))},
-        0x09 => {add_hl_r16(soc, R16::BC)},
-        0x0A => {ld_a_r16ram(soc, R16RAM::BC)},
-        0x0B => {dec_r16(soc, R16::BC)},
-        0x0C => {inc_r8(soc, R8::C)},
-        0x0D => {dec_r8(soc, R8::C)},
-        0x0E => {ld_r8_n8(soc, R8::C, code[1])},
-        0x0F => {rrca(soc)},
-        0x10 => {stop(soc)},
-        0x11 => {ld_r16_n16(soc, R16::DE, b8x2_le(code[1], code[2]))},
-        0x12 => {ld_r16ram_a(soc, R16RAM::DE)},
-        0x13 => {inc_r16(soc, R16::DE)},
-        0x14 => {inc_r8(soc, R8::D)},
-        0x15 => {dec_r8(soc, R8::D)},
-        0x16 => {ld_r8_n8(soc, R8::D, code[1])},
-        0x17 => {rla(soc)},
-        0x18 => {jr_e8(soc, code[1] as i8)},
-        0x19 => {add_hl_r16(soc, R16::DE)},
-        0x1A => {ld_a_r16ram(soc, R16RAM::DE)},
-        0x1B => {dec_r16(soc, R16::DE)},
-        0x1C => {inc_r8(soc, R8::E)},
-        0x1D => {dec_r8(soc, R8::E)},
-        0x1E => {ld_r8_n8(soc, R8::E, code[1])},
-        0x1F => {rra(soc)},
-        0x20 => {jr_cond_e8(soc, COND::NZ, code[1] as i8)},
-        0x21 => {ld_r16_n16(soc, R16::HL, b8x2_le(code[1], code[2]))},
-        0x22 => {ld_r16ram_a(soc, R16RAM::HLI)},
-        0x23 => {inc_r16(soc, R16::HL)},
-        0x24 => {inc_r8(soc, R8::H)},
-        0x25 => {dec_r8(soc, R8::H)},
-        0x26 => {ld_r8_n8(soc, R8::H, code[1])},
-        0x27 => {daa(soc)},
-        0x28 => {jr_cond_e8(soc, COND::Z, code[1] as i8)},
-        0x29 => {add_hl_r16(soc, R16::HL)},
-        0x2A => {ld_a_r16ram(soc, R16RAM::HLI)},
-        0x2B => {dec_r16(soc, R16::HL)},
-        0x2C => {inc_r8(soc, R8::L)},
-        0x2D => {dec_r8(soc, R8::L)},
-        0x2E => {ld_r8_n8(soc, R8::L, code[1])},
-        0x2F => {cpl(soc)},
-        0x30 => {jr_cond_e8(soc, COND::NC, code[1] as i8)},
-        0x31 => {ld_r16_n16(soc, R16::SP, b8x2_le(code[1], code[2]))},
-        0x32 => {ld_r16ram_a(soc, R16RAM::HLD)},
-        0x33 => {inc_r16(soc, R16::SP)},
-        0x34 => {inc_r8(soc, R8::HL)},
-        0x35 => {dec_r8(soc, R8::HL)},
-        0x36 => {ld_r8_n8(soc, R8::HL, code[1])},
-        0x37 => {scf(soc)},
-        0x38 => {jr_cond_e8(soc, COND::C, code[1] as i8)},
-        0x39 => {add_hl_r16(soc, R16::SP)},
-        0x3A => {ld_a_r16ram(soc, R16RAM::HLD)},
-        0x3B => {dec_r16(soc, R16::SP)},
-        0x3C => {inc_r8(soc, R8::A)},
-        0x3D => {dec_r8(soc, R8::A)},
-        0x3E => {ld_r8_n8(soc, R8::A, code[1])},
-        0x3F => {ccf(soc)},
-        0x40 => {ld_r8_r8(soc, R8::B, R8::B)},
-        0x41 => {ld_r8_r8(soc, R8::B, R8::C)},
-        0x42 => {ld_r8_r8(soc, R8::B, R8::D)},
-        0x43 => {ld_r8_r8(soc, R8::B, R8::E)},
-        0x44 => {ld_r8_r8(soc, R8::B, R8::H)},
-        0x45 => {ld_r8_r8(soc, R8::B, R8::L)},
-        0x46 => {ld_r8_r8(soc, R8::B, R8::HL)},
-        0x47 => {ld_r8_r8(soc, R8::B, R8::A)},
-        0x48 => {ld_r8_r8(soc, R8::C, R8::B)},
-        0x49 => {ld_r8_r8(soc, R8::C, R8::C)},
-        0x4A => {ld_r8_r8(soc, R8::C, R8::D)},
-        0x4B => {ld_r8_r8(soc, R8::C, R8::E)},
-        0x4C => {ld_r8_r8(soc, R8::C, R8::H)},
-        0x4D => {ld_r8_r8(soc, R8::C, R8::L)},
-        0x4E => {ld_r8_r8(soc, R8::C, R8::HL)},
-        0x4F => {ld_r8_r8(soc, R8::C, R8::A)},
-        0x50 => {ld_r8_r8(soc, R8::D, R8::B)},
-        0x51 => {ld_r8_r8(soc, R8::D, R8::C)},
-        0x52 => {ld_r8_r8(soc, R8::D, R8::D)},
-        0x53 => {ld_r8_r8(soc, R8::D, R8::E)},
-        0x54 => {ld_r8_r8(soc, R8::D, R8::H)},
-        0x55 => {ld_r8_r8(soc, R8::D, R8::L)},
-        0x56 => {ld_r8_r8(soc, R8::D, R8::HL)},
-        0x57 => {ld_r8_r8(soc, R8::D, R8::A)},
-        0x58 => {ld_r8_r8(soc, R8::E, R8::B)},
-        0x59 => {ld_r8_r8(soc, R8::E, R8::C)},
-        0x5A => {ld_r8_r8(soc, R8::E, R8::D)},
-        0x5B => {ld_r8_r8(soc, R8::E, R8::E)},
-        0x5C => {ld_r8_r8(soc, R8::E, R8::H)},
-        0x5D => {ld_r8_r8(soc, R8::E, R8::L)},
-        0x5E => {ld_r8_r8(soc, R8::E, R8::HL)},
-        0x5F => {ld_r8_r8(soc, R8::E, R8::A)},
-        0x60 => {ld_r8_r8(soc, R8::H, R8::B)},
-        0x61 => {ld_r8_r8(soc, R8::H, R8::C)},
-        0x62 => {ld_r8_r8(soc, R8::H, R8::D)},
-        0x63 => {ld_r8_r8(soc, R8::H, R8::E)},
-        0x64 => {ld_r8_r8(soc, R8::H, R8::H)},
-        0x65 => {ld_r8_r8(soc, R8::H, R8::L)},
-        0x66 => {ld_r8_r8(soc, R8::H, R8::HL)},
-        0x67 => {ld_r8_r8(soc, R8::H, R8::A)},
-        0x68 => {ld_r8_r8(soc, R8::L, R8::B)},
-        0x69 => {ld_r8_r8(soc, R8::L, R8::C)},
-        0x6A => {ld_r8_r8(soc, R8::L, R8::D)},
-        0x6B => {ld_r8_r8(soc, R8::L, R8::E)},
-        0x6C => {ld_r8_r8(soc, R8::L, R8::H)},
-        0x6D => {ld_r8_r8(soc, R8::L, R8::L)},
-        0x6E => {ld_r8_r8(soc, R8::L, R8::HL)},
-        0x6F => {ld_r8_r8(soc, R8::L, R8::A)},
-        0x70 => {ld_r8_r8(soc, R8::HL, R8::B)},
-        0x71 => {ld_r8_r8(soc, R8::HL, R8::C)},
-        0x72 => {ld_r8_r8(soc, R8::HL, R8::D)},
-        0x73 => {ld_r8_r8(soc, R8::HL, R8::E)},
-        0x74 => {ld_r8_r8(soc, R8::HL, R8::H)},
-        0x75 => {ld_r8_r8(soc, R8::HL, R8::L)},
-        0x76 => {halt(soc);},
-        0x77 => {ld_r8_r8(soc, R8::HL, R8::A)},
-        0x78 => {ld_r8_r8(soc, R8::A, R8::B)},
-        0x79 => {ld_r8_r8(soc, R8::A, R8::C)},
-        0x7A => {ld_r8_r8(soc, R8::A, R8::D)},
-        0x7B => {ld_r8_r8(soc, R8::A, R8::E)},
-        0x7C => {ld_r8_r8(soc, R8::A, R8::H)},
-        0x7D => {ld_r8_r8(soc, R8::A, R8::L)},
-        0x7E => {ld_r8_r8(soc, R8::A, R8::HL)},
-        0x7F => {ld_r8_r8(soc, R8::A, R8::A)},
-        0x80 => {alu_a_r8(soc, R8::B,  ALU3::ADD)},
-        0x81 => {alu_a_r8(soc, R8::C,  ALU3::ADD)},
-        0x82 => {alu_a_r8(soc, R8::D,  ALU3::ADD)},
-        0x83 => {alu_a_r8(soc, R8::E,  ALU3::ADD)},
-        0x84 => {alu_a_r8(soc, R8::H,  ALU3::ADD)},
-        0x85 => {alu_a_r8(soc, R8::L,  ALU3::ADD)},
-        0x86 => {alu_a_r8(soc, R8::HL, ALU3::ADD)},
-        0x87 => {alu_a_r8(soc, R8::A,  ALU3::ADD)},
-        0x88 => {alu_a_r8(soc, R8::B,  ALU3::ADC)},
-        0x89 => {alu_a_r8(soc, R8::C,  ALU3::ADC)},
-        0x8A => {alu_a_r8(soc, R8::D,  ALU3::ADC)},
-        0x8B => {alu_a_r8(soc, R8::E,  ALU3::ADC)},
-        0x8C => {alu_a_r8(soc, R8::H,  ALU3::ADC)},
-        0x8D => {alu_a_r8(soc, R8::L,  ALU3::ADC)},
-        0x8E => {alu_a_r8(soc, R8::HL, ALU3::ADC)},
-        0x8F => {alu_a_r8(soc, R8::A,  ALU3::ADC)},
-        0x90 => {alu_a_r8(soc, R8::B,  ALU3::SUB)},
-        0x91 => {alu_a_r8(soc, R8::C,  ALU3::SUB)},
-        0x92 => {alu_a_r8(soc, R8::D,  ALU3::SUB)},
-        0x93 => {alu_a_r8(soc, R8::E,  ALU3::SUB)},
-        0x94 => {alu_a_r8(soc, R8::H,  ALU3::SUB)},
-        0x95 => {alu_a_r8(soc, R8::L,  ALU3::SUB)},
-        0x96 => {alu_a_r8(soc, R8::HL, ALU3::SUB)},
-        0x97 => {alu_a_r8(soc, R8::A,  ALU3::SUB)},
-        0x98 => {alu_a_r8(soc, R8::B,  ALU3::SBC)},
-        0x99 => {alu_a_r8(soc, R8::C,  ALU3::SBC)},
-        0x9A => {alu_a_r8(soc, R8::D,  ALU3::SBC)},
-        0x9B => {alu_a_r8(soc, R8::E,  ALU3::SBC)},
-        0x9C => {alu_a_r8(soc, R8::H,  ALU3::SBC)},
-        0x9D => {alu_a_r8(soc, R8::L,  ALU3::SBC)},
-        0x9E => {alu_a_r8(soc, R8::HL, ALU3::SBC)},
-        0x9F => {alu_a_r8(soc, R8::A,  ALU3::SBC)},
-        0xA0 => {alu_a_r8(soc, R8::B,  ALU3::AND)},
-        0xA1 => {alu_a_r8(soc, R8::C,  ALU3::AND)},
-        0xA2 => {alu_a_r8(soc, R8::D,  ALU3::AND)},
-        0xA3 => {alu_a_r8(soc, R8::E,  ALU3::AND)},
-        0xA4 => {alu_a_r8(soc, R8::H,  ALU3::AND)},
-        0xA5 => {alu_a_r8(soc, R8::L,  ALU3::AND)},
-        0xA6 => {alu_a_r8(soc, R8::HL, ALU3::AND)},
-        0xA7 => {alu_a_r8(soc, R8::A,  ALU3::AND)},
-        0xA8 => {alu_a_r8(soc, R8::B,  ALU3::XOR)},
-        0xA9 => {alu_a_r8(soc, R8::C,  ALU3::XOR)},
-        0xAA => {alu_a_r8(soc, R8::D,  ALU3::XOR)},
-        0xAB => {alu_a_r8(soc, R8::E,  ALU3::XOR)},
-        0xAC => {alu_a_r8(soc, R8::H,  ALU3::XOR)},
-        0xAD => {alu_a_r8(soc, R8::L,  ALU3::XOR)},
-        0xAE => {alu_a_r8(soc, R8::HL, ALU3::XOR)},
-        0xAF => {alu_a_r8(soc, R8::A,  ALU3::XOR)},
-        0xB0 => {alu_a_r8(soc, R8::B,  ALU3::OR )},
-        0xB1 => {alu_a_r8(soc, R8::C,  ALU3::OR )},
-        0xB2 => {alu_a_r8(soc, R8::D,  ALU3::OR )},
-        0xB3 => {alu_a_r8(soc, R8::E,  ALU3::OR )},
-        0xB4 => {alu_a_r8(soc, R8::H,  ALU3::OR )},
-        0xB5 => {alu_a_r8(soc, R8::L,  ALU3::OR )},
-        0xB6 => {alu_a_r8(soc, R8::HL, ALU3::OR )},
-        0xB7 => {alu_a_r8(soc, R8::A,  ALU3::OR )},
-        0xB8 => {alu_a_r8(soc, R8::B,  ALU3::CP )},
-        0xB9 => {alu_a_r8(soc, R8::C,  ALU3::CP )},
-        0xBA => {alu_a_r8(soc, R8::D,  ALU3::CP )},
-        0xBB => {alu_a_r8(soc, R8::E,  ALU3::CP )},
-        0xBC => {alu_a_r8(soc, R8::H,  ALU3::CP )},
-        0xBD => {alu_a_r8(soc, R8::L,  ALU3::CP )},
-        0xBE => {alu_a_r8(soc, R8::HL, ALU3::CP )},
-        0xBF => {alu_a_r8(soc, R8::A,  ALU3::CP )},
-        0xC0 => {ret_cond(soc, COND::NZ)},
-        0xC1 => {pop_r16stk(soc, R16STK::BC)},
-        0xC2 => {jp_cond_a16(soc, COND::NZ, b8x2_le(code[1], code[2]))},
-        0xC3 => {jp_a16(soc, b8x2_le(code[1], code[2]))},
-        0xC4 => {call_cond_a16(soc, COND::NZ, b8x2_le(code[1], code[2]))},
-        0xC5 => {push_r16stk(soc, R16STK::BC)},
-        0xC6 => {alu_a_n8(soc, code[1], ALU3::ADC)},
-        0xC7 => {rst_tgt3(soc, TGT3::T0)},
-        0xC8 => {ret_cond(soc, COND::Z)},
-        0xC9 => {ret(soc)},
-        0xCA => {jp_cond_a16(soc, COND::Z, b8x2_le(code[1], code[2]))},
-        0xCB => {ex_inst(soc, code[1]);},
-        0xCC => {call_cond_a16(soc, COND::Z, b8x2_le(code[1], code[2]))},
-        0xCD => {call_a16(soc, b8x2_le(code[1], code[2]))},
-        0xCE => {alu_a_n8(soc, code[1], ALU3::ADD)},
-        0xCF => {rst_tgt3(soc, TGT3::T1)},
-        0xD0 => {ret_cond(soc, COND::NC)},
-        0xD1 => {pop_r16stk(soc, R16STK::DE)},
-        0xD2 => {jp_cond_a16(soc, COND::NC, b8x2_le(code[1], code[2]))},
-        0xD3 => {},
-        0xD4 => {call_cond_a16(soc, COND::NC, b8x2_le(code[1], code[2]))},
-        0xD5 => {push_r16stk(soc, R16STK::DE)},
-        0xD6 => {alu_a_n8(soc, code[1], ALU3::SUB)},
-        0xD7 => {rst_tgt3(soc, TGT3::T2)},
-        0xD8 => {ret_cond(soc, COND::C)},
-        0xD9 => {reti(soc)},
-        0xDA => {jp_cond_a16(soc, COND::C, b8x2_le(code[1], code[2]))},
-        0xDB => {},
-        0xDC => {call_cond_a16(soc, COND::C, b8x2_le(code[1], code[2]))},
-        0xDD => {},
-        0xDE => {alu_a_n8(soc, code[1], ALU3::SBC)},
-        0xDF => {rst_tgt3(soc, TGT3::T3)},
-        0xE0 => {ldh_a8_a(soc, code[1] as u8)},
-        0xE1 => {pop_r16stk(soc, R16STK::HL)},
-        0xE2 => {ldh_c_a(soc)},
-        0xE3 => {},
-        0xE4 => {},
-        0xE5 => {push_r16stk(soc, R16STK::HL)},
-        0xE6 => {alu_a_n8(soc, code[1], ALU3::AND)},
-        0xE7 => {rst_tgt3(soc, TGT3::T4)},
-        0xE8 => {add_sp_e8(soc, code[1] as i8)},
-        0xE9 => {jp_hl(soc)},
-        0xEA => {ld_a16_a(soc, b8x2_le(code[1], code[2]))},
-        0xEB => {},
-        0xEC => {},
-        0xED => {},
-        0xEE => {alu_a_n8(soc, code[1], ALU3::XOR)},
-        0xEF => {rst_tgt3(soc, TGT3::T5)},
-        0xF0 => {ldh_a_a8(soc, code[1] as u8)},
-        0xF1 => {pop_r16stk(soc, R16STK::AF)},
-        0xF2 => {ldh_a_c(soc)},
-        0xF3 => {di(soc)},
-        0xF4 => {},
-        0xF5 => {push_r16stk(soc, R16STK::AF)},
-        0xF6 => {alu_a_n8(soc, code[1], ALU3::OR)},
-        0xF7 => {rst_tgt3(soc, TGT3::T6)},
-        0xF8 => {ld_hl_sp_e8(soc, code[1] as i8)},
-        0xF9 => {ld_sp_hl(soc)},
-        0xFA => {ld_a_a16(soc, b8x2_le(code[1], code[2]))},
-        0xFB => {ei(soc)},
-        0xFC => {},
-        0xFD => {},
-        0xFE => {alu_a_n8(soc, code[1], ALU3::CP)},
-        0xFF => {rst_tgt3(soc, TGT3::T7)},
-    }
-}
-
-fn ex_inst(soc: &mut SoC, sub_code: u16) {
-    let sub_code = sub_code as u8;
-    match sub_code {
-        0x00 => {bop_r8(soc, R8::B,  BOP3::RLC );}
-        0x01 => {bop_r8(soc, R8::C,  BOP3::RLC );}
-        0x02 => {bop_r8(soc, R8::D,  BOP3::RLC );}
-        0x03 => {bop_r8(soc, R8::E,  BOP3::RLC );}
-        0x04 => {bop_r8(soc, R8::H,  BOP3::RLC );}
-        0x05 => {bop_r8(soc, R8::L,  BOP3::RLC );}
-        0x06 => {bop_r8(soc, R8::HL, BOP3::RLC );}
-        0x07 => {bop_r8(soc, R8::A,  BOP3::RLC );}
-        0x08 => {bop_r8(soc, R8::B,  BOP3::RRC );}
-        0x09 => {bop_r8(soc, R8::C,  BOP3::RRC );}
-        0x0A => {bop_r8(soc, R8::D,  BOP3::RRC );}
-        0x0B => {bop_r8(soc, R8::E,  BOP3::RRC );}
-        0x0C => {bop_r8(soc, R8::H,  BOP3::RRC );}
-        0x0D => {bop_r8(soc, R8::L,  BOP3::RRC );}
-        0x0E => {bop_r8(soc, R8::HL, BOP3::RRC );}
-        0x0F => {bop_r8(soc, R8::A,  BOP3::RRC );}
-        0x10 => {bop_r8(soc, R8::B,  BOP3::RL  );}
-        0x11 => {bop_r8(soc, R8::C,  BOP3::RL  );}
-        0x12 => {bop_r8(soc, R8::D,  BOP3::RL  );}
-        0x13 => {bop_r8(soc, R8::E,  BOP3::RL  );}
-        0x14 => {bop_r8(soc, R8::H,  BOP3::RL  );}
-        0x15 => {bop_r8(soc, R8::L,  BOP3::RL  );}
-        0x16 => {bop_r8(soc, R8::HL, BOP3::RL  );}
-        0x17 => {bop_r8(soc, R8::A,  BOP3::RL  );}
-        0x18 => {bop_r8(soc, R8::B,  BOP3::RR  );}
-        0x19 => {bop_r8(soc, R8::C,  BOP3::RR  );}
-        0x1A => {bop_r8(soc, R8::D,  BOP3::RR  );}
-        0x1B => {bop_r8(soc, R8::E,  BOP3::RR  );}
-        0x1C => {bop_r8(soc, R8::H,  BOP3::RR  );}
-        0x1D => {bop_r8(soc, R8::L,  BOP3::RR  );}
-        0x1E => {bop_r8(soc, R8::HL, BOP3::RR  );}
-        0x1F => {bop_r8(soc, R8::A,  BOP3::RR  );}
-        0x20 => {bop_r8(soc, R8::B,  BOP3::SLA );}
-        0x21 => {bop_r8(soc, R8::C,  BOP3::SLA );}
-        0x22 => {bop_r8(soc, R8::D,  BOP3::SLA );}
-        0x23 => {bop_r8(soc, R8::E,  BOP3::SLA );}
-        0x24 => {bop_r8(soc, R8::H,  BOP3::SLA );}
-        0x25 => {bop_r8(soc, R8::L,  BOP3::SLA );}
-        0x26 => {bop_r8(soc, R8::HL, BOP3::SLA );}
-        0x27 => {bop_r8(soc, R8::A,  BOP3::SLA );}
-        0x28 => {bop_r8(soc, R8::B,  BOP3::SRA );}
-        0x29 => {bop_r8(soc, R8::C,  BOP3::SRA );}
-        0x2A => {bop_r8(soc, R8::D,  BOP3::SRA );}
-        0x2B => {bop_r8(soc, R8::E,  BOP3::SRA );}
-        0x2C => {bop_r8(soc, R8::H,  BOP3::SRA );}
-        0x2D => {bop_r8(soc, R8::L,  BOP3::SRA );}
-        0x2E => {bop_r8(soc, R8::HL, BOP3::SRA );}
-        0x2F => {bop_r8(soc, R8::A,  BOP3::SRA );}
-        0x30 => {bop_r8(soc, R8::B,  BOP3::SWAP);}
-        0x31 => {bop_r8(soc, R8::C,  BOP3::SWAP);}
-        0x32 => {bop_r8(soc, R8::D,  BOP3::SWAP);}
-        0x33 => {bop_r8(soc, R8::E,  BOP3::SWAP);}
-        0x34 => {bop_r8(soc, R8::H,  BOP3::SWAP);}
-        0x35 => {bop_r8(soc, R8::L,  BOP3::SWAP);}
-        0x36 => {bop_r8(soc, R8::HL, BOP3::SWAP);}
-        0x37 => {bop_r8(soc, R8::A,  BOP3::SWAP);}
-        0x38 => {bop_r8(soc, R8::B,  BOP3::SRL );}
-        0x39 => {bop_r8(soc, R8::C,  BOP3::SRL );}
-        0x3A => {bop_r8(soc, R8::D,  BOP3::SRL );}
-        0x3B => {bop_r8(soc, R8::E,  BOP3::SRL );}
-        0x3C => {bop_r8(soc, R8::H,  BOP3::SRL );}
-        0x3D => {bop_r8(soc, R8::L,  BOP3::SRL );}
-        0x3E => {bop_r8(soc, R8::HL, BOP3::SRL );}
-        0x3F => {bop_r8(soc, R8::A,  BOP3::SRL );}
-        0x40 => {bit_r8_b3(soc, R8::B,  0);}
-        0x41 => {bit_r8_b3(soc, R8::C,  0);}
-        0x42 => {bit_r8_b3(soc, R8::D,  0);}
-        0x43 => {bit_r8_b3(soc, R8::E,  0);}
-        0x44 => {bit_r8_b3(soc, R8::H,  0);}
-        0x45 => {bit_r8_b3(soc, R8::L,  0);}
-        0x46 => {bit_r8_b3(soc, R8::HL, 0);}
-        0x47 => {bit_r8_b3(soc, R8::A,  0);}
-        0x48 => {bit_r8_b3(soc, R8::B,  1);}
-        0x49 => {bit_r8_b3(soc, R8::C,  1);}
-        0x4A => {bit_r8_b3(soc, R8::D,  1);}
-        0x4B => {bit_r8_b3(soc, R8::E,  1);}
-        0x4C => {bit_r8_b3(soc, R8::H,  1);}
-        0x4D => {bit_r8_b3(soc, R8::L,  1);}
-        0x4E => {bit_r8_b3(soc, R8::HL, 1);}
-        0x4F => {bit_r8_b3(soc, R8::A,  1);}
-        0x50 => {bit_r8_b3(soc, R8::B,  2);}
-        0x51 => {bit_r8_b3(soc, R8::C,  2);}
-        0x52 => {bit_r8_b3(soc, R8::D,  2);}
-        0x53 => {bit_r8_b3(soc, R8::E,  2);}
-        0x54 => {bit_r8_b3(soc, R8::H,  2);}
-        0x55 => {bit_r8_b3(soc, R8::L,  2);}
-        0x56 => {bit_r8_b3(soc, R8::HL, 2);}
-        0x57 => {bit_r8_b3(soc, R8::A,  2);}
-        0x58 => {bit_r8_b3(soc, R8::B,  3);}
-        0x59 => {bit_r8_b3(soc, R8::C,  3);}
-        0x5A => {bit_r8_b3(soc, R8::D,  3);}
-        0x5B => {bit_r8_b3(soc, R8::E,  3);}
-        0x5C => {bit_r8_b3(soc, R8::H,  3);}
-        0x5D => {bit_r8_b3(soc, R8::L,  3);}
-        0x5E => {bit_r8_b3(soc, R8::HL, 3);}
-        0x5F => {bit_r8_b3(soc, R8::A,  3);}
-        0x60 => {bit_r8_b3(soc, R8::B,  4);}
-        0x61 => {bit_r8_b3(soc, R8::C,  4);}
-        0x62 => {bit_r8_b3(soc, R8::D,  4);}
-        0x63 => {bit_r8_b3(soc, R8::E,  4);}
-        0x64 => {bit_r8_b3(soc, R8::H,  4);}
-        0x65 => {bit_r8_b3(soc, R8::L,  4);}
-        0x66 => {bit_r8_b3(soc, R8::HL, 4);}
-        0x67 => {bit_r8_b3(soc, R8::A,  4);}
-        0x68 => {bit_r8_b3(soc, R8::B,  5);}
-        0x69 => {bit_r8_b3(soc, R8::C,  5);}
-        0x6A => {bit_r8_b3(soc, R8::D,  5);}
-        0x6B => {bit_r8_b3(soc, R8::E,  5);}
-        0x6C => {bit_r8_b3(soc, R8::H,  5);}
-        0x6D => {bit_r8_b3(soc, R8::L,  5);}
-        0x6E => {bit_r8_b3(soc, R8::HL, 5);}
-        0x6F => {bit_r8_b3(soc, R8::A,  5);}
-        0x70 => {bit_r8_b3(soc, R8::B,  6);}
-        0x71 => {bit_r8_b3(soc, R8::C,  6);}
-        0x72 => {bit_r8_b3(soc, R8::D,  6);}
-        0x73 => {bit_r8_b3(soc, R8::E,  6);}
-        0x74 => {bit_r8_b3(soc, R8::H,  6);}
-        0x75 => {bit_r8_b3(soc, R8::L,  6);}
-        0x76 => {bit_r8_b3(soc, R8::HL, 6);}
-        0x77 => {bit_r8_b3(soc, R8::A,  6);}
-        0x78 => {bit_r8_b3(soc, R8::B,  7);}
-        0x79 => {bit_r8_b3(soc, R8::C,  7);}
-        0x7A => {bit_r8_b3(soc, R8::D,  7);}
-        0x7B => {bit_r8_b3(soc, R8::E,  7);}
-        0x7C => {bit_r8_b3(soc, R8::H,  7);}
-        0x7D => {bit_r8_b3(soc, R8::L,  7);}
-        0x7E => {bit_r8_b3(soc, R8::HL, 7);}
-        0x7F => {bit_r8_b3(soc, R8::A,  7);}
-        0x80 => {bit_r8_b3(soc, R8::B,  0);}
-        0x81 => {bit_r8_b3(soc, R8::C,  0);}
-        0x82 => {bit_r8_b3(soc, R8::D,  0);}
-        0x83 => {bit_r8_b3(soc, R8::E,  0);}
-        0x84 => {bit_r8_b3(soc, R8::H,  0);}
-        0x85 => {bit_r8_b3(soc, R8::L,  0);}
-        0x86 => {bit_r8_b3(soc, R8::HL, 0);}
-        0x87 => {bit_r8_b3(soc, R8::A,  0);}
-        0x88 => {bit_r8_b3(soc, R8::B,  1);}
-        0x89 => {bit_r8_b3(soc, R8::C,  1);}
-        0x8A => {bit_r8_b3(soc, R8::D,  1);}
-        0x8B => {bit_r8_b3(soc, R8::E,  1);}
-        0x8C => {bit_r8_b3(soc, R8::H,  1);}
-        0x8D => {bit_r8_b3(soc, R8::L,  1);}
-        0x8E => {bit_r8_b3(soc, R8::HL, 1);}
-        0x8F => {bit_r8_b3(soc, R8::A,  1);}
-        0x90 => {res_r8_b3(soc, R8::B,  2);}
-        0x91 => {res_r8_b3(soc, R8::C,  2);}
-        0x92 => {res_r8_b3(soc, R8::D,  2);}
-        0x93 => {res_r8_b3(soc, R8::E,  2);}
-        0x94 => {res_r8_b3(soc, R8::H,  2);}
-        0x95 => {res_r8_b3(soc, R8::L,  2);}
-        0x96 => {res_r8_b3(soc, R8::HL, 2);}
-        0x97 => {res_r8_b3(soc, R8::A,  2);}
-        0x98 => {res_r8_b3(soc, R8::B,  3);}
-        0x99 => {res_r8_b3(soc, R8::C,  3);}
-        0x9A => {res_r8_b3(soc, R8::D,  3);}
-        0x9B => {res_r8_b3(soc, R8::E,  3);}
-        0x9C => {res_r8_b3(soc, R8::H,  3);}
-        0x9D => {res_r8_b3(soc, R8::L,  3);}
-        0x9E => {res_r8_b3(soc, R8::HL, 3);}
-        0x9F => {res_r8_b3(soc, R8::A,  3);}
-        0xA0 => {res_r8_b3(soc, R8::B,  4);}
-        0xA1 => {res_r8_b3(soc, R8::C,  4);}
-        0xA2 => {res_r8_b3(soc, R8::D,  4);}
-        0xA3 => {res_r8_b3(soc, R8::E,  4);}
-        0xA4 => {res_r8_b3(soc, R8::H,  4);}
-        0xA5 => {res_r8_b3(soc, R8::L,  4);}
-        0xA6 => {res_r8_b3(soc, R8::HL, 4);}
-        0xA7 => {res_r8_b3(soc, R8::A,  4);}
-        0xA8 => {res_r8_b3(soc, R8::B,  5);}
-        0xA9 => {res_r8_b3(soc, R8::C,  5);}
-        0xAA => {res_r8_b3(soc, R8::D,  5);}
-        0xAB => {res_r8_b3(soc, R8::E,  5);}
-        0xAC => {res_r8_b3(soc, R8::H,  5);}
-        0xAD => {res_r8_b3(soc, R8::L,  5);}
-        0xAE => {res_r8_b3(soc, R8::HL, 5);}
-        0xAF => {res_r8_b3(soc, R8::A,  5);}
-        0xB0 => {res_r8_b3(soc, R8::B,  6);}
-        0xB1 => {res_r8_b3(soc, R8::C,  6);}
-        0xB2 => {res_r8_b3(soc, R8::D,  6);}
-        0xB3 => {res_r8_b3(soc, R8::E,  6);}
-        0xB4 => {res_r8_b3(soc, R8::H,  6);}
-        0xB5 => {res_r8_b3(soc, R8::L,  6);}
-        0xB6 => {res_r8_b3(soc, R8::HL, 6);}
-        0xB7 => {res_r8_b3(soc, R8::A,  6);}
-        0xB8 => {res_r8_b3(soc, R8::B,  7);}
-        0xB9 => {res_r8_b3(soc, R8::C,  7);}
-        0xBA => {res_r8_b3(soc, R8::D,  7);}
-        0xBB => {res_r8_b3(soc, R8::E,  7);}
-        0xBC => {res_r8_b3(soc, R8::H,  7);}
-        0xBD => {res_r8_b3(soc, R8::L,  7);}
-        0xBE => {res_r8_b3(soc, R8::HL, 7);}
-        0xBF => {res_r8_b3(soc, R8::A,  7);}
-        0xC0 => {set_r8_b3(soc, R8::B,  0);}
-        0xC1 => {set_r8_b3(soc, R8::C,  0);}
-        0xC2 => {set_r8_b3(soc, R8::D,  0);}
-        0xC3 => {set_r8_b3(soc, R8::E,  0);}
-        0xC4 => {set_r8_b3(soc, R8::H,  0);}
-        0xC5 => {set_r8_b3(soc, R8::L,  0);}
-        0xC6 => {set_r8_b3(soc, R8::HL, 0);}
-        0xC7 => {set_r8_b3(soc, R8::A,  0);}
-        0xC8 => {set_r8_b3(soc, R8::B,  1);}
-        0xC9 => {set_r8_b3(soc, R8::C,  1);}
-        0xCA => {set_r8_b3(soc, R8::D,  1);}
-        0xCB => {set_r8_b3(soc, R8::E,  1);}
-        0xCC => {set_r8_b3(soc, R8::H,  1);}
-        0xCD => {set_r8_b3(soc, R8::L,  1);}
-        0xCE => {set_r8_b3(soc, R8::HL, 1);}
-        0xCF => {set_r8_b3(soc, R8::A,  1);}
-        0xD0 => {set_r8_b3(soc, R8::B,  2);}
-        0xD1 => {set_r8_b3(soc, R8::C,  2);}
-        0xD2 => {set_r8_b3(soc, R8::D,  2);}
-        0xD3 => {set_r8_b3(soc, R8::E,  2);}
-        0xD4 => {set_r8_b3(soc, R8::H,  2);}
-        0xD5 => {set_r8_b3(soc, R8::L,  2);}
-        0xD6 => {set_r8_b3(soc, R8::HL, 2);}
-        0xD7 => {set_r8_b3(soc, R8::A,  2);}
-        0xD8 => {set_r8_b3(soc, R8::B,  3);}
-        0xD9 => {set_r8_b3(soc, R8::C,  3);}
-        0xDA => {set_r8_b3(soc, R8::D,  3);}
-        0xDB => {set_r8_b3(soc, R8::E,  3);}
-        0xDC => {set_r8_b3(soc, R8::H,  3);}
-        0xDD => {set_r8_b3(soc, R8::L,  3);}
-        0xDE => {set_r8_b3(soc, R8::HL, 3);}
-        0xDF => {set_r8_b3(soc, R8::A,  3);}
-        0xE0 => {set_r8_b3(soc, R8::B,  4);}
-        0xE1 => {set_r8_b3(soc, R8::C,  4);}
-        0xE2 => {set_r8_b3(soc, R8::D,  4);}
-        0xE3 => {set_r8_b3(soc, R8::E,  4);}
-        0xE4 => {set_r8_b3(soc, R8::H,  4);}
-        0xE5 => {set_r8_b3(soc, R8::L,  4);}
-        0xE6 => {set_r8_b3(soc, R8::HL, 4);}
-        0xE7 => {set_r8_b3(soc, R8::A,  4);}
-        0xE8 => {set_r8_b3(soc, R8::B,  5);}
-        0xE9 => {set_r8_b3(soc, R8::C,  5);}
-        0xEA => {set_r8_b3(soc, R8::D,  5);}
-        0xEB => {set_r8_b3(soc, R8::E,  5);}
-        0xEC => {set_r8_b3(soc, R8::H,  5);}
-        0xED => {set_r8_b3(soc, R8::L,  5);}
-        0xEE => {set_r8_b3(soc, R8::HL, 5);}
-        0xEF => {set_r8_b3(soc, R8::A,  5);}
-        0xF0 => {set_r8_b3(soc, R8::B,  6);}
-        0xF1 => {set_r8_b3(soc, R8::C,  6);}
-        0xF2 => {set_r8_b3(soc, R8::D,  6);}
-        0xF3 => {set_r8_b3(soc, R8::E,  6);}
-        0xF4 => {set_r8_b3(soc, R8::H,  6);}
-        0xF5 => {set_r8_b3(soc, R8::L,  6);}
-        0xF6 => {set_r8_b3(soc, R8::HL, 6);}
-        0xF7 => {set_r8_b3(soc, R8::A,  6);}
-        0xF8 => {set_r8_b3(soc, R8::B,  7);}
-        0xF9 => {set_r8_b3(soc, R8::C,  7);}
-        0xFA => {set_r8_b3(soc, R8::D,  7);}
-        0xFB => {set_r8_b3(soc, R8::E,  7);}
-        0xFC => {set_r8_b3(soc, R8::H,  7);}
-        0xFD => {set_r8_b3(soc, R8::L,  7);}
-        0xFE => {set_r8_b3(soc, R8::HL, 7);}
-        0xFF => {set_r8_b3(soc, R8::A,  7);}
-    }
-}
-
-// 注册到模块
-#[pymodule]
-fn simu83(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {m.add_class::<SoC>()?; Ok(())}
-
-// ===========================================================
-enum R8 {B, C, D, E, H, L, HL, A}
-enum R16 {BC, DE, HL, SP}
-enum R16STK {BC, DE, HL, AF}
-enum R16RAM {BC, DE, HLI, HLD}
-enum COND {NZ, Z, NC, C}
-enum TGT3 {T0=0x00, T1=0x08, T2=0x10, T3=0x18, T4=0x20, T5=0x28, T6=0x30, T7=0x38}
-enum ALU3 {ADD, ADC, SUB, SBC, AND, XOR, OR, CP}
-enum BOP3 {RLC, RRC, RL, RR, SLA, SRA, SWAP, SRL}
-
-fn get_r8_by_idx(soc: &SoC, r8: &R8) -> u8 {
-    match r8 {
-        R8::B  => {soc.get_r8(0)}
-        R8::C  => {soc.get_r8(1)}
-        R8::D  => {soc.get_r8(2)}
-        R8::E  => {soc.get_r8(3)}
-        R8::H  => {soc.get_r8(4)}
-        R8::L  => {soc.get_r8(5)}
-        R8::HL => {soc.ram_read(soc.get_r16(2))}
-        R8::A  => {soc.get_r8(7)}
-    }
-}
-fn get_r16_by_idx(soc: &SoC, r16: R16) -> u16 {
-    match r16 {
-        R16::BC => {soc.get_r16(0)},
-        R16::DE => {soc.get_r16(1)},
-        R16::HL => {soc.get_r16(2)},
-        R16::SP => {soc.get_sp()}
-    }
-}
-fn get_r16stk_by_idx(soc: &SoC, r16stk: R16STK) -> u16 {
-    match r16stk {
-        R16STK::BC => {soc.get_r16(0)},
-        R16STK::DE => {soc.get_r16(1)},
-        R16STK::HL => {soc.get_r16(2)},
-        R16STK::AF => {soc.get_r16(3)},
-    }
-}
-fn get_cond_by_idx(soc: &SoC, cond: COND) -> bool {
-    match cond {
-        COND::NZ => {soc.get_flag(7) == 0},
-        COND::Z  => {soc.get_flag(7) == 1},
-        COND::NC => {soc.get_flag(4) == 0},
-        COND::C  => {soc.get_flag(4) == 1},
-    }
-}
-
-fn set_r8_by_idx(soc: &mut SoC, r8: &R8, r8_value: u8) {
-    match r8 {
-        R8::B  => {soc.set_r8(0, r8_value);}
-        R8::C  => {soc.set_r8(1, r8_value);}
-        R8::D  => {soc.set_r8(2, r8_value);}
-        R8::E  => {soc.set_r8(3, r8_value);}
-        R8::H  => {soc.set_r8(4, r8_value);}
-        R8::L  => {soc.set_r8(5, r8_value);}
-        R8::HL => {soc.ram_write(soc.get_r16(2),r8_value);}
-        R8::A  => {soc.set_r8(7, r8_value);}
-    }
-}
-
-// ===========================================================
-// 其他辅助函数
-fn b8x2_le(b8a: u16, b8b: u16) -> u16 {(b8b << 8) + b8a}
-
-// ld_r16ram_a
-fn ld_r16ram_a(soc: &mut SoC, r16ram: R16RAM) {
-    let addr: u16 = match r16ram {
-        R16RAM::BC  => {soc.get_r16(0)},
-        R16RAM::DE  => {soc.get_r16(1)},
-        _           => {soc.get_r16(2)},};
-    match r16ram {
-        R16RAM::HLI => {soc.r16_inc(2)},
-        R16RAM::HLD => {soc.r16_dec(2)},
-        _           => {}}
-    soc.ram_write(addr, soc.get_r8(7));
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// inc_r16
-fn inc_r16(soc: &mut SoC, r16: R16) {
-    match r16 {
-        R16::BC => {soc.r16_inc(0)},
-        R16::DE => {soc.r16_inc(1)},
-        R16::HL => {soc.r16_inc(2)},
-        R16::SP => {soc.set_sp(soc.get_sp() + 1);}}
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// add_hl_r16
-fn add_hl_r16(soc: &mut SoC, r16: R16) {
-    let add1 = soc.get_r16(2);
-    let add2 = get_r16_by_idx(soc, r16);
-    soc.res_flag(6);
-    if (add1 & 0xfff) + (add2 & 0xfff) > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
-    if add1 as u32 + add2 as u32 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
-    soc.set_r16(2, add1 + add2);
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// ld_a_r16ram
-fn ld_a_r16ram(soc: &mut SoC, r16ram: R16RAM) {
-    let addr: u16 = match r16ram {
-        R16RAM::BC  => {soc.get_r16(0)},
-        R16RAM::DE  => {soc.get_r16(1)},
-        _           => {soc.get_r16(2)},};
-    match r16ram {
-        R16RAM::HLI => {soc.r16_inc(2)},
-        R16RAM::HLD => {soc.r16_dec(2)},
-        _           => {}}
-    soc.set_r8(7, soc.ram_read(addr));
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// dec_r16
-fn dec_r16(soc: &mut SoC, r16: R16) {
-    match r16 {
-        R16::BC => {soc.r16_dec(0)},
-        R16::DE => {soc.r16_dec(1)},
-        R16::HL => {soc.r16_dec(2)},
-        R16::SP => {soc.set_sp(soc.get_sp() - 1);}}
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// inc_r8
-fn inc_r8(soc: &mut SoC, r8: R8) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    set_r8_by_idx(soc, &r8, r8_value + 1);
-    if r8_value + 1 == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
-    soc.res_flag(6);
-    if (r8_value & 0xf) + 1 > 0xf {soc.set_flag(5);} else {soc.res_flag(5);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// dec_r8
-fn dec_r8(soc: &mut SoC, r8: R8) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    set_r8_by_idx(soc, &r8, r8_value - 1);
-    if r8_value - 1 == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
-    soc.set_flag(6);
-    if ((r8_value & 0xf) as i8 - 1) < 0 {soc.set_flag(5);} else {soc.res_flag(5);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// ld_r8_r8
-fn ld_r8_r8(soc: &mut SoC, r8d: R8, r8s: R8) {
-    let r8_value = get_r8_by_idx(soc, &r8s);
-    set_r8_by_idx(soc, &r8d, r8_value);
-    soc.pc_inc(1);
-    if (r8s as u8 == 6) | (r8d as u8 == 6) {soc.cyc_inc(2);} else {soc.cyc_inc(1);}
-}
-fn alu_a_r8(soc: &mut SoC, r8: R8, alu3: ALU3) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    match alu3 {
-        ALU3::ADD => {
-            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, false, true);
-            soc.set_r8(7, soc.get_r8(7)+r8_value);
-        }
-        ALU3::ADC => {
-            let c_flag = soc.get_flag(4);
-            soc.smart_flag(soc.get_r8(7) as u16, (r8_value + c_flag) as u16, false, true);
-            soc.set_r8(7, soc.get_r8(7) + r8_value + c_flag);
-        }
-        ALU3::SUB => {
-            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, true, true);
-            soc.set_r8(7, soc.get_r8(7) - r8_value);
-        }
-        ALU3::SBC => {
-            let c_flag = soc.get_flag(4);
-            soc.smart_flag(soc.get_r8(7) as u16, (r8_value + c_flag) as u16, true, true);
-            soc.set_r8(7, soc.get_r8(7) - r8_value - c_flag);
-        }
-        ALU3::AND => {
-            if (soc.get_r8(7) & r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.set_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) & r8_value);
-        }
-        ALU3::XOR => {
-            if (soc.get_r8(7) ^ r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.res_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) ^ r8_value);
-        }
-        ALU3::OR => {
-            if (soc.get_r8(7) | r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.res_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) | r8_value);
-        }
-        ALU3::CP => {
-            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, true, true);
-        }
-    }
-    soc.pc_inc(1);
-    match r8 {R8::HL => {soc.cyc_inc(2);} _ => {soc.cyc_inc(1);}};
-}
-// ret_cond
-fn ret_cond(soc: &mut SoC, cond: COND) {
-    if get_cond_by_idx(soc, cond) == true {
-        let lo8 = soc.ram_read(soc.get_sp());
-        if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);};
-        let hi8 = soc.ram_read(soc.get_sp());
-        if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);};
-        soc.set_pc(lo8 as u16 + ((hi8 as u16) << 8));
-        soc.cyc_inc(5);
-    } else {
-        soc.pc_inc(1);
-        soc.cyc_inc(2);
-    }
-}
-// pop_r16stk
-fn pop_r16stk(soc: &mut SoC, r16stk: R16STK) {
-    soc.set_sp(soc.get_sp() + 1);
-    let lo8 = soc.ram_read(soc.get_sp());
-    soc.set_sp(soc.get_sp() + 1);
-    let hi8 = soc.ram_read(soc.get_sp());
-    let new_r16 = lo8 as u16 + ((hi8 as u16) << 8);
-    match r16stk {
-        R16STK::BC => {soc.set_r16(0, new_r16);},
-        R16STK::DE => {soc.set_r16(1, new_r16);},
-        R16STK::HL => {soc.set_r16(2, new_r16);},
-        R16STK::AF => {soc.set_r16(3, new_r16);},}
-    soc.cyc_inc(3);
-    soc.pc_inc(1);
-}
-// push_r16stk
-fn push_r16stk(soc: &mut SoC, r16stk: R16STK) {
-    let new_r16 = get_r16stk_by_idx(soc, r16stk);
-    let hi8 = (new_r16 >> 8) as u8;
-    let lo8 = new_r16 as u8;
-    soc.ram_write(soc.get_sp(), hi8);
-    soc.set_sp(soc.get_sp() - 1);
-    soc.ram_write(soc.get_sp(), lo8);
-    soc.set_sp(soc.get_sp() - 1);
-    soc.cyc_inc(4);
-    soc.pc_inc(1);
-}
-// rst_tgt3
-fn rst_tgt3(soc: &mut SoC, tgt3: TGT3) {
-    soc.pc_inc(tgt3 as u16);
-    soc.cyc_inc(4);
-}
-// ld_r8_n8
-fn ld_r8_n8(soc: &mut SoC, r8: R8, n8: u16) {
-    let n8 = n8 as u8;
-    set_r8_by_idx(soc, &r8, n8);
-    soc.pc_inc(2);
-    if r8 as u8 == 6 {soc.cyc_inc(3)} else {soc.cyc_inc(2);};
-}
-// jr_cond_e8
-fn jr_cond_e8(soc: &mut SoC, cond: COND, e8: i8) {
-    let res = ((soc.get_pc() + 2) as i32 + e8 as i32)as u16;
-    if get_cond_by_idx(soc, cond) == true {
-        soc.set_pc(res);
-        soc.cyc_inc(3);
-    } else {
-        soc.pc_inc(2);
-        soc.cyc_inc(2);
-    }
-}
-// ld_r16_n16
-fn ld_r16_n16(soc: &mut SoC, r16: R16, n16: u16) {
-    match r16 {
-        R16::BC => {soc.set_r16(0, n16);},
-        R16::DE => {soc.set_r16(1, n16);},
-        R16::HL => {soc.set_r16(2, n16);},
-        R16::SP => {soc.set_sp(n16);},
-    }
-    soc.cyc_inc(3);
-    soc.pc_inc(3);
-}
-// jp_cond_a16
-fn jp_cond_a16(soc: &mut SoC, cond:COND, a16: u16) {
-    if get_cond_by_idx(soc, cond) == true {
-        soc.set_pc(a16);
-        soc.cyc_inc(4);
-    } else {
-        soc.pc_inc(3);
-        soc.cyc_inc(3);
-    }
-}
-// call_cond_a16
-fn call_cond_a16(soc: &mut SoC, cond:COND, a16: u16) {
-    if get_cond_by_idx(soc, cond) == true {
-        let new_pc = soc.get_pc() + 3;
-        if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() - 1);};
-        soc.ram_write(soc.get_sp(), (new_pc >> 8) as u8);
-        if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() - 1);};
-        soc.ram_write(soc.get_sp(), (new_pc & 0xff) as u8);
-        soc.set_pc(a16);
-        soc.cyc_inc(6);
-    } else {
-        soc.pc_inc(3);
-        soc.cyc_inc(3);
-    }
-}
-// bop_r8
-fn bop_r8(soc: &mut SoC, r8: R8, bop3: BOP3) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    let c: u8;
-    let res: u8;
-    match bop3 {
-        BOP3::RLC => {
-            c = r8_value >> 7;
-            res = ((r8_value << 1) + c) & 0xff;
-        }
-        BOP3::RRC => {
-            c = r8_value & 1;
-            res = ((r8_value >> 1) + (c << 7)) & 0xff;
-        }
-        BOP3::RL => {
-            c = r8_value >> 7;
-            res = ((r8_value << 1) + soc.get_flag(4)) & 0xff;
-        }
-        BOP3::RR => {
-            c = r8_value & 1;
-            res = (r8_value >> 1) + (soc.get_flag(4) << 7);
-        }
-        BOP3::SLA => {
-            c = r8_value >> 7;
-            res = (r8_value << 1) & 0xff;
-        }
-        BOP3::SRA => {
-            c = r8_value & 1;
-            res = ((r8_value >> 1) & 0xff) + (r8_value & 0x80);
-        }
-        BOP3::SWAP => {
-            c = 42;
-            res = (r8_value >> 4) + ((r8_value & 0xf) << 4);
-        }
-        BOP3::SRL => {
-            c = r8_value & 1;
-            res = (r8_value >> 1) & 0xff;
-        }
-    }
-    set_r8_by_idx(soc, &r8, res);
-    if res == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
-    soc.res_flag(6);
-    soc.res_flag(5);
-    if (bop3 as u8) != 6 {
-        if c == 1 {soc.set_flag(4);} 
-        if c == 0 {soc.res_flag(4);}
-    }
-    soc.pc_inc(2);
-    if (r8 as u8) == 6 {soc.cyc_inc(4)} else {soc.cyc_inc(2);}
-}
-// bit_r8_b3
-fn bit_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    if r8_value & (1 << b3) == 0 {
-        soc.set_flag(7);
-    } else {
-        soc.res_flag(7);
-    }
-    soc.res_flag(6);
-    soc.set_flag(5);
-    soc.pc_inc(2);
-    if (r8 as u8) == 6 {soc.cyc_inc(3);} else {soc.cyc_inc(2);}
-}
-// res_r8_b3
-fn res_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    set_r8_by_idx(soc, &r8, r8_value & (0xff - (1 << b3))); 
-    soc.pc_inc(2);
-    if (r8 as u8) == 6 {soc.cyc_inc(4);} else {soc.cyc_inc(2);}
-}
-// set_r8_b3
-fn set_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
-    let r8_value = get_r8_by_idx(soc, &r8);
-    set_r8_by_idx(soc, &r8, r8_value | (0xff - (1 << b3))); 
-    soc.pc_inc(2);
-    if (r8 as u8) == 6 {soc.cyc_inc(4);} else {soc.cyc_inc(2);}
-}
-// nop
-fn nop(soc: &mut SoC) {
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// rlca
-fn rlca(soc: &mut SoC) {
-    let a_value = soc.get_r8(7);
-    let c = a_value >> 7;
-    soc.set_r8(7, ((a_value << 1) + c) & 0xff);
-    soc.res_flag(7);
-    soc.res_flag(6);
-    soc.res_flag(5);
-    if c == 1 {soc.set_flag(4);} 
-    if c == 0 {soc.res_flag(4);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// rrca
-fn rrca(soc: &mut SoC) {
-    let a_value = soc.get_r8(7);
-    let c = a_value & 1;
-    soc.set_r8(7, ((a_value >> 1) + (c << 7)) & 0xff);
-    soc.res_flag(7);
-    soc.res_flag(6);
-    soc.res_flag(5);
-    if c == 1 {soc.set_flag(4);} 
-    if c == 0 {soc.res_flag(4);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// rla
-fn rla(soc: &mut SoC) {
-    let a_value = soc.get_r8(7);
-    let c = a_value >> 7;
-    soc.set_r8(7, ((a_value << 1) + soc.get_flag(4)) & 0xff);
-    soc.res_flag(7);
-    soc.res_flag(6);
-    soc.res_flag(5);
-    if c == 1 {soc.set_flag(4);} 
-    if c == 0 {soc.res_flag(4);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// rra
-fn rra(soc: &mut SoC) {
-    let a_value = soc.get_r8(7);
-    let c = a_value & 1;
-    soc.set_r8(7, ((a_value >> 1) + (soc.get_flag(4) << 7)) & 0xff);
-    soc.res_flag(7);
-    soc.res_flag(6);
-    soc.res_flag(5);
-    if c == 1 {soc.set_flag(4);} 
-    if c == 0 {soc.res_flag(4);}
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// daa
-fn daa(soc: &mut SoC) {
-    let a_value = soc.get_r8(7);
-    let res;
-    if soc.get_flag(6) == 1 {
-        let adj = soc.get_flag(5) * 0x6 + soc.get_flag(4) * 0x60;
-        res = a_value - adj;
-        soc.smart_flag(a_value as u16, adj as u16, true, true);
-    } else {
-        let mut adj: u8 = 0x0;
-		if (soc.get_flag(5) == 1) || ((a_value & 0xf) > 0x9) {adj = adj + 0x6}
-		if (soc.get_flag(4) == 1) || ((a_value & 0x99) > 0x9) {adj = adj + 0x60}
-        res = a_value + adj;
-        soc.smart_flag(a_value as u16, adj as u16, false, true);
-    }
-    soc.res_flag(5);
-    soc.set_r8(7, res);
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// scf
-fn scf(soc: &mut SoC) {
-    soc.res_flag(6);
-    soc.res_flag(5);
-    soc.set_flag(4);
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// cpl
-fn cpl(soc: &mut SoC) {
-    soc.set_r8(7, soc.get_r8(7) ^ 0xff);
-    soc.set_flag(6);
-    soc.set_flag(5);
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// ccf
-fn ccf(soc: &mut SoC) {
-    let c = soc.get_flag(4);
-    if c == 1 {soc.res_flag(4);}
-    if c == 0 {soc.set_flag(4);}
-    soc.res_flag(6);
-    soc.res_flag(5);
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// ret
-fn ret(soc: &mut SoC) {
-    let lo8 = soc.ram_read(soc.get_sp());
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);}
-    let hi8 = soc.ram_read(soc.get_sp());
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);}
-    soc.set_pc(lo8 as u16 + ((hi8 as u16) << 8));
-    soc.cyc_inc(4);
-}
-// jp_hl
-fn jp_hl(soc: &mut SoC) {
-    soc.set_pc(soc.get_r16(2));
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// di
-fn di(soc: &mut SoC) {
-    soc.res_ime();
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// ei
-fn ei(soc: &mut SoC) {
-    soc.set_ime();
-    soc.pc_inc(1);
-    soc.cyc_inc(1);
-}
-// reti
-fn reti(soc: &mut SoC) {
-    soc.set_ime();
-    let lo8 = soc.ram_read(soc.get_sp());
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);}
-    let hi8 = soc.ram_read(soc.get_sp());
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp() + 1);}
-    soc.set_pc(lo8 as u16 + ((hi8 as u16) << 8));
-    soc.cyc_inc(4);
-}
-// ld_sp_hl
-fn ld_sp_hl(soc: &mut SoC) {
-    soc.set_sp(soc.get_r16(2));
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// jr_e8
-fn jr_e8(soc: &mut SoC, e8: i8) {
-    let res = ((soc.get_pc() + 2) as i32  + e8 as i32)as u16;
-    soc.set_pc(res);
-    soc.cyc_inc(3);
-}
-// ldh_a8_a
-fn ldh_a8_a(soc: &mut SoC, a8: u8) {
-    soc.ram_write(0xff00 + (a8 as u16), soc.get_r8(7));
-    soc.pc_inc(2);
-    soc.cyc_inc(3);
-}
-// ldh_a_a8
-fn ldh_a_a8(soc: &mut SoC, a8: u8) {
-    soc.set_r8(7, soc.ram_read(0xff00 + (a8 as u16)));
-    soc.pc_inc(2);
-    soc.cyc_inc(3);
-}
-// ldh_c_a
-fn ldh_c_a(soc: &mut SoC) {
-    soc.ram_write(0xff00 + (soc.get_r8(1) as u16), soc.get_r8(7));
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// ldh_a_c
-fn ldh_a_c(soc: &mut SoC) {
-    soc.set_r8(7, soc.ram_read(0xff00 + (soc.get_r8(1) as u16)));
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// alu_a_n8
-fn alu_a_n8(soc: &mut SoC, n8: u16, alu3: ALU3) {
-    match alu3 {
-        ALU3::ADD => {
-            soc.smart_flag(soc.get_r8(7) as u16, n8 as u16, false, true);
-            soc.set_r8(7, soc.get_r8(7) + n8 as u8);
-        }
-        ALU3::ADC => {
-            let c_flag = soc.get_flag(4);
-            soc.smart_flag(soc.get_r8(7) as u16, (n8 as u8 + c_flag) as u16, false, true);
-            soc.set_r8(7, soc.get_r8(7) + n8 as u8 + c_flag);
-        }
-        ALU3::SUB => {
-            soc.smart_flag(soc.get_r8(0) as u16, n8 as u16, true, true);
-            soc.set_r8(7, soc.get_r8(7) - n8 as u8);
-        }
-        ALU3::SBC => {
-            let c_flag = soc.get_flag(4);
-            soc.smart_flag(soc.get_r8(7) as u16, (n8 as u8 + c_flag) as u16, true, true);
-            soc.set_r8(7, soc.get_r8(7) - n8 as u8 - c_flag);
-        }
-        ALU3::AND => {
-            if (soc.get_r8(7) & n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.set_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) & n8 as u8);
-        }
-        ALU3::XOR => {
-            if (soc.get_r8(7) ^ n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.res_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) ^ n8 as u8);
-        }
-        ALU3::OR => {
-            if (soc.get_r8(7) | n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
-            soc.res_flag(6);
-            soc.res_flag(5);
-            soc.res_flag(4);
-            soc.set_r8(7, soc.get_r8(7) | n8 as u8);
-        }
-        ALU3::CP => {
-            soc.smart_flag(soc.get_r8(7) as u16, n8 as u16, true, true);
-        }
-    }
-    soc.pc_inc(1);
-    soc.cyc_inc(2);
-}
-// add_sp_e8
-fn add_sp_e8(soc: &mut SoC, e8: i8) {
-    let sp = soc.get_sp() as i32;
-    let e8 = e8 as i32;
-    soc.res_flag(7);
-    soc.res_flag(6);
-    if e8 > 0 {
-        if (sp & 0xfff) + e8 > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
-        if (sp & 0xffff) + e8 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
-        soc.set_sp((sp + e8) as u16);
-    } else {
-        soc.res_flag(5);
-        soc.res_flag(4);
-    };
-    soc.set_sp((sp + e8) as u16);
-    soc.pc_inc(2);
-    soc.cyc_inc(4);
-}
-// ld_hl_sp_e8
-fn ld_hl_sp_e8(soc: &mut SoC, e8: i8) {
-    let sp = soc.get_sp() as i32;
-    let e8 = e8 as i32;
-    soc.res_flag(7);
-    soc.res_flag(6);
-    if e8 > 0 {
-        if (sp & 0xfff) + e8 > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
-        if (sp & 0xffff) + e8 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
-        soc.set_sp((sp + e8) as u16);
-    } else {
-        soc.res_flag(5);
-        soc.res_flag(4);
-    };
-    soc.set_r16(2, (sp + e8) as u16);
-    soc.pc_inc(2);
-    soc.cyc_inc(3);
-}
-// ld_a16_sp
-fn ld_a16_sp(soc: &mut SoC, a16: u16) {
-    soc.ram_write(a16, soc.get_sp() as u8);
-    soc.ram_write(a16 + 1, (soc.get_sp() >> 8) as u8);
-    soc.pc_inc(3);
-    soc.cyc_inc(5);
-}
-// jp_a16
-fn jp_a16(soc: &mut SoC, a16: u16) {
-    soc.set_pc(a16);
-    soc.cyc_inc(4);
-}
-// call_a16
-fn call_a16(soc: &mut SoC, a16: u16) {
-    let pc = soc.get_pc() + 3;
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp()-1);}
-    soc.ram_write(soc.get_sp(), (pc >> 8) as u8);
-    if soc.get_sp() != 0xfffe {soc.set_sp(soc.get_sp()-1);}
-    soc.ram_write(soc.get_sp(), pc as u8);
-    soc.set_pc(a16);
-    soc.cyc_inc(6);
-}
-// ld_a16_a
-fn ld_a16_a(soc: &mut SoC, a16: u16) {
-    soc.ram_write(a16, soc.get_r8(7));
-    soc.pc_inc(3);
-    soc.cyc_inc(4);
-}
-// ld_a_a16
-fn ld_a_a16(soc: &mut SoC, a16: u16) {
-    soc.set_r8(7, soc.ram_read(a16));
-    soc.pc_inc(3);
-    soc.cyc_inc(4);
-}
-// halt
-fn halt(_soc: &mut SoC) {
-    /* 此处没有代码 */
-}
-// stop
-fn stop(soc: &mut SoC) {
-    soc.pc_inc(2);
-    soc.cyc_inc(1);
-}
+use std::{ffi::CString, path::PathBuf};
+
+use pyo3::{PyTraverseError, PyVisit, buffer::PyBuffer, exceptions::{PyUserWarning, PyValueError}, prelude::*, types::PyByteArray,};
+
+mod apu;
+mod boot;
+mod bus;
+mod cartridge;
+mod error;
+mod header;
+mod interrupt;
+mod joypad;
+mod mbc;
+mod ppu;
+mod serial;
+mod timer;
+
+use apu::{MAX_SAMPLE_RATE, recorder::{AudioFormat, Recorder}};
+use boot::Model;
+use bus::Bus;
+use cartridge::Cartridge;
+use error::{check_b3, check_flag_bit, check_r16pos, check_r8pos, illegal_opcode, rom_out_of_range};
+use header::CartridgeHeader;
+use interrupt::Interrupt;
+use joypad::Button;
+use mbc::{Mbc, mbc3::RtcClock};
+use ppu::PpuBackend;
+use serial::{Link, LinkSocket};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+
+// 定义数据处理对象
+struct Register {a: u8, b: u8, c: u8, d: u8, e: u8, f: u8, h: u8, l: u8}
+
+// 遇到未定义操作码时的处理策略
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IllegalOpcodePolicy {
+    // 与真机一致：CPU 锁死，只有周期继续推进
+    LockUp,
+    // 抛出 IllegalOpcodeError
+    Raise,
+    // 调用用户回调 callback(pc, opcode)
+    Callback,
+}
+
+#[pyclass]
+// 构建 Game Boy SoC 结构（含全部寄存器与内存）
+struct SoC {
+    reg: Register, pc: u16, sp: u16, cyc: u128,
+    ime: bool, ime_pending: bool, halted: bool, halt_bug: bool, locked: bool,
+    illegal_policy: IllegalOpcodePolicy, illegal_callback: Option<PyObject>,
+    autosave: Option<PathBuf>,
+    model: Model,
+    bus: Bus,
+}
+
+// 全部的实例化方法
+#[pymethods]
+impl SoC {
+    /// 初始化 SoC 对象，含全部寄存器与内存
+    /// rom_data 可以是 bytes、bytearray、memoryview、numpy uint8 数组等支持缓冲区协议的对象，也可以是整数列表
+    /// model 缺省时按卡带头部的 CGB 标志选择 DMG 或 CGB
+    #[new]
+    #[pyo3(signature = (rom_data, model=None), text_signature = "(rom_data, model=None)")]
+    fn new(rom_data: &Bound<'_, PyAny>, model: Option<Model>) -> PyResult<Self> {
+        Self::load(rom_data.py(), rom_bytes(rom_data)?, model)
+    }
+    /// 从 .gb/.gbc/.bin 文件载入卡带
+    #[staticmethod]
+    #[pyo3(signature = (path, model=None), text_signature = "(path, model=None)")]
+    fn from_file(py: Python<'_>, path: PathBuf, model: Option<Model>) -> PyResult<Self> {
+        Self::load(py, std::fs::read(path)?, model)
+    }
+    /// 从 bytes 或任意支持缓冲区协议的对象载入卡带
+    #[staticmethod]
+    #[pyo3(signature = (data, model=None), text_signature = "(data, model=None)")]
+    fn from_bytes(data: &Bound<'_, PyAny>, model: Option<Model>) -> PyResult<Self> {
+        Self::load(data.py(), rom_bytes(data)?, model)
+    }
+    /// 卡带头部信息
+    fn header(&self) -> CartridgeHeader {
+        self.bus.cart.header.clone()
+    }
+    // 当前使用的 MBC 名称
+    fn mbc_name(&self) -> &'static str {
+        self.bus.cart.mbc.name()
+    }
+    // 强制指定 MBC1 卡带是否按 MBC1M 多合一方式接线（默认由 ROM 内容自动检测）
+    #[pyo3(text_signature = "(enabled)")]
+    fn set_mbc1_multicart(&mut self, enabled: bool) -> PyResult<()> {
+        match &mut self.bus.cart.mbc {
+            Mbc::Mbc1(mbc1) => {
+                mbc1.multicart = enabled;
+                Ok(())
+            }
+            mbc => Err(PyValueError::new_err(format!("当前卡带的 MBC 为 {}，不是 MBC1", mbc.name()))),
+        }
+    }
+    // MBC1 卡带是否按 MBC1M 多合一方式接线
+    fn is_mbc1_multicart(&self) -> bool {
+        matches!(&self.bus.cart.mbc, Mbc::Mbc1(mbc1) if mbc1.multicart)
+    }
+    // 选择 MBC3 RTC 的时间来源：Cycles（模拟周期，可复现）或 WallTime（系统时间）
+    #[pyo3(text_signature = "(clock)")]
+    fn set_rtc_clock(&mut self, clock: RtcClock) -> PyResult<()> {
+        match &mut self.bus.cart.mbc {
+            Mbc::Mbc3(mbc3) if mbc3.rtc.is_some() => {
+                if let Some(rtc) = &mut mbc3.rtc {rtc.set_clock(clock);}
+                Ok(())
+            }
+            _ => Err(PyValueError::new_err("当前卡带没有 RTC")),
+        }
+    }
+    // 取 RTC 的时间来源；卡带没有 RTC 时为 None
+    fn get_rtc_clock(&self) -> Option<RtcClock> {
+        match &self.bus.cart.mbc {
+            Mbc::Mbc3(mbc3) => mbc3.rtc.as_ref().map(|rtc| rtc.clock),
+            _ => None,
+        }
+    }
+    // 载入 .sav 存档：参数为文件路径，或 bytes 等支持缓冲区协议的对象
+    #[pyo3(text_signature = "(path_or_bytes)")]
+    fn load_save(&mut self, path_or_bytes: &Bound<'_, PyAny>) -> PyResult<()> {
+        let save = match PyBuffer::<u8>::get(path_or_bytes) {
+            Ok(buffer) => buffer.to_vec(path_or_bytes.py())?,
+            Err(_) => std::fs::read(path_or_bytes.extract::<PathBuf>()?)?,
+        };
+        self.bus.cart.load_save(&save).map_err(PyValueError::new_err)
+    }
+    // 导出 .sav 存档（外部 RAM，带 RTC 的 MBC3 附带 48 字节 RTC 数据）
+    fn export_save(&mut self) -> Vec<u8> {
+        self.bus.cart.export_save()
+    }
+    // 设置对象销毁时自动写出存档的路径（卡带无电池时不写），传入 None 关闭
+    #[pyo3(signature = (path), text_signature = "(path)")]
+    fn set_autosave(&mut self, path: Option<PathBuf>) {
+        self.autosave = path;
+    }
+    // 载入 boot ROM 并从 0x0000 开始执行，直到软件写 0xFF50 解除映射；
+    // model 缺省时按长度判断：256 字节为 DMG，2304 字节为 CGB
+    #[pyo3(signature = (path_or_bytes, model=None), text_signature = "(path_or_bytes, model=None)")]
+    fn load_boot_rom(&mut self, path_or_bytes: &Bound<'_, PyAny>, model: Option<Model>) -> PyResult<()> {
+        let boot = match PyBuffer::<u8>::get(path_or_bytes) {
+            Ok(buffer) => buffer.to_vec(path_or_bytes.py())?,
+            Err(_) => std::fs::read(path_or_bytes.extract::<PathBuf>()?)?,
+        };
+        let model = match model {
+            Some(model) => model,
+            None if boot.len() == boot::CGB_BOOT_ROM_SIZE => Model::Cgb,
+            None => Model::Dmg,
+        };
+        if boot.len() != model.boot_rom_size() {
+            return Err(PyValueError::new_err(format!("{} 的 boot ROM 应为 {} 字节，实际为 {} 字节", model.name(), model.boot_rom_size(), boot.len())));
+        }
+        self.set_model(model);
+        // CGB boot ROM 总在 CGB 模式下运行，由它写 KEY0 决定是否进入 DMG 兼容模式
+        if model == Model::Cgb {self.bus.set_cgb(true);}
+        self.bus.boot_rom = Some(boot);
+        self.pc = 0;
+        Ok(())
+    }
+    // 不执行 boot ROM，直接把寄存器与 IO 设为该型号 boot ROM 结束时的状态；model 缺省时沿用当前型号
+    #[pyo3(signature = (model=None), text_signature = "(model=None)")]
+    fn skip_boot(&mut self, model: Option<Model>) {
+        let model = model.unwrap_or(self.model);
+        self.set_model(model);
+        let [af, bc, de, hl] = model.post_boot_registers(self.bus.cart.header.header_checksum);
+        self.set_r16(3, af);
+        self.set_r16(0, bc);
+        self.set_r16(1, de);
+        self.set_r16(2, hl);
+        self.sp = 0xfffe;
+        self.pc = 0x0100;
+        for (addr, data) in model.post_boot_io() {
+            self.bus.write(addr, data);
+        }
+        self.bus.timer.div = boot::POST_BOOT_DIV;
+        self.bus.dma_reg = 0xff;
+        // boot ROM 的提示音结束后通道 1 仍开启，音量已衰减为 0
+        self.bus.apu.square1.enabled = true;
+        // CGB boot ROM 把背景调色板全部设为白色
+        if self.bus.cgb {self.bus.ppu.bg_palette.fill(0xff);}
+        self.bus.boot_rom = None;
+    }
+    // 当前模拟的硬件型号
+    fn get_model(&self) -> Model {
+        self.model
+    }
+    // 是否运行在 CGB 模式；CGB 运行不支持 CGB 的卡带时为 DMG 兼容模式，返回 False
+    fn is_cgb_mode(&self) -> bool {
+        self.bus.cgb
+    }
+    // CPU 是否处于 CGB 倍速模式
+    fn is_double_speed(&self) -> bool {
+        self.bus.double_speed
+    }
+    // boot ROM 是否仍映射在 0x0000 处
+    fn is_boot_rom_mapped(&self) -> bool {
+        self.bus.boot_rom.is_some()
+    }
+    // 最近一帧完整的画面：160x144 字节，每字节为灰度 0-3（0 为最浅）；CGB 模式下按亮度换算
+    fn frame(&self) -> Vec<u8> {
+        self.bus.ppu.frame_shades()
+    }
+    // 最近一帧完整的画面，形状为 (144, 160) 的 numpy uint8 数组
+    fn frame_array(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let numpy = py.import("numpy")?;
+        let data = PyByteArray::new(py, &self.bus.ppu.frame_shades());
+        let array = numpy.call_method1("frombuffer", (data, "uint8"))?
+                         .call_method1("reshape", ((ppu::SCREEN_HEIGHT, ppu::SCREEN_WIDTH),))?;
+        Ok(array.unbind())
+    }
+    // 最近一帧完整的彩色画面：160x144 个像素，每像素 R、G、B 三个字节
+    fn frame_rgb(&self) -> Vec<u8> {
+        self.bus.ppu.frame_rgb()
+    }
+    // 最近一帧完整的彩色画面，形状为 (144, 160, 3) 的 numpy uint8 数组
+    fn frame_rgb_array(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let numpy = py.import("numpy")?;
+        let data = PyByteArray::new(py, &self.bus.ppu.frame_rgb());
+        let array = numpy.call_method1("frombuffer", (data, "uint8"))?
+                         .call_method1("reshape", ((ppu::SCREEN_HEIGHT, ppu::SCREEN_WIDTH, 3),))?;
+        Ok(array.unbind())
+    }
+    // 选择 PPU 实现：Scanline（逐行，速度快）或 Fifo（像素 FIFO，逐 dot 精确）
+    #[pyo3(text_signature = "(backend)")]
+    fn set_ppu_backend(&mut self, backend: PpuBackend) {
+        self.bus.ppu.set_backend(backend);
+    }
+    fn get_ppu_backend(&self) -> PpuBackend {
+        self.bus.ppu.backend
+    }
+    // 已完成的帧数（每次进入 VBlank 加 1）
+    fn get_frame_count(&self) -> u64 {
+        self.bus.ppu.frame_count
+    }
+    // 设置音频输出的采样率（Hz），范围 1 到 1048576（每个 M-cycle 一个采样）
+    #[pyo3(text_signature = "(rate)")]
+    fn set_sample_rate(&mut self, rate: u32) -> PyResult<()> {
+        if rate == 0 || rate > MAX_SAMPLE_RATE {
+            return Err(PyValueError::new_err(format!("采样率必须在 1 到 {MAX_SAMPLE_RATE} Hz 之间")));
+        }
+        if self.bus.apu.recorder.is_some() {return Err(PyValueError::new_err("录音期间不能修改采样率"));}
+        self.bus.apu.set_sample_rate(rate);
+        Ok(())
+    }
+    fn get_sample_rate(&self) -> u32 {
+        self.bus.apu.sample_rate
+    }
+    // 取出并清空已生成的音频采样：左右声道交错的浮点数，范围 -1.0..1.0
+    fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.bus.apu.samples)
+    }
+    // 取出并清空已生成的音频采样，形状为 (n, 2) 的 numpy float32 数组
+    fn take_samples_array(&mut self, py: Python<'_>) -> PyResult<PyObject> {
+        let numpy = py.import("numpy")?;
+        let samples = std::mem::take(&mut self.bus.apu.samples);
+        let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let array = numpy.call_method1("frombuffer", (PyByteArray::new(py, &data), "<f4"))?
+                         .call_method1("reshape", ((-1, 2),))?;
+        Ok(array.unbind())
+    }
+    // 开始把 APU 输出录制到文件：Wav 为 16 位立体声 WAV，RawFloat 为交错的 f32 原始 PCM；
+    // per_channel 为 True 时另外为四个通道各写一个单声道文件（如 out.ch1.wav）
+    #[pyo3(signature = (path, format=AudioFormat::Wav, per_channel=false), text_signature = "(path, format=AudioFormat.Wav, per_channel=False)")]
+    fn start_audio_recording(&mut self, path: PathBuf, format: AudioFormat, per_channel: bool) -> PyResult<()> {
+        self.stop_audio_recording()?;
+        self.bus.apu.recorder = Some(Recorder::create(&path, format, self.bus.apu.sample_rate, per_channel)?);
+        Ok(())
+    }
+    // 结束录音并补全文件头，返回写入的文件路径；录音途中写文件失败时在此抛出 OSError
+    fn stop_audio_recording(&mut self) -> PyResult<Vec<PathBuf>> {
+        if let Some(err) = self.bus.apu.record_error.take() {return Err(err.into());}
+        match self.bus.apu.recorder.take() {
+            Some(mut recorder) => Ok(recorder.finish()?),
+            None => Ok(Vec::new()),
+        }
+    }
+    // 是否正在录音
+    fn is_audio_recording(&self) -> bool {
+        self.bus.apu.recorder.is_some()
+    }
+    // 按下一个按键
+    #[pyo3(text_signature = "(button)")]
+    fn press(&mut self, button: Button) {
+        self.bus.set_buttons(self.bus.joypad.pressed | button.bit());
+    }
+    // 松开一个按键
+    #[pyo3(text_signature = "(button)")]
+    fn release(&mut self, button: Button) {
+        self.bus.set_buttons(self.bus.joypad.pressed & !button.bit());
+    }
+    // 一次设置全部按键：mask 的第 n 位对应序号为 n 的 Button，1 为按下
+    #[pyo3(text_signature = "(mask)")]
+    fn set_buttons(&mut self, mask: u8) {
+        self.bus.set_buttons(mask);
+    }
+    // 当前按下的按键掩码
+    fn get_buttons(&self) -> u8 {
+        self.bus.joypad.pressed
+    }
+    // 作为主机从串口发出的全部字节，按 UTF-8 解码（无效字节替换为 U+FFFD）；
+    // 记录不设上限，长时间运行时应定期调用 clear_serial_output
+    fn serial_output(&self) -> String {
+        String::from_utf8_lossy(&self.bus.serial.output).into_owned()
+    }
+    // 清空已记录的串口输出
+    fn clear_serial_output(&mut self) {
+        self.bus.serial.output.clear();
+    }
+    // 运行直到串口输出中出现 text，最多推进 max_cycles 个 M-cycle；返回是否找到
+    #[pyo3(text_signature = "(text, max_cycles)")]
+    fn run_until_serial_contains(&mut self, py: Python<'_>, text: &str, max_cycles: u128) -> PyResult<bool> {
+        let needle = text.as_bytes();
+        if needle.is_empty() {return Ok(true);}
+        let end = self.cyc.saturating_add(max_cycles);
+        // 已查找过的输出长度；只查新增的部分，并向前多取 len-1 字节以覆盖跨越边界的匹配
+        let mut searched = 0;
+        let mut steps: u64 = 0;
+        loop {
+            let output = &self.bus.serial.output;
+            if output.len() != searched {
+                let start = searched.saturating_sub(needle.len() - 1);
+                if output[start..].windows(needle.len()).any(|window| window == needle) {return Ok(true);}
+                searched = output.len();
+            }
+            if self.cyc >= end {return Ok(false);}
+            // 定期响应 Ctrl-C
+            if steps.is_multiple_of(0x10000) {py.check_signals()?;}
+            steps += 1;
+            self.one_step()?;
+        }
+    }
+    // 断开串口连线，此后内部时钟传输收到 0xFF；与另一台 SoC 互连时对端一并断开
+    fn disconnect_serial(&mut self, py: Python<'_>) {
+        if let Link::Soc(peer) = std::mem::replace(&mut self.bus.serial.link, Link::Disconnected)
+            && let Ok(mut peer) = peer.try_borrow_mut(py) {
+            peer.bus.serial.link = Link::Disconnected;
+        }
+    }
+    // 串口回环：收到的是自己发出的字节
+    fn connect_serial_loopback(&mut self, py: Python<'_>) {
+        self.disconnect_serial(py);
+        self.bus.serial.link = Link::Loopback;
+    }
+    // 串口接到 Python 回调：内部时钟传输开始时调用 callback(发出的字节)，返回收到的字节（None 视为 0xFF）
+    #[pyo3(text_signature = "(callback)")]
+    fn connect_serial_callback(&mut self, py: Python<'_>, callback: PyObject) {
+        self.disconnect_serial(py);
+        self.bus.serial.link = Link::Callback(callback);
+    }
+    // 用连线与另一台 SoC 互连（双向）
+    #[pyo3(text_signature = "(other)")]
+    fn connect_serial_soc(slf: &Bound<'_, Self>, other: &Bound<'_, SoC>) -> PyResult<()> {
+        if slf.is(other) {return Err(PyValueError::new_err("不能把 SoC 连到自身"));}
+        let py = slf.py();
+        slf.borrow_mut().disconnect_serial(py);
+        other.borrow_mut().disconnect_serial(py);
+        slf.borrow_mut().bus.serial.link = Link::Soc(other.clone().unbind());
+        other.borrow_mut().bus.serial.link = Link::Soc(slf.clone().unbind());
+        Ok(())
+    }
+    // 经套接字连到另一个模拟器：address 为 TCP 的 "host:port" 或 Unix 套接字的 "unix:/path"
+    #[pyo3(text_signature = "(address)")]
+    fn connect_serial_socket(&mut self, py: Python<'_>, address: &str) -> PyResult<()> {
+        let socket = py.allow_threads(|| LinkSocket::connect(address))?;
+        self.disconnect_serial(py);
+        self.bus.serial.link = Link::Socket(socket);
+        Ok(())
+    }
+    // 在 address 上监听（格式同 connect_serial_socket），阻塞到另一个模拟器连入
+    #[pyo3(text_signature = "(address)")]
+    fn listen_serial_socket(&mut self, py: Python<'_>, address: &str) -> PyResult<()> {
+        let socket = py.allow_threads(|| LinkSocket::listen(address))?;
+        self.disconnect_serial(py);
+        self.bus.serial.link = Link::Socket(socket);
+        Ok(())
+    }
+    // 取出并清空 MBC5 震动马达的开关事件：[(M-cycle 计数, 是否开启), ...]
+    fn take_rumble_events(&mut self) -> Vec<(u64, bool)> {
+        match &mut self.bus.cart.mbc {
+            Mbc::Mbc5(mbc5) => std::mem::take(&mut mbc5.rumble_events),
+            _ => Vec::new(),
+        }
+    }
+    // 震动马达当前是否开启
+    fn is_rumbling(&self) -> bool {
+        matches!(&self.bus.cart.mbc, Mbc::Mbc5(mbc5) if mbc5.rumble)
+    }
+    // RTC 当前（未锁存）的 5 个寄存器：秒、分、时、天数低位、天数高位
+    fn get_rtc_registers(&mut self) -> Option<(u8, u8, u8, u8, u8)> {
+        match &mut self.bus.cart.mbc {
+            Mbc::Mbc3(mbc3) => mbc3.rtc.as_mut().map(|rtc| {
+                rtc.sync();
+                let [s, m, h, dl, dh] = rtc.regs;
+                (s, m, h, dl, dh)
+            }),
+            _ => None,
+        }
+    }
+    // 取寄存器 r8
+    #[pyo3(name = "get_r8", text_signature = "(r8pos)")]
+    fn py_get_r8(&self, r8pos: u8) -> PyResult<u8> {
+        Ok(self.get_r8(check_r8pos(r8pos)?))
+    }
+    // 写寄存器 r8
+    #[pyo3(name = "set_r8", text_signature = "(r8pos, new_r8)")]
+    fn py_set_r8(&mut self, r8pos: u8, new_r8: u8) -> PyResult<()> {
+        self.set_r8(check_r8pos(r8pos)?, new_r8);
+        Ok(())
+    }
+    // 取寄存器 r16
+    #[pyo3(name = "get_r16", text_signature = "(r16pos)")]
+    fn py_get_r16(&self, r16pos: u8) -> PyResult<u16> {
+        Ok(self.get_r16(check_r16pos(r16pos)?))
+    }
+    // 写寄存器 r16
+    #[pyo3(name = "set_r16", text_signature = "(r16pos, new_r16)")]
+    fn py_set_r16(&mut self, r16pos: u8, new_r16: u16) -> PyResult<()> {
+        self.set_r16(check_r16pos(r16pos)?, new_r16);
+        Ok(())
+    }
+    // 自增 r8
+    #[pyo3(name = "r8_inc", text_signature = "(r8pos)")]
+    fn py_r8_inc(&mut self, r8pos: u8) -> PyResult<()> {
+        self.r8_inc(check_r8pos(r8pos)?);
+        Ok(())
+    }
+    // 自减 r8
+    #[pyo3(name = "r8_dec", text_signature = "(r8pos)")]
+    fn py_r8_dec(&mut self, r8pos: u8) -> PyResult<()> {
+        self.r8_dec(check_r8pos(r8pos)?);
+        Ok(())
+    }
+    // 自增 r16
+    #[pyo3(name = "r16_inc", text_signature = "(r16pos)")]
+    fn py_r16_inc(&mut self, r16pos: u8) -> PyResult<()> {
+        self.r16_inc(check_r16pos(r16pos)?);
+        Ok(())
+    }
+    // 自减 r16
+    #[pyo3(name = "r16_dec", text_signature = "(r16pos)")]
+    fn py_r16_dec(&mut self, r16pos: u8) -> PyResult<()> {
+        self.r16_dec(check_r16pos(r16pos)?);
+        Ok(())
+    }
+    // 设置 bit
+    #[pyo3(name = "r8_set", text_signature = "(r8pos, b3)")]
+    fn py_r8_set(&mut self, r8pos: u8, b3: u8) -> PyResult<()> {
+        self.r8_set(check_r8pos(r8pos)?, check_b3(b3)?);
+        Ok(())
+    }
+    // 清除 bit
+    #[pyo3(name = "r8_res", text_signature = "(r8pos, b3)")]
+    fn py_r8_res(&mut self, r8pos: u8, b3: u8) -> PyResult<()> {
+        self.r8_res(check_r8pos(r8pos)?, check_b3(b3)?);
+        Ok(())
+    }
+    // 取 flag
+    #[pyo3(name = "get_flag", text_signature = "(flag_bit)")]
+    fn py_get_flag(&self, flag_bit: u8) -> PyResult<u8> {
+        Ok(self.get_flag(check_flag_bit(flag_bit)?))
+    }
+    // 设 flag
+    #[pyo3(name = "set_flag", text_signature = "(flag_bit)")]
+    fn py_set_flag(&mut self, flag_bit: u8) -> PyResult<()> {
+        self.set_flag(check_flag_bit(flag_bit)?);
+        Ok(())
+    }
+    // 清 flag
+    #[pyo3(name = "res_flag", text_signature = "(flag_bit)")]
+    fn py_res_flag(&mut self, flag_bit: u8) -> PyResult<()> {
+        self.res_flag(check_flag_bit(flag_bit)?);
+        Ok(())
+    }
+    // 取 SP
+    fn get_sp(&self) -> u16 {
+        self.sp
+    }
+    // 设 SP
+    #[pyo3(text_signature = "(new_sp)")]
+    fn set_sp(&mut self, new_sp: u16) {
+        self.sp = new_sp;
+    }
+    // 取 PC
+    fn get_pc(&self) -> u16 {
+        self.pc
+    }
+    // 设 PC
+    #[pyo3(text_signature = "(new_pc)")]
+    fn set_pc(&mut self, new_pc: u16) {
+        self.pc = new_pc;
+    }
+    // 增加 PC
+    fn pc_inc(&mut self, n: u16) {
+        self.pc = self.pc.wrapping_add(n);
+    }
+    // 减少 PC
+    fn pc_dec(&mut self, n: u16) {
+        self.pc = self.pc.wrapping_sub(n);
+    }
+    // 取 CYC
+    fn get_cyc(&self) -> u128 {
+        self.cyc
+    }
+    // 增加 CYC，并以同样的周期数推进总线上的硬件
+    fn cyc_inc(&mut self, n: u128) {
+        self.cyc += n;
+        self.bus.tick(n as u64);
+    }
+    // 经总线读内存
+    #[pyo3(text_signature = "(addr)")]
+    fn ram_read(&self, addr: u16) -> u8 {
+        self.bus.read(addr)
+    }
+    // 经总线写内存
+    #[pyo3(text_signature = "(addr, data)")]
+    fn ram_write(&mut self, addr: u16, data: u8) {
+        self.bus.write(addr, data);
+    }
+    // 读取整个地址空间
+    fn ram_data(&self) -> [u8; 65536] {
+        self.bus.snapshot()
+    }
+    // 取 ROM（按卡带 ROM 的原始偏移）
+    #[pyo3(text_signature = "(addr)")]
+    fn read_rom(&self, addr: u16) -> PyResult<u8> {
+        self.bus.cart.rom.get(addr as usize).copied().ok_or_else(|| rom_out_of_range(addr))
+    }
+    // 是否处于 HALT 低功耗状态
+    fn halt(&self) -> bool {
+        self.halted
+    }
+    // 取 IME
+    fn get_ime(&self) -> bool {
+        self.ime
+    }
+    // EI 之后、IME 尚未生效的挂起状态
+    fn get_ime_pending(&self) -> bool {
+        self.ime_pending
+    }
+    // 启用 IME（立即生效）
+    fn set_ime(&mut self) {
+        self.ime = true;
+        self.ime_pending = false;
+    }
+    // 禁止 IME（同时取消 EI 的挂起）
+    fn res_ime(&mut self) {
+        self.ime = false;
+        self.ime_pending = false;
+    }
+    // 手动发出中断请求（置位 IF）
+    #[pyo3(text_signature = "(kind)")]
+    fn request_interrupt(&mut self, kind: Interrupt) {
+        self.bus.request_interrupt(kind);
+    }
+    // 智能 flag
+    #[pyo3(text_signature = "(num1, num2, n, u8_mode)")]
+    fn smart_flag(&mut self, num1: u16, num2: u16, n: bool, u8_mode: bool) {
+        match u8_mode {
+            true => {
+                let num1_lo4: u8 = (num1 & 0xf) as u8;
+                let num2_lo4: u8 = (num2 & 0xf) as u8;
+
+                if n == false {
+                    if (num1 + num2) & 0xff == 0 { self.set_flag(7) } else { self.res_flag(7) };
+                    self.res_flag(6);
+                    if num1_lo4 + num2_lo4 > 0xf { self.set_flag(5) } else { self.res_flag(5) };
+                    if num1+ num2 > 0xff { self.set_flag(4) } else { self.res_flag(4) };
+                } else {
+                    if (num1 as i16) - (num2 as i16) == 0 { self.set_flag(7) } else { self.res_flag(7) };
+                    self.set_flag(6);
+                    if (num1_lo4 as i8) - (num2_lo4 as i8) < 0 { self.set_flag(5) } else { self.res_flag(5) };
+                    if (num1 as i16) - (num2 as i16) < 0 { self.set_flag(4) } else { self.res_flag(4) };
+                }
+            },
+            false => {
+                let num1: u32 = num1 as u32;
+                let num2: u32 = num2 as u32;
+                let num1_lo12: u32 = num1 & 0xfff;
+                let num2_lo12: u32 = num2 & 0xfff;
+
+                if n == false {
+                    self.res_flag(6);
+                    if num1_lo12 + num2_lo12 > 0xfff { self.set_flag(5) } else { self.res_flag(5) };
+                    if num1 + num2 > 0xffff { self.set_flag(4) } else { self.res_flag(4) };
+                } else {
+                    self.set_flag(6);
+                    if (num1_lo12 as i32) - (num2_lo12 as i32) < 0 { self.set_flag(5) } else { self.res_flag(5) };
+                    if (num1 as i32) - (num2 as i32) < 0 { self.set_flag(4) } else { self.res_flag(4) };
+                }
+            }
+        }
+    }
+    // 根据区间提供ROM数据
+    #[pyo3(text_signature = "(length)")]
+    fn give_opt_code(&self, length: u8) -> Vec<u8> {
+        return_instruction(self, length)
+    }
+
+    // 查看寄存器状况
+    fn disp(&self) {
+        println!("+----------- GameBoy SM83 Register Display -----------+");
+        println!("|Flag Register: {} - {} - {} - {}               IME: {}|", 
+        if (self.get_flag(7)) == 1 {"Z"} else {"0"},
+        if (self.get_flag(6)) == 1 {"N"} else {"0"},
+        if (self.get_flag(5)) == 1 {"H"} else {"0"},
+        if (self.get_flag(4)) == 1 {"C"} else {"0"},
+        if (self.ime) == true {"TRUE "} else {"FALSE"});
+        println!("|R16:        {:5},      {:5},      {:5},      {:5}|",
+                 self.get_r16(3), self.get_r16(0), self.get_r16(1), self.get_r16(2));
+        println!("|R8_10: {:4}, {:4}, {:4}, {:4}, {:4}, {:4}, {:4}, {:4}|", 
+                 self.reg.a, self.reg.f, self.reg.b, self.reg.c,
+                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
+        println!("|R8_16: 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}, 0x{:02x}|", 
+                 self.reg.a, self.reg.f, self.reg.b, self.reg.c,
+                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
+        println!("|A: {0:08b},  F: {1:08b},  B: {2:08b},  C: {3:08b}|",
+                 self.reg.a, self.reg.f, self.reg.b, self.reg.c);
+        println!("|D: {0:08b},  E: {1:08b},  H: {2:08b},  L: {3:08b}|",
+                 self.reg.d, self.reg.e, self.reg.h, self.reg.l);
+        println!("|PC:{:08},  SP:0x{:04x},    INST_CYC:{:16}|",
+                 self.pc, self.sp, self.cyc);
+        println!("+----------ROM Next 3 Bytes [0x{0:02x} 0x{1:02x} 0x{2:02x}]----------+\n",
+                 self.ram_read(self.get_pc()), self.ram_read(self.get_pc().wrapping_add(1)), self.ram_read(self.get_pc().wrapping_add(2)),)
+    }
+    // 设置非法操作码的处理策略（默认 LockUp）；只有 Callback 策略接受且必须提供 callback(pc, opcode)，
+    // 回调返回整数时以其作为新的 PC，返回 None 时跳过该字节继续执行。
+    // 回调在指令执行途中被调用，此时 SoC 正被占用，回调内不能再访问该 SoC
+    #[pyo3(signature = (policy, callback=None), text_signature = "(policy, callback=None)")]
+    fn set_illegal_opcode_policy(&mut self, policy: IllegalOpcodePolicy, callback: Option<PyObject>) -> PyResult<()> {
+        match (policy, &callback) {
+            (IllegalOpcodePolicy::Callback, None) => return Err(PyValueError::new_err("Callback 策略需要提供 callback")),
+            (IllegalOpcodePolicy::LockUp | IllegalOpcodePolicy::Raise, Some(_)) => {
+                return Err(PyValueError::new_err("只有 Callback 策略可以提供 callback"));
+            }
+            _ => {},
+        }
+        self.illegal_policy = policy;
+        self.illegal_callback = callback;
+        Ok(())
+    }
+    // 取非法操作码的处理策略
+    fn get_illegal_opcode_policy(&self) -> IllegalOpcodePolicy {
+        self.illegal_policy
+    }
+    // CPU 是否因非法操作码而锁死
+    fn is_locked(&self) -> bool {
+        self.locked
+    }
+    // 显示寄存器 r8
+    fn disp_r8(&self) {
+        println!("============ GameBoy SM83 R8B Display ============");
+        println!("Flag Register: {} - {} - {} - {}", 
+                 if (self.get_flag(7)) == 1 {"Z"} else {"0"},
+                 if (self.get_flag(6)) == 1 {"N"} else {"0"},
+                 if (self.get_flag(5)) == 1 {"H"} else {"0"},
+                 if (self.get_flag(4)) == 1 {"C"} else {"0"});
+        println!("A: {0:08b}, F: {1:08b}, B: {2:08b}, C: {3:08b}", self.reg.a, self.reg.f, self.reg.b, self.reg.c);
+        println!("D: {0:08b}, E: {1:08b}, H: {2:08b}, L: {3:08b}\n", self.reg.d, self.reg.e, self.reg.h, self.reg.l);
+    }
+
+    // 执行下一个指令（或响应一个中断）
+    fn one_step(&mut self) -> PyResult<()> {
+        // EI 的效果延迟到其后一条指令执行完毕
+        let ei_delay = self.ime_pending;
+        if self.locked {
+            // 锁死后不再取指，中断也无法唤醒
+            self.cyc_inc(1);
+        } else if self.halted && self.bus.pending_interrupts() == 0 {
+            // HALT 期间不取指，只推进周期，直到 IE & IF 非零时唤醒
+            self.bus.halted = true;
+            self.cyc_inc(1);
+            self.bus.halted = false;
+        } else {
+            self.halted = false;
+            if !service_interrupt(self) {process_by_step(self)?;}
+        }
+        if ei_delay && self.ime_pending {self.set_ime();}
+        // VRAM DMA 与速度切换期间 CPU 暂停
+        while self.bus.stall > 0 {
+            let stall = std::mem::take(&mut self.bus.stall);
+            self.cyc_inc(stall as u128);
+        }
+        if let Some(err) = self.bus.serial.error.take() {return Err(err);}
+        Ok(())
+    }
+    // Python 垃圾回收：回调与串口对端可能与本对象形成引用环
+    fn __traverse__(&self, visit: PyVisit<'_>) -> Result<(), PyTraverseError> {
+        if let Some(callback) = &self.illegal_callback {visit.call(callback)?;}
+        self.bus.serial.traverse(&visit)
+    }
+    fn __clear__(&mut self) {
+        self.illegal_callback = None;
+        self.bus.serial.link = Link::Disconnected;
+    }
+}
+
+// 内部构造，以及寄存器与 flag 的内部操作（索引由指令译码保证合法，Python 侧经 py_* 校验后调用）
+impl SoC {
+    // 以卡带 ROM 构建 SoC，寄存器与内存全部清零
+    fn with_rom(rom: Vec<u8>) -> Self {
+        Self {
+            reg: Register { a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0 },
+            pc: 0,
+            sp: 0,
+            cyc: 0,
+            ime: false,
+            ime_pending: false,
+            halted: false,
+            halt_bug: false,
+            locked: false,
+            illegal_policy: IllegalOpcodePolicy::LockUp,
+            illegal_callback: None,
+            autosave: None,
+            model: Model::Dmg,
+            bus: Bus::new(Cartridge::new(rom)),
+        }
+    }
+    // 构建 SoC，并把卡带头部的问题作为 Python 警告发出；model 缺省时按头部的 CGB 标志选择
+    fn load(py: Python<'_>, rom: Vec<u8>, model: Option<Model>) -> PyResult<Self> {
+        let mut soc = Self::with_rom(rom);
+        soc.set_model(model.unwrap_or(Model::from_cgb_flag(soc.bus.cart.header.cgb_flag)));
+        let warnings = &soc.bus.cart.header.warnings;
+        if !warnings.is_empty() {
+            let message = CString::new(format!("卡带头部：{}", warnings.join("；")))?;
+            PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1)?;
+        }
+        Ok(soc)
+    }
+
+    // 设置硬件型号；CGB 运行头部未声明支持 CGB 的卡带时进入 DMG 兼容模式：
+    // CGB 专有寄存器不可用，画面沿用 DMG 的调色板与精灵优先级
+    fn set_model(&mut self, model: Model) {
+        self.model = model;
+        self.bus.set_cgb(model == Model::Cgb && Model::from_cgb_flag(self.bus.cart.header.cgb_flag) == Model::Cgb);
+    }
+
+    // 取寄存器 r8
+    fn get_r8(&self, r8pos: u8) -> u8 {
+        match r8pos {
+            0 => self.reg.b,
+            1 => self.reg.c,
+            2 => self.reg.d,
+            3 => self.reg.e,
+            4 => self.reg.h,
+            5 => self.reg.l,
+            6 => self.reg.f,
+            7 => self.reg.a,
+            _ => unreachable!("非法的寄存器位置：{r8pos}")
+        }
+    }
+    // 写寄存器 r8
+    fn set_r8(&mut self, r8pos: u8, new_r8: u8) {
+        match r8pos {
+            0 => {self.reg.b = new_r8},
+            1 => {self.reg.c = new_r8},
+            2 => {self.reg.d = new_r8},
+            3 => {self.reg.e = new_r8},
+            4 => {self.reg.h = new_r8},
+            5 => {self.reg.l = new_r8},
+            6 => {self.reg.f = new_r8},
+            7 => {self.reg.a = new_r8},
+            _ => unreachable!("非法的寄存器位置: {}", r8pos),
+        }
+    }
+    // 取寄存器 r16
+    fn get_r16(&self, r16pos: u8) -> u16 {
+        match r16pos {
+            0 => ((self.reg.b as u16) << 8) + (self.reg.c as u16),
+            1 => ((self.reg.d as u16) << 8) + (self.reg.e as u16),
+            2 => ((self.reg.h as u16) << 8) + (self.reg.l as u16),
+            3 => ((self.reg.a as u16) << 8) + (self.reg.f as u16),
+            _ => unreachable!("非法的寄存器位置: {}", r16pos),
+        }
+    }
+    // 写寄存器 r16
+    fn set_r16(&mut self, r16pos: u8, new_r16: u16) {
+        match r16pos {
+            0 => {
+                self.reg.b = (new_r16 >> 8) as u8;
+                self.reg.c = (new_r16 & 0xff) as u8;
+            },
+            1 => {
+                self.reg.d = (new_r16 >> 8) as u8;
+                self.reg.e = (new_r16 & 0xff) as u8;
+            },
+            2 => {
+                self.reg.h = (new_r16 >> 8) as u8;
+                self.reg.l = (new_r16 & 0xff) as u8;
+            },
+            3 => {
+                self.reg.a = (new_r16 >> 8) as u8;
+                self.reg.f = (new_r16 & 0xff) as u8;
+            },
+            _ => unreachable!("非法的寄存器位置: {}", r16pos),
+        }
+    }
+    // 自增 r8
+    fn r8_inc(&mut self, r8pos: u8) {
+        match r8pos {
+            0 => self.reg.b = self.reg.b.wrapping_add(1),
+            1 => self.reg.c = self.reg.c.wrapping_add(1),
+            2 => self.reg.d = self.reg.d.wrapping_add(1),
+            3 => self.reg.e = self.reg.e.wrapping_add(1),
+            4 => self.reg.h = self.reg.h.wrapping_add(1),
+            5 => self.reg.l = self.reg.l.wrapping_add(1),
+            6 => self.reg.f = self.reg.f.wrapping_add(1),
+            7 => self.reg.a = self.reg.a.wrapping_add(1),
+            _ => unreachable!("非法的寄存器位置: {}", r8pos),
+        }
+    }
+    // 自减 r8
+    fn r8_dec(&mut self, r8pos: u8) {
+        match r8pos {
+            0 => self.reg.b = self.reg.b.wrapping_sub(1),
+            1 => self.reg.c = self.reg.c.wrapping_sub(1),
+            2 => self.reg.d = self.reg.d.wrapping_sub(1),
+            3 => self.reg.e = self.reg.e.wrapping_sub(1),
+            4 => self.reg.h = self.reg.h.wrapping_sub(1),
+            5 => self.reg.l = self.reg.l.wrapping_sub(1),
+            6 => self.reg.f = self.reg.f.wrapping_sub(1),
+            7 => self.reg.a = self.reg.a.wrapping_sub(1),
+            _ => unreachable!("非法的寄存器位置: {}", r8pos),
+        }
+    }
+    // 自增 r16
+    fn r16_inc(&mut self, r16pos: u8) {
+        match r16pos {
+               0 => {self.set_r16(0, self.get_r16(0).wrapping_add(1));},
+               1 => {self.set_r16(1, self.get_r16(1).wrapping_add(1));},
+               2 => {self.set_r16(2, self.get_r16(2).wrapping_add(1));},
+               3 => {self.set_r16(3, self.get_r16(3).wrapping_add(1));},
+               _ => unreachable!("非法的寄存器位置: {}", r16pos),
+        }
+    }
+    // 自减 r16
+    fn r16_dec(&mut self, r16pos: u8) {
+        match r16pos {
+               0 => {self.set_r16(0, self.get_r16(0).wrapping_sub(1));},
+               1 => {self.set_r16(1, self.get_r16(1).wrapping_sub(1));},
+               2 => {self.set_r16(2, self.get_r16(2).wrapping_sub(1));},
+               3 => {self.set_r16(3, self.get_r16(3).wrapping_sub(1));},
+               _ => unreachable!("非法的寄存器位置: {}", r16pos),
+        }
+    }
+    // 设置 bit
+    fn r8_set(&mut self, r8pos: u8, b3: u8) {
+        match r8pos {
+            0 => {self.reg.b = self.reg.b | (1 << b3);},
+            1 => {self.reg.c = self.reg.c | (1 << b3);},
+            2 => {self.reg.d = self.reg.d | (1 << b3);},
+            3 => {self.reg.e = self.reg.e | (1 << b3);},
+            4 => {self.reg.h = self.reg.h | (1 << b3);},
+            5 => {self.reg.l = self.reg.l | (1 << b3);},
+            6 => {self.reg.f = self.reg.f | (1 << b3);},
+            7 => {self.reg.a = self.reg.a | (1 << b3);},
+            _ => unreachable!("非法的寄存器位置: {}", r8pos),
+        }
+    }
+    // 清除 bit
+    fn r8_res(&mut self, r8pos: u8, b3: u8) {
+        match r8pos {
+            0 => {self.reg.b = self.reg.b & (0xff - (1 << b3));},
+            1 => {self.reg.c = self.reg.c & (0xff - (1 << b3));},
+            2 => {self.reg.d = self.reg.d & (0xff - (1 << b3));},
+            3 => {self.reg.e = self.reg.e & (0xff - (1 << b3));},
+            4 => {self.reg.h = self.reg.h & (0xff - (1 << b3));},
+            5 => {self.reg.l = self.reg.l & (0xff - (1 << b3));},
+            6 => {self.reg.f = self.reg.f & (0xff - (1 << b3));},
+            7 => {self.reg.a = self.reg.a & (0xff - (1 << b3));},
+            _ => unreachable!("非法的寄存器位置: {}", r8pos),
+        }
+    }
+    // 取 flag
+    fn get_flag(&self, flag_bit: u8) -> u8 {
+        match flag_bit {
+            4 => {(self.reg.f >> 4) & 1},
+            5 => {(self.reg.f >> 5) & 1},
+            6 => {(self.reg.f >> 6) & 1},
+            7 => {(self.reg.f >> 7) & 1},
+            _ => unreachable!("非法的 flag 位: {}", flag_bit),
+        }
+    }
+    // 设 flag
+    fn set_flag(&mut self, flag_bit: u8) {
+        match flag_bit {
+            4 => {self.reg.f = self.reg.f | (1 << 4)},
+            5 => {self.reg.f = self.reg.f | (1 << 5)},
+            6 => {self.reg.f = self.reg.f | (1 << 6)},
+            7 => {self.reg.f = self.reg.f | (1 << 7)},
+            _ => unreachable!("非法的 flag 位: {}", flag_bit),
+        }
+    }
+    // 清 flag
+    fn res_flag(&mut self, flag_bit: u8) {
+        match flag_bit {
+            4 => self.reg.f = self.reg.f & (0xff - (1 << 4)),
+            5 => self.reg.f = self.reg.f & (0xff - (1 << 5)),
+            6 => self.reg.f = self.reg.f & (0xff - (1 << 6)),
+            7 => self.reg.f = self.reg.f & (0xff - (1 << 7)),
+            _ => unreachable!("非法的 flag 位: {}", flag_bit),
+        }
+    }
+}
+
+
+// 自动存档：SoC 被销毁时把外部 RAM 写回文件，只有带电池的卡带才写
+impl Drop for SoC {
+    fn drop(&mut self) {
+        // 析构时无法抛出异常：写入失败作为 Python 警告发出，警告被设为错误时交给 sys.unraisablehook
+        if let Some(path) = self.autosave.take()
+            && self.bus.cart.header.has_battery
+            && let Err(err) = std::fs::write(&path, self.bus.cart.export_save()) {
+            Python::with_gil(|py| {
+                let message = CString::new(format!("自动存档写入 {} 失败：{}", path.display(), err)).unwrap_or_default();
+                if let Err(err) = PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1) {
+                    err.write_unraisable(py, None);
+                }
+            });
+        }
+    }
+}
+
+// 把 ROM 数据一次性复制进 Rust：优先走缓冲区协议，否则按整数序列提取
+fn rom_bytes(data: &Bound<'_, PyAny>) -> PyResult<Vec<u8>> {
+    match PyBuffer::<u8>::get(data) {
+        Ok(buffer) => buffer.to_vec(data.py()),
+        Err(_) => data.extract::<Vec<u8>>(),
+    }
+}
+
+// 调配函数
+fn return_instruction(soc: &SoC, length: u8) -> Vec<u8> {
+    let mut opt_code = Vec::new();
+    let start_point = soc.get_pc();
+
+    for i in 0..length as u16 {
+        opt_code.push(soc.ram_read(start_point.wrapping_add(i)));
+    }
+    opt_code
+}
+
+
+// 中断响应：IME 打开且 IE & IF 非零时，压入 PC 并跳转到最高优先级的中断向量
+fn service_interrupt(soc: &mut SoC) -> bool {
+    if !soc.ime {return false;}
+    let Some(int) = Interrupt::highest(soc.bus.pending_interrupts()) else {return false;};
+    soc.res_ime();
+    soc.bus.int_flag &= !int.bit();
+    stack_push(soc, soc.get_pc());
+    soc.set_pc(int.vector());
+    soc.cyc_inc(5);
+    true
+}
+
+// 非法操作码：按 SoC 上设置的策略处理
+fn illegal_op(soc: &mut SoC, opcode: u8) -> PyResult<()> {
+    let pc = soc.get_pc();
+    match soc.illegal_policy {
+        IllegalOpcodePolicy::LockUp => {
+            soc.locked = true;
+            soc.cyc_inc(1);
+        }
+        IllegalOpcodePolicy::Raise => {return Err(illegal_opcode(pc, opcode));}
+        IllegalOpcodePolicy::Callback => {
+            let new_pc = Python::with_gil(|py| -> PyResult<Option<u16>> {
+                match &soc.illegal_callback {
+                    Some(callback) => callback.call1(py, (pc, opcode))?.extract(py),
+                    None => Err(illegal_opcode(pc, opcode)),
+                }
+            })?;
+            soc.set_pc(new_pc.unwrap_or(pc.wrapping_add(1)));
+            soc.cyc_inc(1);
+        }
+    }
+    Ok(())
+}
+
+fn process_by_step(soc: &mut SoC) -> PyResult<()> {
+    let pc = soc.get_pc();
+    let code = if soc.halt_bug {
+        // HALT bug：取操作码后 PC 未自增，操作码字节被再读一次
+        soc.halt_bug = false;
+        soc.set_pc(pc.wrapping_sub(1));
+        [
+            soc.ram_read(pc) as u16,
+            soc.ram_read(pc) as u16,
+            soc.ram_read(pc.wrapping_add(1)) as u16,
+        ]
+    } else {
+        [
+            soc.ram_read(pc) as u16,
+            soc.ram_read(pc.wrapping_add(1)) as u16,
+            soc.ram_read(pc.wrapping_add(2)) as u16,
+        ]
+    };
+    match code[0] as u8 {
+        0x00 => {nop(soc)},
+        0x01 => {ld_r16_n16(soc, R16::BC, b8x2_le(code[1], code[2]))},
+        0x02 => {ld_r16ram_a(soc, R16RAM::BC)},
+        0x03 => {inc_r16(soc, R16::BC)},
+        0x04 => {inc_r8(soc, R8::B)},
+        0x05 => {dec_r8(soc, R8::B)},
+        0x06 => {ld_r8_n8(soc, R8::B, code[1])},
+        0x07 => {rlca(soc)},
+        0x08 => {ld_a16_sp(soc, b8x2_le(code[1], code[2]))},
+        0x09 => {add_hl_r16(soc, R16::BC)},
+        0x0A => {ld_a_r16ram(soc, R16RAM::BC)},
+        0x0B => {dec_r16(soc, R16::BC)},
+        0x0C => {inc_r8(soc, R8::C)},
+        0x0D => {dec_r8(soc, R8::C)},
+        0x0E => {ld_r8_n8(soc, R8::C, code[1])},
+        0x0F => {rrca(soc)},
+        0x10 => {stop(soc)},
+        0x11 => {ld_r16_n16(soc, R16::DE, b8x2_le(code[1], code[2]))},
+        0x12 => {ld_r16ram_a(soc, R16RAM::DE)},
+        0x13 => {inc_r16(soc, R16::DE)},
+        0x14 => {inc_r8(soc, R8::D)},
+        0x15 => {dec_r8(soc, R8::D)},
+        0x16 => {ld_r8_n8(soc, R8::D, code[1])},
+        0x17 => {rla(soc)},
+        0x18 => {jr_e8(soc, code[1] as i8)},
+        0x19 => {add_hl_r16(soc, R16::DE)},
+        0x1A => {ld_a_r16ram(soc, R16RAM::DE)},
+        0x1B => {dec_r16(soc, R16::DE)},
+        0x1C => {inc_r8(soc, R8::E)},
+        0x1D => {dec_r8(soc, R8::E)},
+        0x1E => {ld_r8_n8(soc, R8::E, code[1])},
+        0x1F => {rra(soc)},
+        0x20 => {jr_cond_e8(soc, COND::NZ, code[1] as i8)},
+        0x21 => {ld_r16_n16(soc, R16::HL, b8x2_le(code[1], code[2]))},
+        0x22 => {ld_r16ram_a(soc, R16RAM::HLI)},
+        0x23 => {inc_r16(soc, R16::HL)},
+        0x24 => {inc_r8(soc, R8::H)},
+        0x25 => {dec_r8(soc, R8::H)},
+        0x26 => {ld_r8_n8(soc, R8::H, code[1])},
+        0x27 => {daa(soc)},
+        0x28 => {jr_cond_e8(soc, COND::Z, code[1] as i8)},
+        0x29 => {add_hl_r16(soc, R16::HL)},
+        0x2A => {ld_a_r16ram(soc, R16RAM::HLI)},
+        0x2B => {dec_r16(soc, R16::HL)},
+        0x2C => {inc_r8(soc, R8::L)},
+        0x2D => {dec_r8(soc, R8::L)},
+        0x2E => {ld_r8_n8(soc, R8::L, code[1])},
+        0x2F => {cpl(soc)},
+        0x30 => {jr_cond_e8(soc, COND::NC, code[1] as i8)},
+        0x31 => {ld_r16_n16(soc, R16::SP, b8x2_le(code[1], code[2]))},
+        0x32 => {ld_r16ram_a(soc, R16RAM::HLD)},
+        0x33 => {inc_r16(soc, R16::SP)},
+        0x34 => {inc_r8(soc, R8::HL)},
+        0x35 => {dec_r8(soc, R8::HL)},
+        0x36 => {ld_r8_n8(soc, R8::HL, code[1])},
+        0x37 => {scf(soc)},
+        0x38 => {jr_cond_e8(soc, COND::C, code[1] as i8)},
+        0x39 => {add_hl_r16(soc, R16::SP)},
+        0x3A => {ld_a_r16ram(soc, R16RAM::HLD)},
+        0x3B => {dec_r16(soc, R16::SP)},
+        0x3C => {inc_r8(soc, R8::A)},
+        0x3D => {dec_r8(soc, R8::A)},
+        0x3E => {ld_r8_n8(soc, R8::A, code[1])},
+        0x3F => {ccf(soc)},
+        0x40 => {ld_r8_r8(soc, R8::B, R8::B)},
+        0x41 => {ld_r8_r8(soc, R8::B, R8::C)},
+        0x42 => {ld_r8_r8(soc, R8::B, R8::D)},
+        0x43 => {ld_r8_r8(soc, R8::B, R8::E)},
+        0x44 => {ld_r8_r8(soc, R8::B, R8::H)},
+        0x45 => {ld_r8_r8(soc, R8::B, R8::L)},
+        0x46 => {ld_r8_r8(soc, R8::B, R8::HL)},
+        0x47 => {ld_r8_r8(soc, R8::B, R8::A)},
+        0x48 => {ld_r8_r8(soc, R8::C, R8::B)},
+        0x49 => {ld_r8_r8(soc, R8::C, R8::C)},
+        0x4A => {ld_r8_r8(soc, R8::C, R8::D)},
+        0x4B => {ld_r8_r8(soc, R8::C, R8::E)},
+        0x4C => {ld_r8_r8(soc, R8::C, R8::H)},
+        0x4D => {ld_r8_r8(soc, R8::C, R8::L)},
+        0x4E => {ld_r8_r8(soc, R8::C, R8::HL)},
+        0x4F => {ld_r8_r8(soc, R8::C, R8::A)},
+        0x50 => {ld_r8_r8(soc, R8::D, R8::B)},
+        0x51 => {ld_r8_r8(soc, R8::D, R8::C)},
+        0x52 => {ld_r8_r8(soc, R8::D, R8::D)},
+        0x53 => {ld_r8_r8(soc, R8::D, R8::E)},
+        0x54 => {ld_r8_r8(soc, R8::D, R8::H)},
+        0x55 => {ld_r8_r8(soc, R8::D, R8::L)},
+        0x56 => {ld_r8_r8(soc, R8::D, R8::HL)},
+        0x57 => {ld_r8_r8(soc, R8::D, R8::A)},
+        0x58 => {ld_r8_r8(soc, R8::E, R8::B)},
+        0x59 => {ld_r8_r8(soc, R8::E, R8::C)},
+        0x5A => {ld_r8_r8(soc, R8::E, R8::D)},
+        0x5B => {ld_r8_r8(soc, R8::E, R8::E)},
+        0x5C => {ld_r8_r8(soc, R8::E, R8::H)},
+        0x5D => {ld_r8_r8(soc, R8::E, R8::L)},
+        0x5E => {ld_r8_r8(soc, R8::E, R8::HL)},
+        0x5F => {ld_r8_r8(soc, R8::E, R8::A)},
+        0x60 => {ld_r8_r8(soc, R8::H, R8::B)},
+        0x61 => {ld_r8_r8(soc, R8::H, R8::C)},
+        0x62 => {ld_r8_r8(soc, R8::H, R8::D)},
+        0x63 => {ld_r8_r8(soc, R8::H, R8::E)},
+        0x64 => {ld_r8_r8(soc, R8::H, R8::H)},
+        0x65 => {ld_r8_r8(soc, R8::H, R8::L)},
+        0x66 => {ld_r8_r8(soc, R8::H, R8::HL)},
+        0x67 => {ld_r8_r8(soc, R8::H, R8::A)},
+        0x68 => {ld_r8_r8(soc, R8::L, R8::B)},
+        0x69 => {ld_r8_r8(soc, R8::L, R8::C)},
+        0x6A => {ld_r8_r8(soc, R8::L, R8::D)},
+        0x6B => {ld_r8_r8(soc, R8::L, R8::E)},
+        0x6C => {ld_r8_r8(soc, R8::L, R8::H)},
+        0x6D => {ld_r8_r8(soc, R8::L, R8::L)},
+        0x6E => {ld_r8_r8(soc, R8::L, R8::HL)},
+        0x6F => {ld_r8_r8(soc, R8::L, R8::A)},
+        0x70 => {ld_r8_r8(soc, R8::HL, R8::B)},
+        0x71 => {ld_r8_r8(soc, R8::HL, R8::C)},
+        0x72 => {ld_r8_r8(soc, R8::HL, R8::D)},
+        0x73 => {ld_r8_r8(soc, R8::HL, R8::E)},
+        0x74 => {ld_r8_r8(soc, R8::HL, R8::H)},
+        0x75 => {ld_r8_r8(soc, R8::HL, R8::L)},
+        0x76 => {halt(soc);},
+        0x77 => {ld_r8_r8(soc, R8::HL, R8::A)},
+        0x78 => {ld_r8_r8(soc, R8::A, R8::B)},
+        0x79 => {ld_r8_r8(soc, R8::A, R8::C)},
+        0x7A => {ld_r8_r8(soc, R8::A, R8::D)},
+        0x7B => {ld_r8_r8(soc, R8::A, R8::E)},
+        0x7C => {ld_r8_r8(soc, R8::A, R8::H)},
+        0x7D => {ld_r8_r8(soc, R8::A, R8::L)},
+        0x7E => {ld_r8_r8(soc, R8::A, R8::HL)},
+        0x7F => {ld_r8_r8(soc, R8::A, R8::A)},
+        0x80 => {alu_a_r8(soc, R8::B,  ALU3::ADD)},
+        0x81 => {alu_a_r8(soc, R8::C,  ALU3::ADD)},
+        0x82 => {alu_a_r8(soc, R8::D,  ALU3::ADD)},
+        0x83 => {alu_a_r8(soc, R8::E,  ALU3::ADD)},
+        0x84 => {alu_a_r8(soc, R8::H,  ALU3::ADD)},
+        0x85 => {alu_a_r8(soc, R8::L,  ALU3::ADD)},
+        0x86 => {alu_a_r8(soc, R8::HL, ALU3::ADD)},
+        0x87 => {alu_a_r8(soc, R8::A,  ALU3::ADD)},
+        0x88 => {alu_a_r8(soc, R8::B,  ALU3::ADC)},
+        0x89 => {alu_a_r8(soc, R8::C,  ALU3::ADC)},
+        0x8A => {alu_a_r8(soc, R8::D,  ALU3::ADC)},
+        0x8B => {alu_a_r8(soc, R8::E,  ALU3::ADC)},
+        0x8C => {alu_a_r8(soc, R8::H,  ALU3::ADC)},
+        0x8D => {alu_a_r8(soc, R8::L,  ALU3::ADC)},
+        0x8E => {alu_a_r8(soc, R8::HL, ALU3::ADC)},
+        0x8F => {alu_a_r8(soc, R8::A,  ALU3::ADC)},
+        0x90 => {alu_a_r8(soc, R8::B,  ALU3::SUB)},
+        0x91 => {alu_a_r8(soc, R8::C,  ALU3::SUB)},
+        0x92 => {alu_a_r8(soc, R8::D,  ALU3::SUB)},
+        0x93 => {alu_a_r8(soc, R8::E,  ALU3::SUB)},
+        0x94 => {alu_a_r8(soc, R8::H,  ALU3::SUB)},
+        0x95 => {alu_a_r8(soc, R8::L,  ALU3::SUB)},
+        0x96 => {alu_a_r8(soc, R8::HL, ALU3::SUB)},
+        0x97 => {alu_a_r8(soc, R8::A,  ALU3::SUB)},
+        0x98 => {alu_a_r8(soc, R8::B,  ALU3::SBC)},
+        0x99 => {alu_a_r8(soc, R8::C,  ALU3::SBC)},
+        0x9A => {alu_a_r8(soc, R8::D,  ALU3::SBC)},
+        0x9B => {alu_a_r8(soc, R8::E,  ALU3::SBC)},
+        0x9C => {alu_a_r8(soc, R8::H,  ALU3::SBC)},
+        0x9D => {alu_a_r8(soc, R8::L,  ALU3::SBC)},
+        0x9E => {alu_a_r8(soc, R8::HL, ALU3::SBC)},
+        0x9F => {alu_a_r8(soc, R8::A,  ALU3::SBC)},
+        0xA0 => {alu_a_r8(soc, R8::B,  ALU3::AND)},
+        0xA1 => {alu_a_r8(soc, R8::C,  ALU3::AND)},
+        0xA2 => {alu_a_r8(soc, R8::D,  ALU3::AND)},
+        0xA3 => {alu_a_r8(soc, R8::E,  ALU3::AND)},
+        0xA4 => {alu_a_r8(soc, R8::H,  ALU3::AND)},
+        0xA5 => {alu_a_r8(soc, R8::L,  ALU3::AND)},
+        0xA6 => {alu_a_r8(soc, R8::HL, ALU3::AND)},
+        0xA7 => {alu_a_r8(soc, R8::A,  ALU3::AND)},
+        0xA8 => {alu_a_r8(soc, R8::B,  ALU3::XOR)},
+        0xA9 => {alu_a_r8(soc, R8::C,  ALU3::XOR)},
+        0xAA => {alu_a_r8(soc, R8::D,  ALU3::XOR)},
+        0xAB => {alu_a_r8(soc, R8::E,  ALU3::XOR)},
+        0xAC => {alu_a_r8(soc, R8::H,  ALU3::XOR)},
+        0xAD => {alu_a_r8(soc, R8::L,  ALU3::XOR)},
+        0xAE => {alu_a_r8(soc, R8::HL, ALU3::XOR)},
+        0xAF => {alu_a_r8(soc, R8::A,  ALU3::XOR)},
+        0xB0 => {alu_a_r8(soc, R8::B,  ALU3::OR )},
+        0xB1 => {alu_a_r8(soc, R8::C,  ALU3::OR )},
+        0xB2 => {alu_a_r8(soc, R8::D,  ALU3::OR )},
+        0xB3 => {alu_a_r8(soc, R8::E,  ALU3::OR )},
+        0xB4 => {alu_a_r8(soc, R8::H,  ALU3::OR )},
+        0xB5 => {alu_a_r8(soc, R8::L,  ALU3::OR )},
+        0xB6 => {alu_a_r8(soc, R8::HL, ALU3::OR )},
+        0xB7 => {alu_a_r8(soc, R8::A,  ALU3::OR )},
+        0xB8 => {alu_a_r8(soc, R8::B,  ALU3::CP )},
+        0xB9 => {alu_a_r8(soc, R8::C,  ALU3::CP )},
+        0xBA => {alu_a_r8(soc, R8::D,  ALU3::CP )},
+        0xBB => {alu_a_r8(soc, R8::E,  ALU3::CP )},
+        0xBC => {alu_a_r8(soc, R8::H,  ALU3::CP )},
+        0xBD => {alu_a_r8(soc, R8::L,  ALU3::CP )},
+        0xBE => {alu_a_r8(soc, R8::HL, ALU3::CP )},
+        0xBF => {alu_a_r8(soc, R8::A,  ALU3::CP )},
+        0xC0 => {ret_cond(soc, COND::NZ)},
+        0xC1 => {pop_r16stk(soc, R16STK::BC)},
+        0xC2 => {jp_cond_a16(soc, COND::NZ, b8x2_le(code[1], code[2]))},
+        0xC3 => {jp_a16(soc, b8x2_le(code[1], code[2]))},
+        0xC4 => {call_cond_a16(soc, COND::NZ, b8x2_le(code[1], code[2]))},
+        0xC5 => {push_r16stk(soc, R16STK::BC)},
+        0xC6 => {alu_a_n8(soc, code[1], ALU3::ADC)},
+        0xC7 => {rst_tgt3(soc, TGT3::T0)},
+        0xC8 => {ret_cond(soc, COND::Z)},
+        0xC9 => {ret(soc)},
+        0xCA => {jp_cond_a16(soc, COND::Z, b8x2_le(code[1], code[2]))},
+        0xCB => {ex_inst(soc, code[1]);},
+        0xCC => {call_cond_a16(soc, COND::Z, b8x2_le(code[1], code[2]))},
+        0xCD => {call_a16(soc, b8x2_le(code[1], code[2]))},
+        0xCE => {alu_a_n8(soc, code[1], ALU3::ADD)},
+        0xCF => {rst_tgt3(soc, TGT3::T1)},
+        0xD0 => {ret_cond(soc, COND::NC)},
+        0xD1 => {pop_r16stk(soc, R16STK::DE)},
+        0xD2 => {jp_cond_a16(soc, COND::NC, b8x2_le(code[1], code[2]))},
+        0xD3 => {return illegal_op(soc, 0xd3);},
+        0xD4 => {call_cond_a16(soc, COND::NC, b8x2_le(code[1], code[2]))},
+        0xD5 => {push_r16stk(soc, R16STK::DE)},
+        0xD6 => {alu_a_n8(soc, code[1], ALU3::SUB)},
+        0xD7 => {rst_tgt3(soc, TGT3::T2)},
+        0xD8 => {ret_cond(soc, COND::C)},
+        0xD9 => {reti(soc)},
+        0xDA => {jp_cond_a16(soc, COND::C, b8x2_le(code[1], code[2]))},
+        0xDB => {return illegal_op(soc, 0xdb);},
+        0xDC => {call_cond_a16(soc, COND::C, b8x2_le(code[1], code[2]))},
+        0xDD => {return illegal_op(soc, 0xdd);},
+        0xDE => {alu_a_n8(soc, code[1], ALU3::SBC)},
+        0xDF => {rst_tgt3(soc, TGT3::T3)},
+        0xE0 => {ldh_a8_a(soc, code[1] as u8)},
+        0xE1 => {pop_r16stk(soc, R16STK::HL)},
+        0xE2 => {ldh_c_a(soc)},
+        0xE3 => {return illegal_op(soc, 0xe3);},
+        0xE4 => {return illegal_op(soc, 0xe4);},
+        0xE5 => {push_r16stk(soc, R16STK::HL)},
+        0xE6 => {alu_a_n8(soc, code[1], ALU3::AND)},
+        0xE7 => {rst_tgt3(soc, TGT3::T4)},
+        0xE8 => {add_sp_e8(soc, code[1] as i8)},
+        0xE9 => {jp_hl(soc)},
+        0xEA => {ld_a16_a(soc, b8x2_le(code[1], code[2]))},
+        0xEB => {return illegal_op(soc, 0xeb);},
+        0xEC => {return illegal_op(soc, 0xec);},
+        0xED => {return illegal_op(soc, 0xed);},
+        0xEE => {alu_a_n8(soc, code[1], ALU3::XOR)},
+        0xEF => {rst_tgt3(soc, TGT3::T5)},
+        0xF0 => {ldh_a_a8(soc, code[1] as u8)},
+        0xF1 => {pop_r16stk(soc, R16STK::AF)},
+        0xF2 => {ldh_a_c(soc)},
+        0xF3 => {di(soc)},
+        0xF4 => {return illegal_op(soc, 0xf4);},
+        0xF5 => {push_r16stk(soc, R16STK::AF)},
+        0xF6 => {alu_a_n8(soc, code[1], ALU3::OR)},
+        0xF7 => {rst_tgt3(soc, TGT3::T6)},
+        0xF8 => {ld_hl_sp_e8(soc, code[1] as i8)},
+        0xF9 => {ld_sp_hl(soc)},
+        0xFA => {ld_a_a16(soc, b8x2_le(code[1], code[2]))},
+        0xFB => {ei(soc)},
+        0xFC => {return illegal_op(soc, 0xfc);},
+        0xFD => {return illegal_op(soc, 0xfd);},
+        0xFE => {alu_a_n8(soc, code[1], ALU3::CP)},
+        0xFF => {rst_tgt3(soc, TGT3::T7)},
+    }
+    Ok(())
+}
+
+fn ex_inst(soc: &mut SoC, sub_code: u16) {
+    let sub_code = sub_code as u8;
+    match sub_code {
+        0x00 => {bop_r8(soc, R8::B,  BOP3::RLC );}
+        0x01 => {bop_r8(soc, R8::C,  BOP3::RLC );}
+        0x02 => {bop_r8(soc, R8::D,  BOP3::RLC );}
+        0x03 => {bop_r8(soc, R8::E,  BOP3::RLC );}
+        0x04 => {bop_r8(soc, R8::H,  BOP3::RLC );}
+        0x05 => {bop_r8(soc, R8::L,  BOP3::RLC );}
+        0x06 => {bop_r8(soc, R8::HL, BOP3::RLC );}
+        0x07 => {bop_r8(soc, R8::A,  BOP3::RLC );}
+        0x08 => {bop_r8(soc, R8::B,  BOP3::RRC );}
+        0x09 => {bop_r8(soc, R8::C,  BOP3::RRC );}
+        0x0A => {bop_r8(soc, R8::D,  BOP3::RRC );}
+        0x0B => {bop_r8(soc, R8::E,  BOP3::RRC );}
+        0x0C => {bop_r8(soc, R8::H,  BOP3::RRC );}
+        0x0D => {bop_r8(soc, R8::L,  BOP3::RRC );}
+        0x0E => {bop_r8(soc, R8::HL, BOP3::RRC );}
+        0x0F => {bop_r8(soc, R8::A,  BOP3::RRC );}
+        0x10 => {bop_r8(soc, R8::B,  BOP3::RL  );}
+        0x11 => {bop_r8(soc, R8::C,  BOP3::RL  );}
+        0x12 => {bop_r8(soc, R8::D,  BOP3::RL  );}
+        0x13 => {bop_r8(soc, R8::E,  BOP3::RL  );}
+        0x14 => {bop_r8(soc, R8::H,  BOP3::RL  );}
+        0x15 => {bop_r8(soc, R8::L,  BOP3::RL  );}
+        0x16 => {bop_r8(soc, R8::HL, BOP3::RL  );}
+        0x17 => {bop_r8(soc, R8::A,  BOP3::RL  );}
+        0x18 => {bop_r8(soc, R8::B,  BOP3::RR  );}
+        0x19 => {bop_r8(soc, R8::C,  BOP3::RR  );}
+        0x1A => {bop_r8(soc, R8::D,  BOP3::RR  );}
+        0x1B => {bop_r8(soc, R8::E,  BOP3::RR  );}
+        0x1C => {bop_r8(soc, R8::H,  BOP3::RR  );}
+        0x1D => {bop_r8(soc, R8::L,  BOP3::RR  );}
+        0x1E => {bop_r8(soc, R8::HL, BOP3::RR  );}
+        0x1F => {bop_r8(soc, R8::A,  BOP3::RR  );}
+        0x20 => {bop_r8(soc, R8::B,  BOP3::SLA );}
+        0x21 => {bop_r8(soc, R8::C,  BOP3::SLA );}
+        0x22 => {bop_r8(soc, R8::D,  BOP3::SLA );}
+        0x23 => {bop_r8(soc, R8::E,  BOP3::SLA );}
+        0x24 => {bop_r8(soc, R8::H,  BOP3::SLA );}
+        0x25 => {bop_r8(soc, R8::L,  BOP3::SLA );}
+        0x26 => {bop_r8(soc, R8::HL, BOP3::SLA );}
+        0x27 => {bop_r8(soc, R8::A,  BOP3::SLA );}
+        0x28 => {bop_r8(soc, R8::B,  BOP3::SRA );}
+        0x29 => {bop_r8(soc, R8::C,  BOP3::SRA );}
+        0x2A => {bop_r8(soc, R8::D,  BOP3::SRA );}
+        0x2B => {bop_r8(soc, R8::E,  BOP3::SRA );}
+        0x2C => {bop_r8(soc, R8::H,  BOP3::SRA );}
+        0x2D => {bop_r8(soc, R8::L,  BOP3::SRA );}
+        0x2E => {bop_r8(soc, R8::HL, BOP3::SRA );}
+        0x2F => {bop_r8(soc, R8::A,  BOP3::SRA );}
+        0x30 => {bop_r8(soc, R8::B,  BOP3::SWAP);}
+        0x31 => {bop_r8(soc, R8::C,  BOP3::SWAP);}
+        0x32 => {bop_r8(soc, R8::D,  BOP3::SWAP);}
+        0x33 => {bop_r8(soc, R8::E,  BOP3::SWAP);}
+        0x34 => {bop_r8(soc, R8::H,  BOP3::SWAP);}
+        0x35 => {bop_r8(soc, R8::L,  BOP3::SWAP);}
+        0x36 => {bop_r8(soc, R8::HL, BOP3::SWAP);}
+        0x37 => {bop_r8(soc, R8::A,  BOP3::SWAP);}
+        0x38 => {bop_r8(soc, R8::B,  BOP3::SRL );}
+        0x39 => {bop_r8(soc, R8::C,  BOP3::SRL );}
+        0x3A => {bop_r8(soc, R8::D,  BOP3::SRL );}
+        0x3B => {bop_r8(soc, R8::E,  BOP3::SRL );}
+        0x3C => {bop_r8(soc, R8::H,  BOP3::SRL );}
+        0x3D => {bop_r8(soc, R8::L,  BOP3::SRL );}
+        0x3E => {bop_r8(soc, R8::HL, BOP3::SRL );}
+        0x3F => {bop_r8(soc, R8::A,  BOP3::SRL );}
+        0x40 => {bit_r8_b3(soc, R8::B,  0);}
+        0x41 => {bit_r8_b3(soc, R8::C,  0);}
+        0x42 => {bit_r8_b3(soc, R8::D,  0);}
+        0x43 => {bit_r8_b3(soc, R8::E,  0);}
+        0x44 => {bit_r8_b3(soc, R8::H,  0);}
+        0x45 => {bit_r8_b3(soc, R8::L,  0);}
+        0x46 => {bit_r8_b3(soc, R8::HL, 0);}
+        0x47 => {bit_r8_b3(soc, R8::A,  0);}
+        0x48 => {bit_r8_b3(soc, R8::B,  1);}
+        0x49 => {bit_r8_b3(soc, R8::C,  1);}
+        0x4A => {bit_r8_b3(soc, R8::D,  1);}
+        0x4B => {bit_r8_b3(soc, R8::E,  1);}
+        0x4C => {bit_r8_b3(soc, R8::H,  1);}
+        0x4D => {bit_r8_b3(soc, R8::L,  1);}
+        0x4E => {bit_r8_b3(soc, R8::HL, 1);}
+        0x4F => {bit_r8_b3(soc, R8::A,  1);}
+        0x50 => {bit_r8_b3(soc, R8::B,  2);}
+        0x51 => {bit_r8_b3(soc, R8::C,  2);}
+        0x52 => {bit_r8_b3(soc, R8::D,  2);}
+        0x53 => {bit_r8_b3(soc, R8::E,  2);}
+        0x54 => {bit_r8_b3(soc, R8::H,  2);}
+        0x55 => {bit_r8_b3(soc, R8::L,  2);}
+        0x56 => {bit_r8_b3(soc, R8::HL, 2);}
+        0x57 => {bit_r8_b3(soc, R8::A,  2);}
+        0x58 => {bit_r8_b3(soc, R8::B,  3);}
+        0x59 => {bit_r8_b3(soc, R8::C,  3);}
+        0x5A => {bit_r8_b3(soc, R8::D,  3);}
+        0x5B => {bit_r8_b3(soc, R8::E,  3);}
+        0x5C => {bit_r8_b3(soc, R8::H,  3);}
+        0x5D => {bit_r8_b3(soc, R8::L,  3);}
+        0x5E => {bit_r8_b3(soc, R8::HL, 3);}
+        0x5F => {bit_r8_b3(soc, R8::A,  3);}
+        0x60 => {bit_r8_b3(soc, R8::B,  4);}
+        0x61 => {bit_r8_b3(soc, R8::C,  4);}
+        0x62 => {bit_r8_b3(soc, R8::D,  4);}
+        0x63 => {bit_r8_b3(soc, R8::E,  4);}
+        0x64 => {bit_r8_b3(soc, R8::H,  4);}
+        0x65 => {bit_r8_b3(soc, R8::L,  4);}
+        0x66 => {bit_r8_b3(soc, R8::HL, 4);}
+        0x67 => {bit_r8_b3(soc, R8::A,  4);}
+        0x68 => {bit_r8_b3(soc, R8::B,  5);}
+        0x69 => {bit_r8_b3(soc, R8::C,  5);}
+        0x6A => {bit_r8_b3(soc, R8::D,  5);}
+        0x6B => {bit_r8_b3(soc, R8::E,  5);}
+        0x6C => {bit_r8_b3(soc, R8::H,  5);}
+        0x6D => {bit_r8_b3(soc, R8::L,  5);}
+        0x6E => {bit_r8_b3(soc, R8::HL, 5);}
+        0x6F => {bit_r8_b3(soc, R8::A,  5);}
+        0x70 => {bit_r8_b3(soc, R8::B,  6);}
+        0x71 => {bit_r8_b3(soc, R8::C,  6);}
+        0x72 => {bit_r8_b3(soc, R8::D,  6);}
+        0x73 => {bit_r8_b3(soc, R8::E,  6);}
+        0x74 => {bit_r8_b3(soc, R8::H,  6);}
+        0x75 => {bit_r8_b3(soc, R8::L,  6);}
+        0x76 => {bit_r8_b3(soc, R8::HL, 6);}
+        0x77 => {bit_r8_b3(soc, R8::A,  6);}
+        0x78 => {bit_r8_b3(soc, R8::B,  7);}
+        0x79 => {bit_r8_b3(soc, R8::C,  7);}
+        0x7A => {bit_r8_b3(soc, R8::D,  7);}
+        0x7B => {bit_r8_b3(soc, R8::E,  7);}
+        0x7C => {bit_r8_b3(soc, R8::H,  7);}
+        0x7D => {bit_r8_b3(soc, R8::L,  7);}
+        0x7E => {bit_r8_b3(soc, R8::HL, 7);}
+        0x7F => {bit_r8_b3(soc, R8::A,  7);}
+        0x80 => {bit_r8_b3(soc, R8::B,  0);}
+        0x81 => {bit_r8_b3(soc, R8::C,  0);}
+        0x82 => {bit_r8_b3(soc, R8::D,  0);}
+        0x83 => {bit_r8_b3(soc, R8::E,  0);}
+        0x84 => {bit_r8_b3(soc, R8::H,  0);}
+        0x85 => {bit_r8_b3(soc, R8::L,  0);}
+        0x86 => {bit_r8_b3(soc, R8::HL, 0);}
+        0x87 => {bit_r8_b3(soc, R8::A,  0);}
+        0x88 => {bit_r8_b3(soc, R8::B,  1);}
+        0x89 => {bit_r8_b3(soc, R8::C,  1);}
+        0x8A => {bit_r8_b3(soc, R8::D,  1);}
+        0x8B => {bit_r8_b3(soc, R8::E,  1);}
+        0x8C => {bit_r8_b3(soc, R8::H,  1);}
+        0x8D => {bit_r8_b3(soc, R8::L,  1);}
+        0x8E => {bit_r8_b3(soc, R8::HL, 1);}
+        0x8F => {bit_r8_b3(soc, R8::A,  1);}
+        0x90 => {res_r8_b3(soc, R8::B,  2);}
+        0x91 => {res_r8_b3(soc, R8::C,  2);}
+        0x92 => {res_r8_b3(soc, R8::D,  2);}
+        0x93 => {res_r8_b3(soc, R8::E,  2);}
+        0x94 => {res_r8_b3(soc, R8::H,  2);}
+        0x95 => {res_r8_b3(soc, R8::L,  2);}
+        0x96 => {res_r8_b3(soc, R8::HL, 2);}
+        0x97 => {res_r8_b3(soc, R8::A,  2);}
+        0x98 => {res_r8_b3(soc, R8::B,  3);}
+        0x99 => {res_r8_b3(soc, R8::C,  3);}
+        0x9A => {res_r8_b3(soc, R8::D,  3);}
+        0x9B => {res_r8_b3(soc, R8::E,  3);}
+        0x9C => {res_r8_b3(soc, R8::H,  3);}
+        0x9D => {res_r8_b3(soc, R8::L,  3);}
+        0x9E => {res_r8_b3(soc, R8::HL, 3);}
+        0x9F => {res_r8_b3(soc, R8::A,  3);}
+        0xA0 => {res_r8_b3(soc, R8::B,  4);}
+        0xA1 => {res_r8_b3(soc, R8::C,  4);}
+        0xA2 => {res_r8_b3(soc, R8::D,  4);}
+        0xA3 => {res_r8_b3(soc, R8::E,  4);}
+        0xA4 => {res_r8_b3(soc, R8::H,  4);}
+        0xA5 => {res_r8_b3(soc, R8::L,  4);}
+        0xA6 => {res_r8_b3(soc, R8::HL, 4);}
+        0xA7 => {res_r8_b3(soc, R8::A,  4);}
+        0xA8 => {res_r8_b3(soc, R8::B,  5);}
+        0xA9 => {res_r8_b3(soc, R8::C,  5);}
+        0xAA => {res_r8_b3(soc, R8::D,  5);}
+        0xAB => {res_r8_b3(soc, R8::E,  5);}
+        0xAC => {res_r8_b3(soc, R8::H,  5);}
+        0xAD => {res_r8_b3(soc, R8::L,  5);}
+        0xAE => {res_r8_b3(soc, R8::HL, 5);}
+        0xAF => {res_r8_b3(soc, R8::A,  5);}
+        0xB0 => {res_r8_b3(soc, R8::B,  6);}
+        0xB1 => {res_r8_b3(soc, R8::C,  6);}
+        0xB2 => {res_r8_b3(soc, R8::D,  6);}
+        0xB3 => {res_r8_b3(soc, R8::E,  6);}
+        0xB4 => {res_r8_b3(soc, R8::H,  6);}
+        0xB5 => {res_r8_b3(soc, R8::L,  6);}
+        0xB6 => {res_r8_b3(soc, R8::HL, 6);}
+        0xB7 => {res_r8_b3(soc, R8::A,  6);}
+        0xB8 => {res_r8_b3(soc, R8::B,  7);}
+        0xB9 => {res_r8_b3(soc, R8::C,  7);}
+        0xBA => {res_r8_b3(soc, R8::D,  7);}
+        0xBB => {res_r8_b3(soc, R8::E,  7);}
+        0xBC => {res_r8_b3(soc, R8::H,  7);}
+        0xBD => {res_r8_b3(soc, R8::L,  7);}
+        0xBE => {res_r8_b3(soc, R8::HL, 7);}
+        0xBF => {res_r8_b3(soc, R8::A,  7);}
+        0xC0 => {set_r8_b3(soc, R8::B,  0);}
+        0xC1 => {set_r8_b3(soc, R8::C,  0);}
+        0xC2 => {set_r8_b3(soc, R8::D,  0);}
+        0xC3 => {set_r8_b3(soc, R8::E,  0);}
+        0xC4 => {set_r8_b3(soc, R8::H,  0);}
+        0xC5 => {set_r8_b3(soc, R8::L,  0);}
+        0xC6 => {set_r8_b3(soc, R8::HL, 0);}
+        0xC7 => {set_r8_b3(soc, R8::A,  0);}
+        0xC8 => {set_r8_b3(soc, R8::B,  1);}
+        0xC9 => {set_r8_b3(soc, R8::C,  1);}
+        0xCA => {set_r8_b3(soc, R8::D,  1);}
+        0xCB => {set_r8_b3(soc, R8::E,  1);}
+        0xCC => {set_r8_b3(soc, R8::H,  1);}
+        0xCD => {set_r8_b3(soc, R8::L,  1);}
+        0xCE => {set_r8_b3(soc, R8::HL, 1);}
+        0xCF => {set_r8_b3(soc, R8::A,  1);}
+        0xD0 => {set_r8_b3(soc, R8::B,  2);}
+        0xD1 => {set_r8_b3(soc, R8::C,  2);}
+        0xD2 => {set_r8_b3(soc, R8::D,  2);}
+        0xD3 => {set_r8_b3(soc, R8::E,  2);}
+        0xD4 => {set_r8_b3(soc, R8::H,  2);}
+        0xD5 => {set_r8_b3(soc, R8::L,  2);}
+        0xD6 => {set_r8_b3(soc, R8::HL, 2);}
+        0xD7 => {set_r8_b3(soc, R8::A,  2);}
+        0xD8 => {set_r8_b3(soc, R8::B,  3);}
+        0xD9 => {set_r8_b3(soc, R8::C,  3);}
+        0xDA => {set_r8_b3(soc, R8::D,  3);}
+        0xDB => {set_r8_b3(soc, R8::E,  3);}
+        0xDC => {set_r8_b3(soc, R8::H,  3);}
+        0xDD => {set_r8_b3(soc, R8::L,  3);}
+        0xDE => {set_r8_b3(soc, R8::HL, 3);}
+        0xDF => {set_r8_b3(soc, R8::A,  3);}
+        0xE0 => {set_r8_b3(soc, R8::B,  4);}
+        0xE1 => {set_r8_b3(soc, R8::C,  4);}
+        0xE2 => {set_r8_b3(soc, R8::D,  4);}
+        0xE3 => {set_r8_b3(soc, R8::E,  4);}
+        0xE4 => {set_r8_b3(soc, R8::H,  4);}
+        0xE5 => {set_r8_b3(soc, R8::L,  4);}
+        0xE6 => {set_r8_b3(soc, R8::HL, 4);}
+        0xE7 => {set_r8_b3(soc, R8::A,  4);}
+        0xE8 => {set_r8_b3(soc, R8::B,  5);}
+        0xE9 => {set_r8_b3(soc, R8::C,  5);}
+        0xEA => {set_r8_b3(soc, R8::D,  5);}
+        0xEB => {set_r8_b3(soc, R8::E,  5);}
+        0xEC => {set_r8_b3(soc, R8::H,  5);}
+        0xED => {set_r8_b3(soc, R8::L,  5);}
+        0xEE => {set_r8_b3(soc, R8::HL, 5);}
+        0xEF => {set_r8_b3(soc, R8::A,  5);}
+        0xF0 => {set_r8_b3(soc, R8::B,  6);}
+        0xF1 => {set_r8_b3(soc, R8::C,  6);}
+        0xF2 => {set_r8_b3(soc, R8::D,  6);}
+        0xF3 => {set_r8_b3(soc, R8::E,  6);}
+        0xF4 => {set_r8_b3(soc, R8::H,  6);}
+        0xF5 => {set_r8_b3(soc, R8::L,  6);}
+        0xF6 => {set_r8_b3(soc, R8::HL, 6);}
+        0xF7 => {set_r8_b3(soc, R8::A,  6);}
+        0xF8 => {set_r8_b3(soc, R8::B,  7);}
+        0xF9 => {set_r8_b3(soc, R8::C,  7);}
+        0xFA => {set_r8_b3(soc, R8::D,  7);}
+        0xFB => {set_r8_b3(soc, R8::E,  7);}
+        0xFC => {set_r8_b3(soc, R8::H,  7);}
+        0xFD => {set_r8_b3(soc, R8::L,  7);}
+        0xFE => {set_r8_b3(soc, R8::HL, 7);}
+        0xFF => {set_r8_b3(soc, R8::A,  7);}
+    }
+}
+
+// 注册到模块
+#[pymodule]
+fn simu83(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<SoC>()?;
+    m.add_class::<Interrupt>()?;
+    m.add_class::<IllegalOpcodePolicy>()?;
+    m.add_class::<CartridgeHeader>()?;
+    m.add_class::<RtcClock>()?;
+    m.add_class::<Model>()?;
+    m.add_class::<PpuBackend>()?;
+    m.add_class::<Button>()?;
+    m.add_class::<AudioFormat>()?;
+    error::register(m)?;
+    Ok(())
+}
+
+// ===========================================================
+enum R8 {B, C, D, E, H, L, HL, A}
+enum R16 {BC, DE, HL, SP}
+enum R16STK {BC, DE, HL, AF}
+enum R16RAM {BC, DE, HLI, HLD}
+enum COND {NZ, Z, NC, C}
+enum TGT3 {T0=0x00, T1=0x08, T2=0x10, T3=0x18, T4=0x20, T5=0x28, T6=0x30, T7=0x38}
+enum ALU3 {ADD, ADC, SUB, SBC, AND, XOR, OR, CP}
+enum BOP3 {RLC, RRC, RL, RR, SLA, SRA, SWAP, SRL}
+
+fn get_r8_by_idx(soc: &SoC, r8: &R8) -> u8 {
+    match r8 {
+        R8::B  => {soc.get_r8(0)}
+        R8::C  => {soc.get_r8(1)}
+        R8::D  => {soc.get_r8(2)}
+        R8::E  => {soc.get_r8(3)}
+        R8::H  => {soc.get_r8(4)}
+        R8::L  => {soc.get_r8(5)}
+        R8::HL => {soc.ram_read(soc.get_r16(2))}
+        R8::A  => {soc.get_r8(7)}
+    }
+}
+fn get_r16_by_idx(soc: &SoC, r16: R16) -> u16 {
+    match r16 {
+        R16::BC => {soc.get_r16(0)},
+        R16::DE => {soc.get_r16(1)},
+        R16::HL => {soc.get_r16(2)},
+        R16::SP => {soc.get_sp()}
+    }
+}
+fn get_r16stk_by_idx(soc: &SoC, r16stk: R16STK) -> u16 {
+    match r16stk {
+        R16STK::BC => {soc.get_r16(0)},
+        R16STK::DE => {soc.get_r16(1)},
+        R16STK::HL => {soc.get_r16(2)},
+        R16STK::AF => {soc.get_r16(3)},
+    }
+}
+fn get_cond_by_idx(soc: &SoC, cond: COND) -> bool {
+    match cond {
+        COND::NZ => {soc.get_flag(7) == 0},
+        COND::Z  => {soc.get_flag(7) == 1},
+        COND::NC => {soc.get_flag(4) == 0},
+        COND::C  => {soc.get_flag(4) == 1},
+    }
+}
+
+fn set_r8_by_idx(soc: &mut SoC, r8: &R8, r8_value: u8) {
+    match r8 {
+        R8::B  => {soc.set_r8(0, r8_value);}
+        R8::C  => {soc.set_r8(1, r8_value);}
+        R8::D  => {soc.set_r8(2, r8_value);}
+        R8::E  => {soc.set_r8(3, r8_value);}
+        R8::H  => {soc.set_r8(4, r8_value);}
+        R8::L  => {soc.set_r8(5, r8_value);}
+        R8::HL => {soc.ram_write(soc.get_r16(2),r8_value);}
+        R8::A  => {soc.set_r8(7, r8_value);}
+    }
+}
+
+// ===========================================================
+// 其他辅助函数
+fn b8x2_le(b8a: u16, b8b: u16) -> u16 {(b8b << 8) + b8a}
+// 压栈：先写高字节，SP 最终指向低字节
+fn stack_push(soc: &mut SoC, value: u16) {
+    soc.set_sp(soc.get_sp().wrapping_sub(1));
+    soc.ram_write(soc.get_sp(), (value >> 8) as u8);
+    soc.set_sp(soc.get_sp().wrapping_sub(1));
+    soc.ram_write(soc.get_sp(), value as u8);
+}
+// 出栈
+fn stack_pop(soc: &mut SoC) -> u16 {
+    let lo8 = soc.ram_read(soc.get_sp());
+    soc.set_sp(soc.get_sp().wrapping_add(1));
+    let hi8 = soc.ram_read(soc.get_sp());
+    soc.set_sp(soc.get_sp().wrapping_add(1));
+    lo8 as u16 + ((hi8 as u16) << 8)
+}
+
+// ld_r16ram_a
+fn ld_r16ram_a(soc: &mut SoC, r16ram: R16RAM) {
+    let addr: u16 = match r16ram {
+        R16RAM::BC  => {soc.get_r16(0)},
+        R16RAM::DE  => {soc.get_r16(1)},
+        _           => {soc.get_r16(2)},};
+    match r16ram {
+        R16RAM::HLI => {soc.r16_inc(2)},
+        R16RAM::HLD => {soc.r16_dec(2)},
+        _           => {}}
+    soc.ram_write(addr, soc.get_r8(7));
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// inc_r16
+fn inc_r16(soc: &mut SoC, r16: R16) {
+    match r16 {
+        R16::BC => {soc.r16_inc(0)},
+        R16::DE => {soc.r16_inc(1)},
+        R16::HL => {soc.r16_inc(2)},
+        R16::SP => {soc.set_sp(soc.get_sp().wrapping_add(1));}}
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// add_hl_r16
+fn add_hl_r16(soc: &mut SoC, r16: R16) {
+    let add1 = soc.get_r16(2);
+    let add2 = get_r16_by_idx(soc, r16);
+    soc.res_flag(6);
+    if (add1 & 0xfff) + (add2 & 0xfff) > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
+    if add1 as u32 + add2 as u32 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
+    soc.set_r16(2, add1 + add2);
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// ld_a_r16ram
+fn ld_a_r16ram(soc: &mut SoC, r16ram: R16RAM) {
+    let addr: u16 = match r16ram {
+        R16RAM::BC  => {soc.get_r16(0)},
+        R16RAM::DE  => {soc.get_r16(1)},
+        _           => {soc.get_r16(2)},};
+    match r16ram {
+        R16RAM::HLI => {soc.r16_inc(2)},
+        R16RAM::HLD => {soc.r16_dec(2)},
+        _           => {}}
+    soc.set_r8(7, soc.ram_read(addr));
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// dec_r16
+fn dec_r16(soc: &mut SoC, r16: R16) {
+    match r16 {
+        R16::BC => {soc.r16_dec(0)},
+        R16::DE => {soc.r16_dec(1)},
+        R16::HL => {soc.r16_dec(2)},
+        R16::SP => {soc.set_sp(soc.get_sp().wrapping_sub(1));}}
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// inc_r8
+fn inc_r8(soc: &mut SoC, r8: R8) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    set_r8_by_idx(soc, &r8, r8_value.wrapping_add(1));
+    if r8_value.wrapping_add(1) == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
+    soc.res_flag(6);
+    if (r8_value & 0xf) + 1 > 0xf {soc.set_flag(5);} else {soc.res_flag(5);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// dec_r8
+fn dec_r8(soc: &mut SoC, r8: R8) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    set_r8_by_idx(soc, &r8, r8_value.wrapping_sub(1));
+    if r8_value.wrapping_sub(1) == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
+    soc.set_flag(6);
+    if ((r8_value & 0xf) as i8 - 1) < 0 {soc.set_flag(5);} else {soc.res_flag(5);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// ld_r8_r8
+fn ld_r8_r8(soc: &mut SoC, r8d: R8, r8s: R8) {
+    let r8_value = get_r8_by_idx(soc, &r8s);
+    set_r8_by_idx(soc, &r8d, r8_value);
+    soc.pc_inc(1);
+    if (r8s as u8 == 6) | (r8d as u8 == 6) {soc.cyc_inc(2);} else {soc.cyc_inc(1);}
+}
+fn alu_a_r8(soc: &mut SoC, r8: R8, alu3: ALU3) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    match alu3 {
+        ALU3::ADD => {
+            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, false, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_add(r8_value));
+        }
+        ALU3::ADC => {
+            let c_flag = soc.get_flag(4);
+            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16 + c_flag as u16, false, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_add(r8_value).wrapping_add(c_flag));
+        }
+        ALU3::SUB => {
+            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, true, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_sub(r8_value));
+        }
+        ALU3::SBC => {
+            let c_flag = soc.get_flag(4);
+            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16 + c_flag as u16, true, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_sub(r8_value).wrapping_sub(c_flag));
+        }
+        ALU3::AND => {
+            if (soc.get_r8(7) & r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.set_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) & r8_value);
+        }
+        ALU3::XOR => {
+            if (soc.get_r8(7) ^ r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.res_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) ^ r8_value);
+        }
+        ALU3::OR => {
+            if (soc.get_r8(7) | r8_value) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.res_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) | r8_value);
+        }
+        ALU3::CP => {
+            soc.smart_flag(soc.get_r8(7) as u16, r8_value as u16, true, true);
+        }
+    }
+    soc.pc_inc(1);
+    match r8 {R8::HL => {soc.cyc_inc(2);} _ => {soc.cyc_inc(1);}};
+}
+// ret_cond
+fn ret_cond(soc: &mut SoC, cond: COND) {
+    if get_cond_by_idx(soc, cond) == true {
+        let new_pc = stack_pop(soc);
+        soc.set_pc(new_pc);
+        soc.cyc_inc(5);
+    } else {
+        soc.pc_inc(1);
+        soc.cyc_inc(2);
+    }
+}
+// pop_r16stk
+fn pop_r16stk(soc: &mut SoC, r16stk: R16STK) {
+    let new_r16 = stack_pop(soc);
+    match r16stk {
+        R16STK::BC => {soc.set_r16(0, new_r16);},
+        R16STK::DE => {soc.set_r16(1, new_r16);},
+        R16STK::HL => {soc.set_r16(2, new_r16);},
+        R16STK::AF => {soc.set_r16(3, new_r16);},}
+    soc.cyc_inc(3);
+    soc.pc_inc(1);
+}
+// push_r16stk
+fn push_r16stk(soc: &mut SoC, r16stk: R16STK) {
+    let new_r16 = get_r16stk_by_idx(soc, r16stk);
+    stack_push(soc, new_r16);
+    soc.cyc_inc(4);
+    soc.pc_inc(1);
+}
+// rst_tgt3
+fn rst_tgt3(soc: &mut SoC, tgt3: TGT3) {
+    stack_push(soc, soc.get_pc().wrapping_add(1));
+    soc.set_pc(tgt3 as u16);
+    soc.cyc_inc(4);
+}
+// ld_r8_n8
+fn ld_r8_n8(soc: &mut SoC, r8: R8, n8: u16) {
+    let n8 = n8 as u8;
+    set_r8_by_idx(soc, &r8, n8);
+    soc.pc_inc(2);
+    if r8 as u8 == 6 {soc.cyc_inc(3)} else {soc.cyc_inc(2);};
+}
+// jr_cond_e8
+fn jr_cond_e8(soc: &mut SoC, cond: COND, e8: i8) {
+    let res = (soc.get_pc().wrapping_add(2) as i32 + e8 as i32)as u16;
+    if get_cond_by_idx(soc, cond) == true {
+        soc.set_pc(res);
+        soc.cyc_inc(3);
+    } else {
+        soc.pc_inc(2);
+        soc.cyc_inc(2);
+    }
+}
+// ld_r16_n16
+fn ld_r16_n16(soc: &mut SoC, r16: R16, n16: u16) {
+    match r16 {
+        R16::BC => {soc.set_r16(0, n16);},
+        R16::DE => {soc.set_r16(1, n16);},
+        R16::HL => {soc.set_r16(2, n16);},
+        R16::SP => {soc.set_sp(n16);},
+    }
+    soc.cyc_inc(3);
+    soc.pc_inc(3);
+}
+// jp_cond_a16
+fn jp_cond_a16(soc: &mut SoC, cond:COND, a16: u16) {
+    if get_cond_by_idx(soc, cond) == true {
+        soc.set_pc(a16);
+        soc.cyc_inc(4);
+    } else {
+        soc.pc_inc(3);
+        soc.cyc_inc(3);
+    }
+}
+// call_cond_a16
+fn call_cond_a16(soc: &mut SoC, cond:COND, a16: u16) {
+    if get_cond_by_idx(soc, cond) == true {
+        let new_pc = soc.get_pc().wrapping_add(3);
+        stack_push(soc, new_pc);
+        soc.set_pc(a16);
+        soc.cyc_inc(6);
+    } else {
+        soc.pc_inc(3);
+        soc.cyc_inc(3);
+    }
+}
+// bop_r8
+fn bop_r8(soc: &mut SoC, r8: R8, bop3: BOP3) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    let c: u8;
+    let res: u8;
+    match bop3 {
+        BOP3::RLC => {
+            c = r8_value >> 7;
+            res = ((r8_value << 1) + c) & 0xff;
+        }
+        BOP3::RRC => {
+            c = r8_value & 1;
+            res = ((r8_value >> 1) + (c << 7)) & 0xff;
+        }
+        BOP3::RL => {
+            c = r8_value >> 7;
+            res = ((r8_value << 1) + soc.get_flag(4)) & 0xff;
+        }
+        BOP3::RR => {
+            c = r8_value & 1;
+            res = (r8_value >> 1) + (soc.get_flag(4) << 7);
+        }
+        BOP3::SLA => {
+            c = r8_value >> 7;
+            res = (r8_value << 1) & 0xff;
+        }
+        BOP3::SRA => {
+            c = r8_value & 1;
+            res = ((r8_value >> 1) & 0xff) + (r8_value & 0x80);
+        }
+        BOP3::SWAP => {
+            c = 42;
+            res = (r8_value >> 4) + ((r8_value & 0xf) << 4);
+        }
+        BOP3::SRL => {
+            c = r8_value & 1;
+            res = (r8_value >> 1) & 0xff;
+        }
+    }
+    set_r8_by_idx(soc, &r8, res);
+    if res == 0 {soc.set_flag(7);} else {soc.res_flag(7);}
+    soc.res_flag(6);
+    soc.res_flag(5);
+    if (bop3 as u8) != 6 {
+        if c == 1 {soc.set_flag(4);} 
+        if c == 0 {soc.res_flag(4);}
+    }
+    soc.pc_inc(2);
+    if (r8 as u8) == 6 {soc.cyc_inc(4)} else {soc.cyc_inc(2);}
+}
+// bit_r8_b3
+fn bit_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    if r8_value & (1 << b3) == 0 {
+        soc.set_flag(7);
+    } else {
+        soc.res_flag(7);
+    }
+    soc.res_flag(6);
+    soc.set_flag(5);
+    soc.pc_inc(2);
+    if (r8 as u8) == 6 {soc.cyc_inc(3);} else {soc.cyc_inc(2);}
+}
+// res_r8_b3
+fn res_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    set_r8_by_idx(soc, &r8, r8_value & (0xff - (1 << b3))); 
+    soc.pc_inc(2);
+    if (r8 as u8) == 6 {soc.cyc_inc(4);} else {soc.cyc_inc(2);}
+}
+// set_r8_b3
+fn set_r8_b3(soc: &mut SoC, r8: R8, b3: u8) {
+    let r8_value = get_r8_by_idx(soc, &r8);
+    set_r8_by_idx(soc, &r8, r8_value | (0xff - (1 << b3))); 
+    soc.pc_inc(2);
+    if (r8 as u8) == 6 {soc.cyc_inc(4);} else {soc.cyc_inc(2);}
+}
+// nop
+fn nop(soc: &mut SoC) {
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// rlca
+fn rlca(soc: &mut SoC) {
+    let a_value = soc.get_r8(7);
+    let c = a_value >> 7;
+    soc.set_r8(7, ((a_value << 1) + c) & 0xff);
+    soc.res_flag(7);
+    soc.res_flag(6);
+    soc.res_flag(5);
+    if c == 1 {soc.set_flag(4);} 
+    if c == 0 {soc.res_flag(4);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// rrca
+fn rrca(soc: &mut SoC) {
+    let a_value = soc.get_r8(7);
+    let c = a_value & 1;
+    soc.set_r8(7, ((a_value >> 1) + (c << 7)) & 0xff);
+    soc.res_flag(7);
+    soc.res_flag(6);
+    soc.res_flag(5);
+    if c == 1 {soc.set_flag(4);} 
+    if c == 0 {soc.res_flag(4);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// rla
+fn rla(soc: &mut SoC) {
+    let a_value = soc.get_r8(7);
+    let c = a_value >> 7;
+    soc.set_r8(7, ((a_value << 1) + soc.get_flag(4)) & 0xff);
+    soc.res_flag(7);
+    soc.res_flag(6);
+    soc.res_flag(5);
+    if c == 1 {soc.set_flag(4);} 
+    if c == 0 {soc.res_flag(4);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// rra
+fn rra(soc: &mut SoC) {
+    let a_value = soc.get_r8(7);
+    let c = a_value & 1;
+    soc.set_r8(7, ((a_value >> 1) + (soc.get_flag(4) << 7)) & 0xff);
+    soc.res_flag(7);
+    soc.res_flag(6);
+    soc.res_flag(5);
+    if c == 1 {soc.set_flag(4);} 
+    if c == 0 {soc.res_flag(4);}
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// daa
+fn daa(soc: &mut SoC) {
+    let a_value = soc.get_r8(7);
+    let res;
+    if soc.get_flag(6) == 1 {
+        let adj = soc.get_flag(5) * 0x6 + soc.get_flag(4) * 0x60;
+        res = a_value - adj;
+        soc.smart_flag(a_value as u16, adj as u16, true, true);
+    } else {
+        let mut adj: u8 = 0x0;
+		if (soc.get_flag(5) == 1) || ((a_value & 0xf) > 0x9) {adj = adj + 0x6}
+		if (soc.get_flag(4) == 1) || ((a_value & 0x99) > 0x9) {adj = adj + 0x60}
+        res = a_value + adj;
+        soc.smart_flag(a_value as u16, adj as u16, false, true);
+    }
+    soc.res_flag(5);
+    soc.set_r8(7, res);
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// scf
+fn scf(soc: &mut SoC) {
+    soc.res_flag(6);
+    soc.res_flag(5);
+    soc.set_flag(4);
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// cpl
+fn cpl(soc: &mut SoC) {
+    soc.set_r8(7, soc.get_r8(7) ^ 0xff);
+    soc.set_flag(6);
+    soc.set_flag(5);
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// ccf
+fn ccf(soc: &mut SoC) {
+    let c = soc.get_flag(4);
+    if c == 1 {soc.res_flag(4);}
+    if c == 0 {soc.set_flag(4);}
+    soc.res_flag(6);
+    soc.res_flag(5);
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// ret
+fn ret(soc: &mut SoC) {
+    let new_pc = stack_pop(soc);
+    soc.set_pc(new_pc);
+    soc.cyc_inc(4);
+}
+// jp_hl
+fn jp_hl(soc: &mut SoC) {
+    soc.set_pc(soc.get_r16(2));
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// di
+fn di(soc: &mut SoC) {
+    soc.res_ime();
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// ei
+fn ei(soc: &mut SoC) {
+    soc.ime_pending = true;
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// reti
+fn reti(soc: &mut SoC) {
+    soc.set_ime();
+    let new_pc = stack_pop(soc);
+    soc.set_pc(new_pc);
+    soc.cyc_inc(4);
+}
+// ld_sp_hl
+fn ld_sp_hl(soc: &mut SoC) {
+    soc.set_sp(soc.get_r16(2));
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// jr_e8
+fn jr_e8(soc: &mut SoC, e8: i8) {
+    let res = (soc.get_pc().wrapping_add(2) as i32  + e8 as i32)as u16;
+    soc.set_pc(res);
+    soc.cyc_inc(3);
+}
+// ldh_a8_a
+fn ldh_a8_a(soc: &mut SoC, a8: u8) {
+    soc.ram_write(0xff00 + (a8 as u16), soc.get_r8(7));
+    soc.pc_inc(2);
+    soc.cyc_inc(3);
+}
+// ldh_a_a8
+fn ldh_a_a8(soc: &mut SoC, a8: u8) {
+    soc.set_r8(7, soc.ram_read(0xff00 + (a8 as u16)));
+    soc.pc_inc(2);
+    soc.cyc_inc(3);
+}
+// ldh_c_a
+fn ldh_c_a(soc: &mut SoC) {
+    soc.ram_write(0xff00 + (soc.get_r8(1) as u16), soc.get_r8(7));
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// ldh_a_c
+fn ldh_a_c(soc: &mut SoC) {
+    soc.set_r8(7, soc.ram_read(0xff00 + (soc.get_r8(1) as u16)));
+    soc.pc_inc(1);
+    soc.cyc_inc(2);
+}
+// alu_a_n8
+fn alu_a_n8(soc: &mut SoC, n8: u16, alu3: ALU3) {
+    match alu3 {
+        ALU3::ADD => {
+            soc.smart_flag(soc.get_r8(7) as u16, n8 as u16, false, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_add(n8 as u8));
+        }
+        ALU3::ADC => {
+            let c_flag = soc.get_flag(4);
+            soc.smart_flag(soc.get_r8(7) as u16, n8 + c_flag as u16, false, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_add(n8 as u8).wrapping_add(c_flag));
+        }
+        ALU3::SUB => {
+            soc.smart_flag(soc.get_r8(7) as u16, n8 as u16, true, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_sub(n8 as u8));
+        }
+        ALU3::SBC => {
+            let c_flag = soc.get_flag(4);
+            soc.smart_flag(soc.get_r8(7) as u16, n8 + c_flag as u16, true, true);
+            soc.set_r8(7, soc.get_r8(7).wrapping_sub(n8 as u8).wrapping_sub(c_flag));
+        }
+        ALU3::AND => {
+            if (soc.get_r8(7) & n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.set_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) & n8 as u8);
+        }
+        ALU3::XOR => {
+            if (soc.get_r8(7) ^ n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.res_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) ^ n8 as u8);
+        }
+        ALU3::OR => {
+            if (soc.get_r8(7) | n8 as u8) == 0 {soc.set_flag(7);} else {soc.res_flag(7);};
+            soc.res_flag(6);
+            soc.res_flag(5);
+            soc.res_flag(4);
+            soc.set_r8(7, soc.get_r8(7) | n8 as u8);
+        }
+        ALU3::CP => {
+            soc.smart_flag(soc.get_r8(7) as u16, n8 as u16, true, true);
+        }
+    }
+    soc.pc_inc(2);
+    soc.cyc_inc(2);
+}
+// add_sp_e8
+fn add_sp_e8(soc: &mut SoC, e8: i8) {
+    let sp = soc.get_sp() as i32;
+    let e8 = e8 as i32;
+    soc.res_flag(7);
+    soc.res_flag(6);
+    if e8 > 0 {
+        if (sp & 0xfff) + e8 > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
+        if (sp & 0xffff) + e8 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
+        soc.set_sp((sp + e8) as u16);
+    } else {
+        soc.res_flag(5);
+        soc.res_flag(4);
+    };
+    soc.set_sp((sp + e8) as u16);
+    soc.pc_inc(2);
+    soc.cyc_inc(4);
+}
+// ld_hl_sp_e8
+fn ld_hl_sp_e8(soc: &mut SoC, e8: i8) {
+    let sp = soc.get_sp() as i32;
+    let e8 = e8 as i32;
+    soc.res_flag(7);
+    soc.res_flag(6);
+    if e8 > 0 {
+        if (sp & 0xfff) + e8 > 0xfff {soc.set_flag(5);} else {soc.res_flag(5);};
+        if (sp & 0xffff) + e8 > 0xffff {soc.set_flag(4);} else {soc.res_flag(4);};
+        soc.set_sp((sp + e8) as u16);
+    } else {
+        soc.res_flag(5);
+        soc.res_flag(4);
+    };
+    soc.set_r16(2, (sp + e8) as u16);
+    soc.pc_inc(2);
+    soc.cyc_inc(3);
+}
+// ld_a16_sp
+fn ld_a16_sp(soc: &mut SoC, a16: u16) {
+    soc.ram_write(a16, soc.get_sp() as u8);
+    soc.ram_write(a16 + 1, (soc.get_sp() >> 8) as u8);
+    soc.pc_inc(3);
+    soc.cyc_inc(5);
+}
+// jp_a16
+fn jp_a16(soc: &mut SoC, a16: u16) {
+    soc.set_pc(a16);
+    soc.cyc_inc(4);
+}
+// call_a16
+fn call_a16(soc: &mut SoC, a16: u16) {
+    let pc = soc.get_pc().wrapping_add(3);
+    stack_push(soc, pc);
+    soc.set_pc(a16);
+    soc.cyc_inc(6);
+}
+// ld_a16_a
+fn ld_a16_a(soc: &mut SoC, a16: u16) {
+    soc.ram_write(a16, soc.get_r8(7));
+    soc.pc_inc(3);
+    soc.cyc_inc(4);
+}
+// ld_a_a16
+fn ld_a_a16(soc: &mut SoC, a16: u16) {
+    soc.set_r8(7, soc.ram_read(a16));
+    soc.pc_inc(3);
+    soc.cyc_inc(4);
+}
+// halt
+fn halt(soc: &mut SoC) {
+    if !soc.ime && soc.bus.pending_interrupts() != 0 {
+        if soc.ime_pending {
+            // EI; HALT 且已有中断挂起：中断返回地址仍是 HALT 本身
+            soc.cyc_inc(1);
+            return;
+        }
+        // IME=0 且已有中断挂起时不进入 HALT，并触发 HALT bug
+        soc.halt_bug = true;
+    } else {
+        soc.halted = true;
+    }
+    soc.pc_inc(1);
+    soc.cyc_inc(1);
+}
+// stop
+fn stop(soc: &mut SoC) {
+    soc.pc_inc(2);
+    // CGB：KEY1 bit0 置位时切换 CPU 速度，暂停的周期在指令结束后补上
+    soc.bus.switch_speed();
+    soc.cyc_inc(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 32 KiB 无 MBC 的 ROM，program 放在 0x0000，其余为 NOP；SP 指向 HRAM 顶端
+    fn soc(program: &[u8]) -> SoC {
+        let mut rom = vec![0; 0x8000];
+        rom[..program.len()].copy_from_slice(program);
+        let mut soc = SoC::with_rom(rom);
+        soc.sp = 0xfffe;
+        soc
+    }
+
+    #[test]
+    fn interrupt_dispatch_order_and_cost() {
+        let mut soc = soc(&[]);
+        soc.set_ime();
+        soc.bus.ie = 0x05;
+        soc.request_interrupt(Interrupt::Timer);
+        soc.request_interrupt(Interrupt::VBlank);
+        soc.one_step().unwrap();
+        // VBlank 优先于 Timer，响应耗时 5 个 M-cycle，返回地址压栈，IME 清除
+        assert_eq!((soc.get_pc(), soc.get_cyc()), (0x40, 5));
+        assert_eq!(soc.bus.int_flag, Interrupt::Timer.bit());
+        assert_eq!((soc.sp, soc.ram_read(0xfffd), soc.ram_read(0xfffc)), (0xfffc, 0x00, 0x00));
+        assert!(!soc.get_ime());
+        soc.one_step().unwrap();
+        assert_eq!(soc.get_pc(), 0x41);
+    }
+
+    #[test]
+    fn disabled_interrupt_is_not_dispatched() {
+        let mut soc = soc(&[]);
+        soc.set_ime();
+        soc.bus.ie = 0x01;
+        soc.request_interrupt(Interrupt::Timer);
+        soc.one_step().unwrap();
+        assert_eq!((soc.get_pc(), soc.get_cyc()), (0x01, 1));
+    }
+
+    #[test]
+    fn halt_bug_reads_next_byte_twice() {
+        // HALT; INC A; NOP
+        let mut soc = soc(&[0x76, 0x3c, 0x00]);
+        soc.bus.ie = 0x01;
+        soc.request_interrupt(Interrupt::VBlank);
+        soc.one_step().unwrap();
+        assert!(!soc.halt());
+        // IME=0 且已有中断挂起：HALT 之后的字节被读两次
+        soc.one_step().unwrap();
+        assert_eq!((soc.get_r8(7), soc.get_pc()), (1, 1));
+        soc.one_step().unwrap();
+        assert_eq!((soc.get_r8(7), soc.get_pc()), (2, 2));
+    }
+
+    #[test]
+    fn halt_wakes_without_dispatch_when_ime_is_off() {
+        // HALT; INC A
+        let mut soc = soc(&[0x76, 0x3c]);
+        soc.bus.ie = 0x04;
+        soc.one_step().unwrap();
+        assert!(soc.halt());
+        for _ in 0..10 {soc.one_step().unwrap();}
+        assert_eq!((soc.get_pc(), soc.get_cyc()), (1, 11));
+        soc.request_interrupt(Interrupt::Timer);
+        soc.one_step().unwrap();
+        assert!(!soc.halt());
+        assert_eq!((soc.get_r8(7), soc.get_pc()), (1, 2));
+    }
+
+    #[test]
+    fn ei_takes_effect_after_next_instruction() {
+        // EI; NOP; NOP
+        let mut soc = soc(&[0xfb, 0x00, 0x00]);
+        soc.bus.ie = 0x01;
+        soc.request_interrupt(Interrupt::VBlank);
+        soc.one_step().unwrap();
+        assert!(!soc.get_ime() && soc.get_ime_pending());
+        soc.one_step().unwrap();
+        assert!(soc.get_ime());
+        assert_eq!(soc.get_pc(), 2);
+        soc.one_step().unwrap();
+        assert_eq!(soc.get_pc(), 0x40);
+        assert_eq!(soc.ram_read(0xfffc), 0x02);
+    }
+
+    #[test]
+    fn ei_then_di_never_dispatches() {
+        // EI; DI; NOP
+        let mut soc = soc(&[0xfb, 0xf3, 0x00]);
+        soc.bus.ie = 0x01;
+        soc.request_interrupt(Interrupt::VBlank);
+        for _ in 0..3 {soc.one_step().unwrap();}
+        assert!(!soc.get_ime() && !soc.get_ime_pending());
+        assert_eq!(soc.get_pc(), 3);
+    }
+
+    #[test]
+    fn autosave_only_with_battery() {
+        let dir = std::env::temp_dir();
+        for (cartridge_type, battery) in [(0x02u8, false), (0x03, true)] {
+            let path = dir.join(format!("simu83-autosave-{}-{cartridge_type}.sav", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            let mut rom = vec![0; 0x8000];
+            rom[0x147] = cartridge_type;
+            rom[0x149] = 0x02;
+            let mut soc = SoC::with_rom(rom);
+            soc.set_autosave(Some(path.clone()));
+            drop(soc);
+            assert_eq!(path.exists(), battery);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn run_until_serial_accepts_huge_budget() {
+        pyo3::prepare_freethreaded_python();
+        let mut soc = soc(&[]);
+        soc.one_step().unwrap();
+        soc.bus.serial.output.extend(b"ok");
+        Python::with_gil(|py| assert!(soc.run_until_serial_contains(py, "ok", u128::MAX).unwrap()));
+    }
+
+    // 带正确 logo 与头部校验和的 ROM，能通过 DMG boot ROM 的检查
+    fn bootable_rom() -> Vec<u8> {
+        let boot = include_bytes!("dmg_boot.bin");
+        let mut rom = vec![0; 0x8000];
+        rom[0x104..0x134].copy_from_slice(&boot[0xa8..0xd8]);
+        rom[0x14d] = rom[0x134..0x14d].iter().fold(0u8, |x, &b| x.wrapping_sub(b).wrapping_sub(1));
+        rom
+    }
+
+    // 寄存器、DIV 内部计数器、各通道的开关与音量，以及 boot ROM 写过的 IO 寄存器
+    // （LY/STAT 取决于 boot ROM 结束时 PPU 的位置，DMA、OBP0、OBP1 不由 boot ROM 设置，不比较）
+    fn post_boot_state(soc: &SoC) -> Vec<u16> {
+        let apu = &soc.bus.apu;
+        let mut state: Vec<u16> = (0..4).map(|i| soc.get_r16(i)).collect();
+        state.extend([soc.sp, soc.pc, soc.bus.timer.div]);
+        for (enabled, volume) in [(apu.square1.enabled, apu.square1.envelope.volume), (apu.square2.enabled, apu.square2.envelope.volume), (apu.noise.enabled, apu.noise.envelope.volume)] {
+            state.extend([enabled as u16, volume as u16]);
+        }
+        state.push(apu.wave.enabled as u16);
+        for addr in (0xff00..=0xff7f).chain([0xffff]) {
+            if matches!(addr, 0xff41 | 0xff44 | 0xff46 | 0xff48 | 0xff49) {continue;}
+            state.push(soc.ram_read(addr) as u16);
+        }
+        state
+    }
+
+    #[test]
+    fn skip_boot_matches_dmg_boot_rom() {
+        let mut real = SoC::with_rom(bootable_rom());
+        real.bus.boot_rom = Some(include_bytes!("dmg_boot.bin").to_vec());
+        while real.bus.boot_rom.is_some() {real.one_step().unwrap();}
+        let mut skipped = SoC::with_rom(bootable_rom());
+        skipped.skip_boot(Some(Model::Dmg));
+        assert_eq!(post_boot_state(&skipped), post_boot_state(&real));
+        assert_eq!(skipped.ram_read(0xff04), 0xcf);
+    }
+}
@@ -0,0 +1,125 @@
+// MBC1：5 位 ROM bank 寄存器 BANK1、2 位 BANK2 寄存器与 banking 模式选择
+//
+// MBC1M 多合一卡带把 BANK2 接到 ROM 地址的第 18 位而不是第 19 位，
+// 因此 BANK1 只有低 4 位有效
+pub struct Mbc1 {
+    pub ram_enable: bool,
+    pub bank1: u8,
+    pub bank2: u8,
+    pub mode: bool,
+    pub multicart: bool,
+}
+
+// 启动画面的 Nintendo logo（卡带头部 0x0104-0x0133）
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+impl Mbc1 {
+    pub fn new(multicart: bool) -> Self {
+        Self { ram_enable: false, bank1: 1, bank2: 0, mode: false, multicart }
+    }
+
+    // 多合一卡带检测：1 MiB ROM，且第 0x10 个 bank 的头部也带有 Nintendo logo
+    pub fn detect_multicart(rom: &[u8]) -> bool {
+        rom.len() == 0x100000 && rom.get(0x40104..0x40134) == Some(&NINTENDO_LOGO[..])
+    }
+
+    fn bank2_shift(&self) -> u32 {
+        if self.multicart {4} else {5}
+    }
+
+    fn bank1_mask(&self) -> u8 {
+        if self.multicart {0x0f} else {0x1f}
+    }
+
+    // 0x0000-0x3FFF 映射的 ROM bank（模式 1 下受 BANK2 影响）
+    pub fn rom_bank_lo(&self) -> usize {
+        if self.mode {(self.bank2 as usize) << self.bank2_shift()} else {0}
+    }
+
+    // 0x4000-0x7FFF 映射的 ROM bank
+    pub fn rom_bank_hi(&self) -> usize {
+        ((self.bank2 as usize) << self.bank2_shift()) | (self.bank1 & self.bank1_mask()) as usize
+    }
+
+    // 外部 RAM bank（仅模式 1 下由 BANK2 选择）
+    pub fn ram_bank(&self) -> usize {
+        if self.mode {self.bank2 as usize} else {0}
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = data & 0x0f == 0x0a,
+            0x2000..=0x3fff => {
+                // bank 0 怪癖：写入的 5 位值为 0 时按 1 处理
+                self.bank1 = data & 0x1f;
+                if self.bank1 == 0 {self.bank1 = 1;}
+            }
+            0x4000..=0x5fff => self.bank2 = data & 0x03,
+            _               => self.mode = data & 0x01 != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cartridge::Cartridge, mbc::{Mbc, banked_rom}};
+
+    #[test]
+    fn bank1_zero_selects_bank_one() {
+        let mut cart = Cartridge::new(banked_rom(8, 0x01, 0));
+        assert_eq!(cart.read_rom(0x4000), 1);
+        cart.write_rom(0x2000, 0x05);
+        assert_eq!(cart.read_rom(0x4000), 5);
+        // 只比较低 5 位：0x20 也按 0 处理
+        cart.write_rom(0x2000, 0x20);
+        assert_eq!(cart.read_rom(0x4000), 1);
+        cart.write_rom(0x2000, 0x00);
+        assert_eq!(cart.read_rom(0x4000), 1);
+    }
+
+    #[test]
+    fn large_rom_uses_bank2_as_high_bits() {
+        let mut cart = Cartridge::new(banked_rom(64, 0x01, 0));
+        cart.write_rom(0x2000, 0x02);
+        cart.write_rom(0x4000, 0x01);
+        assert_eq!(cart.read_rom(0x4000), 0x22);
+        // 模式 0 下 0x0000-0x3FFF 固定为 bank 0，模式 1 下受 BANK2 影响
+        assert_eq!(cart.read_rom(0x0000), 0x00);
+        cart.write_rom(0x6000, 0x01);
+        assert_eq!(cart.read_rom(0x0000), 0x20);
+        assert_eq!(cart.read_rom(0x4000), 0x22);
+    }
+
+    #[test]
+    fn ram_banking_follows_mode() {
+        let mut cart = Cartridge::new(banked_rom(4, 0x03, 0x03));
+        assert_eq!(cart.read_ram(0xa000), 0xff);
+        cart.write_rom(0x0000, 0x0a);
+        cart.write_rom(0x4000, 0x02);
+        cart.write_ram(0xa000, 0x11);
+        cart.write_rom(0x6000, 0x01);
+        cart.write_ram(0xa000, 0x22);
+        assert_eq!(cart.ram[0x0000], 0x11);
+        assert_eq!(cart.ram[0x4000], 0x22);
+        cart.write_rom(0x0000, 0x00);
+        assert_eq!(cart.read_ram(0xa000), 0xff);
+    }
+
+    #[test]
+    fn multicart_wires_bank2_to_bit_4() {
+        let mut rom = banked_rom(64, 0x01, 0);
+        rom[0x40104..0x40134].copy_from_slice(&NINTENDO_LOGO);
+        let mut cart = Cartridge::new(rom);
+        assert!(matches!(&cart.mbc, Mbc::Mbc1(mbc1) if mbc1.multicart));
+        cart.write_rom(0x2000, 0x13);
+        cart.write_rom(0x4000, 0x02);
+        assert_eq!(cart.read_rom(0x4000), 0x23);
+        cart.write_rom(0x6000, 0x01);
+        assert_eq!(cart.read_rom(0x0000), 0x20);
+    }
+}
@@ -0,0 +1,64 @@
+// MBC2：0x0000-0x3FFF 的写入按地址 bit8 区分 RAM 使能（bit8=0）与 4 位 ROM bank 寄存器（bit8=1）
+//
+// 内置 512 x 4 位 RAM，只接了低 4 位数据线，在 0xA000-0xBFFF 内每 512 字节重复一次
+pub const MBC2_RAM_SIZE: usize = 0x200;
+
+pub struct Mbc2 {
+    pub ram_enable: bool,
+    pub rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new() -> Self {
+        Self { ram_enable: false, rom_bank: 1 }
+    }
+
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize
+    }
+
+    // 内置 RAM 的下标（地址只解码低 9 位）
+    pub fn ram_index(&self, addr: u16) -> Option<usize> {
+        if self.ram_enable {Some((addr & 0x1ff) as usize)} else {None}
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        if addr >= 0x4000 {return;}
+        if addr & 0x100 == 0 {
+            self.ram_enable = data & 0x0f == 0x0a;
+        } else {
+            self.rom_bank = data & 0x0f;
+            if self.rom_bank == 0 {self.rom_bank = 1;}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cartridge::Cartridge, mbc::banked_rom};
+
+    #[test]
+    fn address_bit8_selects_register() {
+        let mut cart = Cartridge::new(banked_rom(16, 0x06, 0));
+        cart.write_rom(0x2100, 0x05);
+        assert_eq!(cart.read_rom(0x4000), 5);
+        // bit8 为 0 时写的是 RAM 使能，不影响 ROM bank
+        cart.write_rom(0x2000, 0x0a);
+        assert_eq!(cart.read_rom(0x4000), 5);
+        cart.write_rom(0x3f00, 0x00);
+        assert_eq!(cart.read_rom(0x4000), 1);
+    }
+
+    #[test]
+    fn half_byte_ram_mirrors_every_512_bytes() {
+        let mut cart = Cartridge::new(banked_rom(2, 0x06, 0));
+        assert_eq!(cart.ram.len(), 0x200);
+        assert_eq!(cart.read_ram(0xa000), 0xff);
+        cart.write_rom(0x0000, 0x0a);
+        cart.write_ram(0xa010, 0xab);
+        assert_eq!(cart.ram[0x010], 0x0b);
+        assert_eq!(cart.read_ram(0xa010), 0xfb);
+        assert_eq!(cart.read_ram(0xa210), 0xfb);
+        assert_eq!(cart.read_ram(0xbe10), 0xfb);
+    }
+}
@@ -0,0 +1,339 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use pyo3::prelude::*;
+
+// 一秒对应的 M-cycle 数（4.194304 MHz / 4）
+pub const CYCLES_PER_SECOND: u64 = 1 << 20;
+
+// RTC 各寄存器的有效位
+const RTC_MASK: [u8; 5] = [0x3f, 0x3f, 0x1f, 0xff, 0xc1];
+
+// 存档中 RTC 数据的长度（旧格式的时间戳只有 4 字节，共 44 字节）
+pub const RTC_FOOTER_SIZE: usize = 48;
+pub const RTC_FOOTER_SIZE_SHORT: usize = 44;
+
+// RTC 的时间来源
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcClock {
+    // 由模拟的周期计数驱动，结果可复现
+    Cycles,
+    // 由宿主机的系统时间驱动
+    WallTime,
+}
+
+// MBC3 实时时钟：秒、分、时、天数低 8 位、天数高位（bit0 天数第 8 位，bit6 停止，bit7 天数溢出）
+#[derive(Debug, Clone)]
+pub struct Rtc {
+    pub regs: [u8; 5],
+    pub latched: [u8; 5],
+    pub clock: RtcClock,
+    // 不足一秒的累计量：Cycles 模式下为 M-cycle，WallTime 模式下为纳秒
+    sub_second: u64,
+    // WallTime 模式下上次同步的系统时间
+    last_sync: SystemTime,
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self { regs: [0; 5], latched: [0; 5], clock: RtcClock::Cycles, sub_second: 0, last_sync: SystemTime::now() }
+    }
+}
+
+impl Rtc {
+    fn halted(&self) -> bool {
+        self.regs[4] & 0x40 != 0
+    }
+
+    // 前进一秒；计数器超出合法范围时只在其位宽内回绕，不向高位进位
+    fn advance_second(&mut self) {
+        self.regs[0] = (self.regs[0] + 1) & 0x3f;
+        if self.regs[0] != 60 {return;}
+        self.regs[0] = 0;
+        self.regs[1] = (self.regs[1] + 1) & 0x3f;
+        if self.regs[1] != 60 {return;}
+        self.regs[1] = 0;
+        self.regs[2] = (self.regs[2] + 1) & 0x1f;
+        if self.regs[2] != 24 {return;}
+        self.regs[2] = 0;
+        let days = ((((self.regs[4] & 0x01) as u16) << 8) | self.regs[3] as u16) + 1;
+        self.regs[3] = days as u8;
+        self.regs[4] = (self.regs[4] & 0xfe) | ((days >> 8) as u8 & 0x01);
+        if days > 0x1ff {
+            // 天数计数器溢出：置位进位标志，直到软件清除
+            self.regs[4] |= 0x80;
+        }
+    }
+
+    // 秒、分、时都在合法范围内
+    fn in_range(&self) -> bool {
+        self.regs[0] < 60 && self.regs[1] < 60 && self.regs[2] < 24
+    }
+
+    // 前进 seconds 秒：先逐秒推进到各计数器回到合法范围，其余按进制一次换算
+    // （存档载入时可能补上多年的时间，不能逐秒循环）
+    pub fn advance_seconds(&mut self, mut seconds: u64) {
+        while seconds > 0 && (seconds == 1 || !self.in_range()) {
+            self.advance_second();
+            seconds -= 1;
+        }
+        if seconds == 0 {return;}
+        let total = self.regs[0] as u64 + seconds;
+        self.regs[0] = (total % 60) as u8;
+        let total = self.regs[1] as u64 + total / 60;
+        self.regs[1] = (total % 60) as u8;
+        let total = self.regs[2] as u64 + total / 60;
+        self.regs[2] = (total % 24) as u8;
+        let days = ((((self.regs[4] & 0x01) as u64) << 8) | self.regs[3] as u64) + total / 24;
+        self.regs[3] = days as u8;
+        self.regs[4] = (self.regs[4] & 0xfe) | ((days >> 8) as u8 & 0x01);
+        if days > 0x1ff {self.regs[4] |= 0x80;}
+    }
+
+    // Cycles 模式下由周期计数推进
+    pub fn tick(&mut self, cycles: u64) {
+        if self.clock != RtcClock::Cycles || self.halted() {return;}
+        self.sub_second += cycles;
+        let seconds = self.sub_second / CYCLES_PER_SECOND;
+        self.sub_second %= CYCLES_PER_SECOND;
+        self.advance_seconds(seconds);
+    }
+
+    // WallTime 模式下按上次同步以来经过的系统时间推进
+    pub fn sync(&mut self) {
+        let now = SystemTime::now();
+        if self.clock == RtcClock::WallTime && !self.halted() {
+            let elapsed = now.duration_since(self.last_sync).map_or(0, |d| d.as_nanos() as u64);
+            self.sub_second += elapsed;
+            let seconds = self.sub_second / 1_000_000_000;
+            self.sub_second %= 1_000_000_000;
+            self.advance_seconds(seconds);
+        }
+        self.last_sync = now;
+    }
+
+    pub fn set_clock(&mut self, clock: RtcClock) {
+        self.sync();
+        self.clock = clock;
+        self.sub_second = 0;
+    }
+
+    pub fn latch(&mut self) {
+        self.sync();
+        self.latched = self.regs;
+    }
+
+    pub fn read(&self, select: u8) -> u8 {
+        let index = (select - 0x08) as usize;
+        self.latched[index] & RTC_MASK[index]
+    }
+
+    // 存档末尾的 RTC 数据（与 BGB/VBA 等模拟器通用的 48 字节格式）：
+    // 当前寄存器 5 x u32、锁存寄存器 5 x u32、Unix 时间戳 u64，均为小端
+    pub fn footer(&mut self) -> Vec<u8> {
+        self.sync();
+        let mut footer = Vec::with_capacity(RTC_FOOTER_SIZE);
+        for reg in self.regs.iter().chain(self.latched.iter()) {
+            footer.extend_from_slice(&(*reg as u32).to_le_bytes());
+        }
+        let timestamp = self.last_sync.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        footer.extend_from_slice(&timestamp.to_le_bytes());
+        footer
+    }
+
+    // 载入存档末尾的 RTC 数据（兼容时间戳只有 4 字节的 44 字节格式）；
+    // WallTime 模式下把存档以来经过的时间补进时钟
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        let word = |i: usize| u32::from_le_bytes([footer[i * 4], footer[i * 4 + 1], footer[i * 4 + 2], footer[i * 4 + 3]]);
+        for (i, mask) in RTC_MASK.into_iter().enumerate() {
+            self.regs[i] = word(i) as u8 & mask;
+            self.latched[i] = word(i + 5) as u8 & mask;
+        }
+        let timestamp = if footer.len() >= RTC_FOOTER_SIZE {
+            u64::from_le_bytes(footer[40..48].try_into().unwrap_or_default())
+        } else {
+            word(10) as u64
+        };
+        self.sub_second = 0;
+        self.last_sync = match self.clock {
+            RtcClock::WallTime => UNIX_EPOCH + Duration::from_secs(timestamp),
+            RtcClock::Cycles => SystemTime::now(),
+        };
+        self.sync();
+    }
+
+    pub fn write(&mut self, select: u8, data: u8) {
+        self.sync();
+        let index = (select - 0x08) as usize;
+        self.regs[index] = data & RTC_MASK[index];
+        // 写秒寄存器会清零内部的分频计数
+        if index == 0 {self.sub_second = 0;}
+    }
+}
+
+// MBC3：7 位 ROM bank 寄存器，RAM bank 0-3 与 RTC 寄存器 0x08-0x0C 共用选择寄存器
+pub struct Mbc3 {
+    pub ram_enable: bool,
+    pub rom_bank: u8,
+    pub select: u8,
+    pub rtc: Option<Rtc>,
+    latch_prev: u8,
+}
+
+impl Mbc3 {
+    pub fn new(has_timer: bool) -> Self {
+        Self { ram_enable: false, rom_bank: 1, select: 0, rtc: has_timer.then(Rtc::default), latch_prev: 0xff }
+    }
+
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize
+    }
+
+    // 当前选中的 RAM bank；选中 RTC 寄存器时为 None
+    pub fn ram_bank(&self) -> Option<usize> {
+        if self.select <= 0x07 {Some(self.select as usize)} else {None}
+    }
+
+    // 当前选中的 RTC 寄存器
+    pub fn rtc_select(&self) -> Option<u8> {
+        if (0x08..=0x0c).contains(&self.select) && self.rtc.is_some() {Some(self.select)} else {None}
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = data & 0x0f == 0x0a,
+            0x2000..=0x3fff => {
+                self.rom_bank = data & 0x7f;
+                if self.rom_bank == 0 {self.rom_bank = 1;}
+            }
+            0x4000..=0x5fff => self.select = data & 0x0f,
+            _ => {
+                // 先写 0x00 再写 0x01 时锁存当前时间
+                if self.latch_prev == 0x00 && data == 0x01 && let Some(rtc) = &mut self.rtc {
+                    rtc.latch();
+                }
+                self.latch_prev = data;
+            }
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        if let Some(rtc) = &mut self.rtc {rtc.tick(cycles);}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cartridge::Cartridge, mbc::{Mbc, banked_rom}};
+
+    // MBC3+TIMER+RAM+BATTERY，32 KiB RAM
+    fn cart() -> Cartridge {
+        let mut cart = Cartridge::new(banked_rom(128, 0x10, 0x03));
+        cart.write_rom(0x0000, 0x0a);
+        cart
+    }
+
+    fn latch(cart: &mut Cartridge) {
+        cart.write_rom(0x6000, 0x00);
+        cart.write_rom(0x6000, 0x01);
+    }
+
+    fn read_rtc(cart: &mut Cartridge, select: u8) -> u8 {
+        cart.write_rom(0x4000, select);
+        cart.read_ram(0xa000)
+    }
+
+    #[test]
+    fn rom_and_ram_banking() {
+        let mut cart = cart();
+        cart.write_rom(0x2000, 0x7f);
+        assert_eq!(cart.read_rom(0x4000), 0x7f);
+        cart.write_rom(0x2000, 0x00);
+        assert_eq!(cart.read_rom(0x4000), 0x01);
+        cart.write_rom(0x4000, 0x03);
+        cart.write_ram(0xa123, 0x5a);
+        assert_eq!(cart.ram[0x6123], 0x5a);
+    }
+
+    #[test]
+    fn latch_freezes_registers() {
+        let mut cart = cart();
+        cart.tick(0, 61 * CYCLES_PER_SECOND);
+        assert_eq!(read_rtc(&mut cart, 0x08), 0);
+        latch(&mut cart);
+        assert_eq!((read_rtc(&mut cart, 0x08), read_rtc(&mut cart, 0x09)), (1, 1));
+        // 锁存值在下次锁存前保持不变；只写 0x01 不会锁存
+        cart.tick(0, 5 * CYCLES_PER_SECOND);
+        cart.write_rom(0x6000, 0x01);
+        assert_eq!(read_rtc(&mut cart, 0x08), 1);
+        latch(&mut cart);
+        assert_eq!(read_rtc(&mut cart, 0x08), 6);
+    }
+
+    #[test]
+    fn halt_stops_the_clock() {
+        let mut cart = cart();
+        cart.write_rom(0x4000, 0x0c);
+        cart.write_ram(0xa000, 0x40);
+        cart.tick(0, 10 * CYCLES_PER_SECOND);
+        latch(&mut cart);
+        assert_eq!(read_rtc(&mut cart, 0x08), 0);
+        assert_eq!(read_rtc(&mut cart, 0x0c), 0x40);
+    }
+
+    #[test]
+    fn day_counter_carry() {
+        let mut rtc = Rtc { regs: [59, 59, 23, 0xff, 0x01], ..Rtc::default() };
+        rtc.advance_seconds(1);
+        assert_eq!(rtc.regs, [0, 0, 0, 0x00, 0x80]);
+        // 进位标志保持到软件清除
+        rtc.advance_seconds(24 * 60 * 60);
+        assert_eq!(rtc.regs, [0, 0, 0, 0x01, 0x80]);
+    }
+
+    #[test]
+    fn long_advance_matches_per_second_steps() {
+        let start = Rtc { regs: [58, 59, 22, 0xfe, 0x01], ..Rtc::default() };
+        for seconds in [2, 59, 3601, 86_400 * 3 + 7, 1_000_003] {
+            let (mut fast, mut slow) = (start.clone(), start.clone());
+            fast.advance_seconds(seconds);
+            for _ in 0..seconds {slow.advance_second();}
+            assert_eq!(fast.regs, slow.regs, "{seconds}");
+        }
+        // 超出合法范围的计数器先逐秒回绕
+        let mut rtc = Rtc { regs: [62, 0, 0, 0, 0], ..Rtc::default() };
+        rtc.advance_seconds(3);
+        assert_eq!(rtc.regs, [1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn stale_wall_time_footer_loads_quickly() {
+        let mut rtc = Rtc { clock: RtcClock::WallTime, ..Rtc::default() };
+        // 时间戳为 0 的存档：补上 1970 年至今的时间，天数溢出
+        rtc.load_footer(&[0; RTC_FOOTER_SIZE]);
+        assert_eq!(rtc.regs[4] & 0x80, 0x80);
+    }
+
+    #[test]
+    fn save_footer_round_trip() {
+        let mut cart = cart();
+        cart.ram[0x1234] = 0x77;
+        cart.tick(0, (3 * 60 * 60 + 2 * 60 + 1) * CYCLES_PER_SECOND);
+        latch(&mut cart);
+        let save = cart.export_save();
+        assert_eq!(save.len(), 0x8000 + RTC_FOOTER_SIZE);
+
+        let mut loaded = Cartridge::new(banked_rom(128, 0x10, 0x03));
+        loaded.load_save(&save).unwrap();
+        assert_eq!(loaded.ram[0x1234], 0x77);
+        let (Mbc::Mbc3(a), Mbc::Mbc3(b)) = (&cart.mbc, &loaded.mbc) else {panic!("不是 MBC3");};
+        let (a, b) = (a.rtc.as_ref().unwrap(), b.rtc.as_ref().unwrap());
+        assert_eq!((a.regs, a.latched), (b.regs, b.latched));
+        assert_eq!(b.regs[..3], [1, 2, 3]);
+
+        // 44 字节的旧格式同样可以载入，其他长度报错
+        assert!(loaded.load_save(&save[..0x8000 + RTC_FOOTER_SIZE_SHORT]).is_ok());
+        assert!(loaded.load_save(&save[..0x8000 + 10]).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+// MBC5：9 位 ROM bank 寄存器（最多 512 个 bank），4 位 RAM bank 寄存器（最多 16 个 bank）
+//
+// 带震动马达的卡带把 RAM bank 寄存器的 bit3 接到马达上，RAM bank 只剩低 3 位
+pub struct Mbc5 {
+    pub ram_enable: bool,
+    pub rom_bank: u16,
+    pub ram_bank: u8,
+    pub has_rumble: bool,
+    pub rumble: bool,
+    // 马达开关的变化：(发生时 CPU 的 M-cycle 计数，不受倍速模式影响, 是否开启)
+    pub rumble_events: Vec<(u64, bool)>,
+    cycles: u64,
+}
+
+impl Mbc5 {
+    pub fn new(has_rumble: bool) -> Self {
+        Self {
+            ram_enable: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+            rumble_events: Vec::new(),
+            cycles: 0,
+        }
+    }
+
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize
+    }
+
+    pub fn ram_bank(&self) -> usize {
+        if self.has_rumble {(self.ram_bank & 0x07) as usize} else {self.ram_bank as usize}
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x0000..=0x1fff => self.ram_enable = data == 0x0a,
+            0x2000..=0x2fff => self.rom_bank = (self.rom_bank & 0x100) | data as u16,
+            0x3000..=0x3fff => self.rom_bank = (self.rom_bank & 0xff) | (((data & 0x01) as u16) << 8),
+            0x4000..=0x5fff => {
+                self.ram_bank = data & 0x0f;
+                let rumble = self.has_rumble && data & 0x08 != 0;
+                if rumble != self.rumble {
+                    self.rumble = rumble;
+                    self.rumble_events.push((self.cycles, rumble));
+                }
+            }
+            _ => {},
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bus::Bus, cartridge::Cartridge, mbc::{Mbc, banked_rom}};
+
+    #[test]
+    fn nine_bit_rom_bank() {
+        let mut cart = Cartridge::new(banked_rom(512, 0x19, 0));
+        assert_eq!(cart.header.rom_size, 0x800000);
+        cart.write_rom(0x2000, 0x34);
+        cart.write_rom(0x3000, 0x01);
+        assert_eq!((cart.read_rom(0x4000), cart.read_rom(0x4001)), (0x34, 0x01));
+        // 与 MBC1 不同，bank 0 也可以映射到 0x4000
+        cart.write_rom(0x2000, 0x00);
+        cart.write_rom(0x3000, 0x00);
+        assert_eq!(cart.read_rom(0x4000), 0x00);
+    }
+
+    #[test]
+    fn ram_banking() {
+        let mut cart = Cartridge::new(banked_rom(4, 0x1b, 0x04));
+        cart.write_rom(0x0000, 0x0a);
+        cart.write_rom(0x4000, 0x0f);
+        cart.write_ram(0xa001, 0x99);
+        assert_eq!(cart.ram[0x1e001], 0x99);
+        // 只有 0x0A 使能 RAM
+        cart.write_rom(0x0000, 0x1a);
+        assert_eq!(cart.read_ram(0xa001), 0xff);
+    }
+
+    #[test]
+    fn rumble_uses_ram_bank_bit3() {
+        let mut cart = Cartridge::new(banked_rom(4, 0x1e, 0x03));
+        cart.write_rom(0x0000, 0x0a);
+        cart.tick(100, 100);
+        cart.write_rom(0x4000, 0x09);
+        cart.write_ram(0xa000, 0x42);
+        assert_eq!(cart.ram[0x2000], 0x42);
+        cart.tick(50, 50);
+        cart.write_rom(0x4000, 0x01);
+        let Mbc::Mbc5(mbc5) = &cart.mbc else {panic!("不是 MBC5");};
+        assert_eq!(mbc5.rumble_events, [(100, true), (150, false)]);
+    }
+
+    #[test]
+    fn rumble_timestamps_use_cpu_cycles_in_double_speed() {
+        let mut bus = Bus::new(Cartridge::new(banked_rom(4, 0x1e, 0x03)));
+        bus.double_speed = true;
+        bus.tick(100);
+        bus.write(0x4000, 0x08);
+        // 倍速模式下 RTC 计时减半，震动时间戳仍按 CPU 的 M-cycle
+        let Mbc::Mbc5(mbc5) = &bus.cart.mbc else {panic!("不是 MBC5");};
+        assert_eq!(mbc5.rumble_events, [(100, true)]);
+    }
+}
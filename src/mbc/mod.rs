@@ -0,0 +1,54 @@
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+
+use mbc1::Mbc1;
+use mbc2::Mbc2;
+use mbc3::Mbc3;
+use mbc5::Mbc5;
+
+// 卡带上的存储体控制器
+pub enum Mbc {
+    // 无 MBC：32 KiB ROM，可选 8 KiB RAM
+    None,
+    Mbc1(Mbc1),
+    Mbc2(Mbc2),
+    Mbc3(Mbc3),
+    Mbc5(Mbc5),
+}
+
+impl Mbc {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mbc::None    => "ROM",
+            Mbc::Mbc1(_) => "MBC1",
+            Mbc::Mbc2(_) => "MBC2",
+            Mbc::Mbc3(_) => "MBC3",
+            Mbc::Mbc5(_) => "MBC5",
+        }
+    }
+
+    // 推进随时间变化的卡带硬件：MBC3 的 RTC 按 rtc_cycles（倍速模式下减半）计时，MBC5 震动事件的时间戳按 CPU 的 M-cycle
+    pub fn tick(&mut self, cycles: u64, rtc_cycles: u64) {
+        match self {
+            Mbc::Mbc3(mbc3) => mbc3.tick(rtc_cycles),
+            Mbc::Mbc5(mbc5) => mbc5.tick(cycles),
+            _ => {},
+        }
+    }
+}
+
+// 测试用 ROM：共 banks 个 16 KiB bank，每个 bank 的头两个字节为其 bank 号（小端）
+#[cfg(test)]
+pub fn banked_rom(banks: usize, cartridge_type: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; banks * 0x4000];
+    for bank in 0..banks {
+        rom[bank * 0x4000] = bank as u8;
+        rom[bank * 0x4000 + 1] = (bank >> 8) as u8;
+    }
+    rom[0x147] = cartridge_type;
+    rom[0x148] = (banks / 2).trailing_zeros() as u8;
+    rom[0x149] = ram_size_code;
+    rom
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+
+use super::{Ppu, SCREEN_WIDTH};
+
+// 每行开始时第一次取 tile 的结果被丢弃，占用的 dot 数
+const FIRST_FETCH_DOTS: u8 = 6;
+// 取一个 tile 的 dot 数：tile 号、低字节、高字节各 2 个 dot
+const FETCH_DOTS: u8 = 6;
+// 取一个精灵 tile 的 dot 数；另有 0-5 个 dot 等待背景取数器，见 fifo_sprite_wait
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+// 精灵 FIFO 中的像素：颜色号、OAM 属性与 OAM 序号
+#[derive(Debug, Clone, Copy)]
+struct SpritePixel {color: u8, attr: u8, index: usize}
+
+// 像素 FIFO 的逐行状态
+#[derive(Debug, Default)]
+pub struct Fifo {
+    // 背景像素的颜色号与 CGB 属性
+    bg: VecDeque<(u8, u8)>,
+    sprite: VecDeque<SpritePixel>,
+    // 背景取数器：已进行的 dot 数、下一个要取的 tile 列、取到的一行 tile 数据
+    fetch_dot: u8,
+    fetch_x: u8,
+    fetched: Option<[(u8, u8); 8]>,
+    // 行首等待的 dot 数
+    stall: u8,
+    // 行首因 SCX 低 3 位需要丢弃的像素数
+    discard: u8,
+    // 已输出到屏幕的像素数
+    lx: u8,
+    in_window: bool,
+    // 本行 OAM 扫描得到的精灵（OAM 序号）及其是否已经取过
+    sprites: Vec<(usize, bool)>,
+    // 正在取的精灵及剩余 dot 数
+    sprite_fetch: Option<(usize, u8)>,
+    // 最近一次等待过背景取数器的 tile（背景或窗口坐标中的 tile 列）
+    wait_tile: Option<i32>,
+}
+
+// 像素 FIFO 渲染：背景/窗口取数器每 8 个 dot 推入 8 个像素，每个 dot 移出一个像素；
+// 取精灵会暂停像素输出，因此 mode 3 的长度随 SCX、窗口与精灵数量变化
+impl Ppu {
+    // 进入 mode 3 时初始化本行的 FIFO
+    pub fn fifo_start_line(&mut self) {
+        let sprites = if self.lcdc & 0x02 != 0 {self.scan_oam()} else {Vec::new()};
+        self.fifo = Fifo {
+            stall: FIRST_FETCH_DOTS,
+            discard: self.scx % 8,
+            sprites: sprites.into_iter().map(|i| (i, false)).collect(),
+            ..Fifo::default()
+        };
+        self.fifo_check_window();
+    }
+
+    // 推进一个 dot，本行 160 个像素全部输出后返回 true
+    pub fn fifo_dot(&mut self) -> bool {
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+
+        // 取精灵：像素输出与背景取数器都暂停，取完后合入精灵 FIFO
+        if let Some((index, remaining)) = self.fifo.sprite_fetch {
+            if remaining > 1 {
+                self.fifo.sprite_fetch = Some((index, remaining - 1));
+            } else {
+                self.fifo.sprite_fetch = None;
+                self.fifo_merge_sprite(index);
+            }
+            return false;
+        }
+
+        if !self.fifo.bg.is_empty() && self.fifo.discard == 0 && self.lcdc & 0x02 != 0 {
+            let lx = self.fifo.lx as usize;
+            let oam = &self.oam;
+            // 同时到达的精灵中 X 小的先取，X 相同时 OAM 序号小的先取
+            let found = self.fifo.sprites.iter_mut()
+                .filter(|(i, done)| !done && oam[i * 4 + 1] as usize <= lx + 8)
+                .min_by_key(|(i, _)| (oam[i * 4 + 1], *i))
+                .map(|entry| {entry.1 = true; entry.0});
+            if let Some(index) = found {
+                // 本 dot 计入取精灵的时间
+                let dots = self.fifo_sprite_wait(self.oam[index * 4 + 1]) + SPRITE_FETCH_DOTS;
+                self.fifo.sprite_fetch = Some((index, dots - 1));
+                return false;
+            }
+        }
+
+        if let Some((color, attr)) = self.fifo.bg.pop_front() {
+            let sprite = self.fifo.sprite.pop_front();
+            if self.fifo.discard > 0 {
+                self.fifo.discard -= 1;
+            } else {
+                let pixel = self.fifo_mix(color, attr, sprite);
+                self.back[self.ly as usize * SCREEN_WIDTH + self.fifo.lx as usize] = pixel;
+                self.fifo.lx += 1;
+            }
+        }
+        // 取数器推入的像素从下一个 dot 开始移出；切换到窗口后取数器从下一个 dot 开始重新取
+        self.fifo_fetch_step();
+        self.fifo_check_window();
+
+        let done = self.fifo.lx as usize == SCREEN_WIDTH;
+        if done && self.fifo.in_window {self.window_line += 1;}
+        done
+    }
+
+    // 当前 X 到达 WX-7 时切换到窗口：清空背景 FIFO，取数器从窗口第 0 列重新开始；
+    // DMG 中 LCDC bit0 为 0 时窗口也不显示
+    fn fifo_check_window(&mut self) {
+        let enabled = self.lcdc & 0x20 != 0 && (self.cgb || self.lcdc & 0x01 != 0);
+        if self.fifo.in_window || !enabled || !self.wy_triggered {return;}
+        if self.fifo.lx as usize + 7 < self.wx as usize {return;}
+        self.fifo.in_window = true;
+        self.fifo.bg.clear();
+        self.fifo.fetch_dot = 0;
+        self.fifo.fetch_x = 0;
+        self.fifo.fetched = None;
+        // WX 小于 7 时窗口左侧被截掉
+        if self.fifo.lx == 0 {self.fifo.discard = 7u8.saturating_sub(self.wx);}
+    }
+
+    // 精灵最左像素所在的 tile 第一次遇到精灵时，要等背景取数器取完该 tile：
+    // 等待的 dot 数为该 tile 中位于精灵最左像素右侧的像素数减 2；同一 tile 的后续精灵不再等待
+    fn fifo_sprite_wait(&mut self, x: u8) -> u8 {
+        let layer_x = if self.fifo.in_window {x as i32 - 1 - self.wx as i32} else {x as i32 - 8 + self.scx as i32};
+        let tile = layer_x.div_euclid(8);
+        if self.fifo.wait_tile == Some(tile) {return 0;}
+        self.fifo.wait_tile = Some(tile);
+        5u8.saturating_sub(layer_x.rem_euclid(8) as u8)
+    }
+
+    // 背景取数器推进一个 dot；取完后在背景 FIFO 为空时推入 8 个像素
+    fn fifo_fetch_step(&mut self) {
+        if self.fifo.fetched.is_none() {
+            self.fifo.fetch_dot += 1;
+            if self.fifo.fetch_dot == FETCH_DOTS {
+                self.fifo.fetched = Some(self.fifo_fetch_tile());
+                self.fifo.fetch_dot = 0;
+            }
+        }
+        if self.fifo.bg.is_empty() && let Some(pixels) = self.fifo.fetched.take() {
+            self.fifo.bg.extend(pixels);
+            self.fifo.fetch_x = self.fifo.fetch_x.wrapping_add(1);
+        }
+    }
+
+    // 取一行 8 个背景/窗口像素的颜色号与属性；SCX/SCY 在取 tile 号时读取，因此行中修改会立即生效
+    fn fifo_fetch_tile(&self) -> [(u8, u8); 8] {
+        let (map_bit, x, y) = if self.fifo.in_window {
+            (0x40, self.fifo.fetch_x, self.window_line)
+        } else {
+            (0x08, (self.scx / 8).wrapping_add(self.fifo.fetch_x), self.ly.wrapping_add(self.scy))
+        };
+        let map = if self.lcdc & map_bit != 0 {0x1c00} else {0x1800};
+        let index = map + (y as usize / 8) * 32 + (x & 0x1f) as usize;
+        std::array::from_fn(|col| self.bg_pixel(index, y % 8, col as u8))
+    }
+
+    // 把取到的精灵像素合入精灵 FIFO：DMG 中已有的不透明像素优先（先取的精灵优先级更高），
+    // CGB 中 OAM 序号小的优先
+    fn fifo_merge_sprite(&mut self, index: usize) {
+        let [y, x, tile, attr] = [self.oam[index * 4], self.oam[index * 4 + 1], self.oam[index * 4 + 2], self.oam[index * 4 + 3]];
+        let height = self.sprite_height();
+        let mut row = (self.ly as i32 - (y as i32 - 16)) as u8;
+        if attr & 0x40 != 0 {row = height - 1 - row;}
+        let base = self.obj_tile_addr(if height == 16 {tile & 0xfe} else {tile}, attr);
+        let by_index = self.obj_priority_by_index();
+        // 精灵左侧超出当前位置的部分不再显示
+        let skip = (self.fifo.lx as usize + 8).saturating_sub(x as usize);
+        for col in skip..8 {
+            let color = self.tile_pixel(base, row, if attr & 0x20 != 0 {7 - col as u8} else {col as u8});
+            let pixel = SpritePixel { color, attr, index };
+            match self.fifo.sprite.get_mut(col - skip) {
+                Some(slot) if slot.color == 0 || (by_index && color != 0 && index < slot.index) => *slot = pixel,
+                Some(_) => {},
+                None => self.fifo.sprite.push_back(pixel),
+            }
+        }
+    }
+
+    // 混合背景像素与精灵像素，得到输出的灰度或颜色
+    fn fifo_mix(&self, color: u8, attr: u8, sprite: Option<SpritePixel>) -> u16 {
+        let bg_on = self.cgb || self.lcdc & 0x01 != 0;
+        let color = if bg_on {color} else {0};
+        match sprite {
+            Some(sprite) if sprite.color != 0 && self.lcdc & 0x02 != 0 && !self.bg_over_obj(color, attr, sprite.attr) => {
+                self.obj_output(sprite.color, sprite.attr)
+            }
+            _ if bg_on => self.bg_output(color, attr),
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,466 @@
+pub mod fifo;
+pub mod scanline;
+
+use pyo3::prelude::*;
+
+use crate::interrupt::Interrupt;
+use fifo::Fifo;
+
+// 屏幕尺寸
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+pub const FRAME_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+// 每行 456 个 dot（T-cycle），每帧 154 行（144 行可见 + 10 行 VBlank）
+const DOTS_PER_LINE: u16 = 456;
+const LINES_PER_FRAME: u8 = 154;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS: u16 = 172;
+// 第 153 行中 LY 保持 153 的 dot 数
+const LINE_153_LY_DOTS: u16 = 4;
+
+// PPU 的实现方式
+#[pyclass(eq, eq_int)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PpuBackend {
+    // 在每行进入 HBlank 时一次画出整行，mode 3 固定 172 个 dot，速度快
+    Scanline,
+    // 逐 dot 模拟像素 FIFO，行中修改寄存器立即生效，mode 3 长度随 SCX、窗口与精灵变化
+    Fifo,
+}
+
+// PPU 模式（STAT 低 2 位）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {HBlank = 0, VBlank = 1, OamScan = 2, Drawing = 3}
+
+// DMG 灰度 0-3 对应的 RGB
+const DMG_RGB: [u8; 4] = [0xff, 0xaa, 0x55, 0x00];
+
+// CGB 调色板 RAM 中的颜色（小端 RGB555）
+pub fn cgb_color(palette_ram: &[u8; 0x40], palette: u8, color: u8) -> u16 {
+    let index = palette as usize * 8 + color as usize * 2;
+    u16::from_le_bytes([palette_ram[index], palette_ram[index + 1]]) & 0x7fff
+}
+
+// PPU：VRAM、OAM 与 0xFF40-0xFF4B 中的 LCD 寄存器，按 dot 推进并逐行画出 160x144 的画面
+//
+// 画面中每个像素在 DMG 模式下是经过调色板后的灰度 0-3（0 为最浅），CGB 模式下是 RGB555 颜色
+pub struct Ppu {
+    // CGB 有两个 VRAM bank，bank 1 存放背景属性与另一半 tile 数据
+    pub vram: [u8; 0x4000],
+    pub oam: [u8; 0xa0],
+    pub lcdc: u8,
+    pub scy: u8,
+    pub scx: u8,
+    pub ly: u8,
+    pub lyc: u8,
+    // STAT 的中断源使能位（bit3-6）
+    pub stat: u8,
+    pub bgp: u8,
+    pub obp0: u8,
+    pub obp1: u8,
+    pub wy: u8,
+    pub wx: u8,
+    // CGB 模式：0xFF4F VBK、0xFF68-0xFF6B 调色板 RAM 的索引与数据、0xFF6C OPRI
+    pub cgb: bool,
+    pub vbk: u8,
+    bcps: u8,
+    ocps: u8,
+    pub bg_palette: [u8; 0x40],
+    pub obj_palette: [u8; 0x40],
+    pub opri: u8,
+    pub mode: Mode,
+    pub backend: PpuBackend,
+    fifo: Fifo,
+    // STAT 中断线：各中断源相或，只在由低变高时请求中断
+    stat_line: bool,
+    // 待请求的中断位
+    irq: u8,
+    // 当前行内的 dot 位置
+    dot: u16,
+    // 窗口内部行计数器：只在窗口实际画出的行递增
+    window_line: u8,
+    // 本帧中 LY 是否已经等于过 WY
+    wy_triggered: bool,
+    // 第 153 行开始不久 LY 就提前变为 0，本行结束时不再递增
+    ly_wrapped: bool,
+    // 刚在可见行进入 HBlank（HBlank DMA 由此触发）
+    pub hblank: bool,
+    // 正在绘制的画面与最近一帧完整的画面
+    back: Box<[u16; FRAME_SIZE]>,
+    pub frame: Box<[u16; FRAME_SIZE]>,
+    pub frame_count: u64,
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Self {
+            vram: [0; 0x4000],
+            oam: [0; 0xa0],
+            lcdc: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            stat: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            cgb: false,
+            vbk: 0,
+            bcps: 0,
+            ocps: 0,
+            bg_palette: [0; 0x40],
+            obj_palette: [0; 0x40],
+            opri: 0,
+            mode: Mode::HBlank,
+            backend: PpuBackend::Scanline,
+            fifo: Fifo::default(),
+            stat_line: false,
+            irq: 0,
+            dot: 0,
+            window_line: 0,
+            wy_triggered: false,
+            ly_wrapped: false,
+            hblank: false,
+            back: Box::new([0; FRAME_SIZE]),
+            frame: Box::new([0; FRAME_SIZE]),
+            frame_count: 0,
+        }
+    }
+
+    pub fn lcd_on(&self) -> bool {
+        self.lcdc & 0x80 != 0
+    }
+
+    // 推进一个 M-cycle（普通速度 4 个 dot，CGB 倍速模式 2 个），返回请求的中断位
+    pub fn step(&mut self, dots: u8) -> u8 {
+        for _ in 0..dots {self.tick_dot();}
+        std::mem::take(&mut self.irq)
+    }
+
+    // LCD 关闭时显示的白色
+    fn blank_pixel(&self) -> u16 {
+        if self.cgb {0x7fff} else {0}
+    }
+
+    // 最近一帧的 RGB888，每像素 3 字节
+    pub fn frame_rgb(&self) -> Vec<u8> {
+        self.frame.iter().flat_map(|&pixel| {
+            if self.cgb {
+                [pixel & 0x1f, (pixel >> 5) & 0x1f, (pixel >> 10) & 0x1f].map(|c| ((c << 3) | (c >> 2)) as u8)
+            } else {
+                [DMG_RGB[pixel as usize & 0x03]; 3]
+            }
+        }).collect()
+    }
+
+    // 最近一帧的灰度 0-3；CGB 模式下按亮度换算
+    pub fn frame_shades(&self) -> Vec<u8> {
+        self.frame.iter().map(|&pixel| {
+            if self.cgb {
+                let luma = (pixel & 0x1f) * 2 + ((pixel >> 5) & 0x1f) * 5 + ((pixel >> 10) & 0x1f);
+                3 - (luma * 4 / 256).min(3) as u8
+            } else {
+                pixel as u8
+            }
+        }).collect()
+    }
+
+    // STAT 中断线：HBlank、VBlank、OAM 扫描与 LY=LYC 四个中断源按使能位相或
+    fn update_stat_line(&mut self) {
+        let line = (self.stat & 0x08 != 0 && self.mode == Mode::HBlank)
+                || (self.stat & 0x10 != 0 && self.mode == Mode::VBlank)
+                || (self.stat & 0x20 != 0 && self.mode == Mode::OamScan)
+                || (self.stat & 0x40 != 0 && self.ly == self.lyc);
+        let line = line && self.lcd_on();
+        if line && !self.stat_line {self.irq |= Interrupt::Stat.bit();}
+        self.stat_line = line;
+    }
+
+    fn tick_dot(&mut self) {
+        if !self.lcd_on() {return;}
+        self.dot += 1;
+        match self.mode {
+            Mode::OamScan if self.dot == OAM_SCAN_DOTS => {
+                self.mode = Mode::Drawing;
+                if self.backend == PpuBackend::Fifo {self.fifo_start_line();}
+            }
+            Mode::Drawing => match self.backend {
+                PpuBackend::Scanline => if self.dot >= OAM_SCAN_DOTS + DRAWING_DOTS {
+                    self.mode = Mode::HBlank;
+                    self.hblank = true;
+                    self.render_scanline();
+                },
+                PpuBackend::Fifo => if self.fifo_dot() {
+                    self.mode = Mode::HBlank;
+                    self.hblank = true;
+                },
+            },
+            _ => {},
+        }
+        // 第 153 行的第一个 M-cycle 之后 LY 就读出 0，LY=LYC 比较随之变化
+        if self.ly == LINES_PER_FRAME - 1 && self.dot == LINE_153_LY_DOTS {
+            self.ly = 0;
+            self.ly_wrapped = true;
+        }
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            if std::mem::take(&mut self.ly_wrapped) {
+                self.window_line = 0;
+                self.wy_triggered = false;
+                self.start_line();
+            } else {
+                self.ly += 1;
+                if self.ly == SCREEN_HEIGHT as u8 {
+                    self.mode = Mode::VBlank;
+                    std::mem::swap(&mut self.frame, &mut self.back);
+                    self.frame_count += 1;
+                    self.irq |= Interrupt::VBlank.bit();
+                } else if self.ly < SCREEN_HEIGHT as u8 {
+                    self.start_line();
+                }
+            }
+        }
+        self.update_stat_line();
+    }
+
+    // 进入可见行的 OAM 扫描
+    fn start_line(&mut self) {
+        self.mode = Mode::OamScan;
+        if self.ly == self.wy {self.wy_triggered = true;}
+    }
+
+    // 切换实现方式；在 mode 3 中途切换到像素 FIFO 时从当前位置重新开始本行
+    pub fn set_backend(&mut self, backend: PpuBackend) {
+        if backend == PpuBackend::Fifo && self.backend != backend && self.mode == Mode::Drawing {
+            self.fifo_start_line();
+        }
+        self.backend = backend;
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff40 => self.lcdc,
+            // bit7 恒为 1；LCD 关闭时模式位读出 0
+            0xff41 => {
+                let mode = if self.lcd_on() {self.mode as u8} else {0};
+                0x80 | self.stat | (((self.ly == self.lyc) as u8) << 2) | mode
+            }
+            0xff42 => self.scy,
+            0xff43 => self.scx,
+            0xff44 => self.ly,
+            0xff45 => self.lyc,
+            0xff47 => self.bgp,
+            0xff48 => self.obp0,
+            0xff49 => self.obp1,
+            0xff4a => self.wy,
+            0xff4b => self.wx,
+            0xff4f => 0xfe | self.vbk,
+            0xff68 => 0x40 | self.bcps,
+            0xff69 => self.bg_palette[(self.bcps & 0x3f) as usize],
+            0xff6a => 0x40 | self.ocps,
+            0xff6b => self.obj_palette[(self.ocps & 0x3f) as usize],
+            _      => 0xfe | self.opri,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xff40 => {
+                let was_on = self.lcd_on();
+                self.lcdc = data;
+                if was_on && !self.lcd_on() {
+                    // 关闭 LCD：LY 归零，屏幕显示空白
+                    self.ly = 0;
+                    self.dot = 0;
+                    self.ly_wrapped = false;
+                    self.mode = Mode::HBlank;
+                    let blank = self.blank_pixel();
+                    self.frame.fill(blank);
+                } else if !was_on && self.lcd_on() {
+                    self.window_line = 0;
+                    self.wy_triggered = false;
+                    self.start_line();
+                }
+                self.update_stat_line();
+            }
+            0xff41 => {
+                self.stat = data & 0x78;
+                self.update_stat_line();
+            }
+            0xff42 => self.scy = data,
+            0xff43 => self.scx = data,
+            // LY 只读
+            0xff44 => {},
+            0xff45 => {
+                self.lyc = data;
+                self.update_stat_line();
+            }
+            0xff47 => self.bgp = data,
+            0xff48 => self.obp0 = data,
+            0xff49 => self.obp1 = data,
+            0xff4a => self.wy = data,
+            0xff4b => self.wx = data,
+            0xff4f => self.vbk = data & 0x01,
+            0xff68 => self.bcps = data & 0xbf,
+            0xff69 => {
+                self.bg_palette[(self.bcps & 0x3f) as usize] = data;
+                self.bcps = palette_index_inc(self.bcps);
+            }
+            0xff6a => self.ocps = data & 0xbf,
+            0xff6b => {
+                self.obj_palette[(self.ocps & 0x3f) as usize] = data;
+                self.ocps = palette_index_inc(self.ocps);
+            }
+            _      => self.opri = data & 0x01,
+        }
+    }
+}
+
+// BCPS/OCPS bit7 置位时，每写一次数据寄存器索引自动加 1
+fn palette_index_inc(index: u8) -> u8 {
+    if index & 0x80 == 0 {index} else {0x80 | (index.wrapping_add(1) & 0x3f)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LCD 开启、显示背景与精灵的 PPU
+    fn ppu(backend: PpuBackend) -> Ppu {
+        let mut ppu = Ppu::new();
+        ppu.backend = backend;
+        ppu.write(0xff40, 0x93);
+        ppu
+    }
+
+    // 跑到下一次 mode 3，返回其长度（dot）
+    fn mode3_length(ppu: &mut Ppu) -> u16 {
+        while ppu.mode != Mode::Drawing {ppu.tick_dot();}
+        let mut dots = 0;
+        while ppu.mode == Mode::Drawing {
+            ppu.tick_dot();
+            dots += 1;
+        }
+        dots
+    }
+
+    #[test]
+    fn scanline_mode3_is_fixed() {
+        let mut ppu = ppu(PpuBackend::Scanline);
+        ppu.scx = 5;
+        ppu.oam[..2].copy_from_slice(&[16, 8]);
+        assert_eq!(mode3_length(&mut ppu), 172);
+    }
+
+    #[test]
+    fn scanline_sprite_limit_and_priority() {
+        let mut ppu = ppu(PpuBackend::Scanline);
+        // tile 1 全为颜色 3；OBP0 映射为灰度 3，OBP1 映射为灰度 1，背景为白色
+        ppu.vram[0x10..0x20].fill(0xff);
+        (ppu.obp0, ppu.obp1) = (0xc0, 0x40);
+        // 同一行 11 个精灵，OAM 中第 11 个（X=88）不显示
+        for i in 0..11 {ppu.oam[i * 4..i * 4 + 4].copy_from_slice(&[16, 8 + i as u8 * 8, 1, 0]);}
+        // DMG 中 X 小的精灵在上：OAM 序号为 0 的精灵（X=20，OBP1）被 X=16 的盖住，但盖住 X=24 的
+        ppu.oam[0..4].copy_from_slice(&[16, 20, 1, 0x10]);
+        run_to_line(&mut ppu, 1);
+        let line = &ppu.back[..SCREEN_WIDTH];
+        assert_eq!(line[..8], [0; 8]);
+        assert_eq!(line[8..16], [3; 8]);
+        assert_eq!(line[16..20], [1; 4]);
+        assert_eq!(line[20..80], [3; 60]);
+        assert_eq!(line[80..], [0; 80]);
+    }
+
+    #[test]
+    fn fifo_mode3_length_grows_with_scx() {
+        for scx in 0..16 {
+            let mut ppu = ppu(PpuBackend::Fifo);
+            ppu.scx = scx;
+            assert_eq!(mode3_length(&mut ppu), 172 + (scx % 8) as u16, "SCX={scx}");
+        }
+    }
+
+    #[test]
+    fn fifo_mode3_length_with_sprites() {
+        // (X, SCX, 长度)：每个精灵 6 个 dot，另按其所在 tile 的对齐等待 0-5 个 dot
+        for (x, scx, length) in [(0, 0, 183), (8, 0, 183), (12, 0, 179), (13, 0, 178), (15, 0, 178), (8, 3, 183), (8, 7, 185), (167, 0, 178), (168, 0, 172)] {
+            let mut ppu = ppu(PpuBackend::Fifo);
+            ppu.scx = scx;
+            ppu.oam[..2].copy_from_slice(&[16, x]);
+            assert_eq!(mode3_length(&mut ppu), length, "X={x} SCX={scx}");
+        }
+        // 同一 tile 中的后续精灵只需 6 个 dot；每行最多 10 个精灵
+        for (count, length) in [(2, 189), (10, 237), (12, 237)] {
+            let mut ppu = ppu(PpuBackend::Fifo);
+            for i in 0..count {ppu.oam[i * 4..i * 4 + 2].copy_from_slice(&[16, 8]);}
+            assert_eq!(mode3_length(&mut ppu), length, "{count} 个精灵");
+        }
+        // 精灵关闭时不取精灵
+        let mut ppu = ppu(PpuBackend::Fifo);
+        ppu.oam[..2].copy_from_slice(&[16, 8]);
+        ppu.lcdc &= !0x02;
+        assert_eq!(mode3_length(&mut ppu), 172);
+    }
+
+    #[test]
+    fn fifo_mode3_length_with_window() {
+        // WX=7 时窗口从行首开始，不额外等待；行中切换到窗口多 6 个 dot；WX=167 时窗口不在屏幕上
+        for (wx, length) in [(7, 172), (8, 178), (80, 178), (166, 178), (167, 172)] {
+            let mut ppu = ppu(PpuBackend::Fifo);
+            ppu.wx = wx;
+            ppu.write(0xff40, 0xb3);
+            assert_eq!(mode3_length(&mut ppu), length, "WX={wx}");
+        }
+    }
+
+    // 推进到第 line 行的第一个 dot
+    fn run_to_line(ppu: &mut Ppu, line: u8) {
+        while !(ppu.ly == line && ppu.dot == 0) {ppu.tick_dot();}
+    }
+
+    // 推进 dots 个 dot，返回请求 STAT 中断的 dot 序号
+    fn stat_irq_dots(ppu: &mut Ppu, dots: usize) -> Vec<usize> {
+        (0..dots).filter(|_| ppu.step(1) & Interrupt::Stat.bit() != 0).collect()
+    }
+
+    #[test]
+    fn overlapping_stat_sources_request_once() {
+        // 第 0 行 HBlank 与第 1 行 LY=LYC 相连，中断线一直为高，只请求一次
+        for (stat, count) in [(0x08, 2), (0x40, 1), (0x48, 1)] {
+            let mut ppu = ppu(PpuBackend::Scanline);
+            ppu.write(0xff45, 1);
+            ppu.write(0xff41, stat);
+            assert_eq!(stat_irq_dots(&mut ppu, 456 * 2).len(), count, "STAT={stat:#04x}");
+        }
+        // 中断线为高时再打开另一个中断源不会再次请求
+        let mut ppu = ppu(PpuBackend::Scanline);
+        ppu.write(0xff41, 0x40);
+        assert_eq!(ppu.step(0), Interrupt::Stat.bit());
+        ppu.write(0xff41, 0x60);
+        assert_eq!(ppu.step(0), 0);
+    }
+
+    #[test]
+    fn ly_reads_zero_early_in_line_153() {
+        let mut ppu = ppu(PpuBackend::Scanline);
+        ppu.write(0xff45, 0);
+        ppu.write(0xff41, 0x40);
+        run_to_line(&mut ppu, 153);
+        let lys: Vec<u8> = (0..8).map(|_| {ppu.tick_dot(); ppu.read(0xff44)}).collect();
+        assert_eq!(lys, [153, 153, 153, 0, 0, 0, 0, 0]);
+        // LYC=0 在第 153 行就成立，一直持续到第 0 行结束，只请求一次
+        run_to_line(&mut ppu, 153);
+        ppu.step(0);
+        assert_eq!(stat_irq_dots(&mut ppu, 456 * 2), [3]);
+        assert_eq!((ppu.ly, ppu.dot), (1, 0));
+        // LYC=153 只在第 153 行的前 4 个 dot 成立
+        ppu.write(0xff45, 153);
+        run_to_line(&mut ppu, 153);
+        let coincidence: Vec<bool> = (0..8).map(|_| {ppu.tick_dot(); ppu.read(0xff41) & 0x04 != 0}).collect();
+        assert_eq!(coincidence, [true, true, true, false, false, false, false, false]);
+    }
+}
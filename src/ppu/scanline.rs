@@ -0,0 +1,144 @@
+use super::{Ppu, SCREEN_WIDTH, cgb_color};
+
+// 每行最多显示的精灵数
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+// 按调色板把颜色号 0-3 映射为灰度
+pub fn palette_shade(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0x03
+}
+
+// 扫描线渲染：在每行进入 HBlank 时一次画出整行
+impl Ppu {
+    // 背景与窗口的 tile 数据地址（LCDC bit4 为 1 时按 0x8000 无符号寻址，否则按 0x9000 有符号寻址）
+    pub fn bg_tile_addr(&self, tile: u8) -> usize {
+        if self.lcdc & 0x10 != 0 {tile as usize * 16} else {(0x1000 + tile as i8 as i32 * 16) as usize}
+    }
+
+    // tile 中第 row 行第 col 列像素的颜色号
+    pub fn tile_pixel(&self, base: usize, row: u8, col: u8) -> u8 {
+        let lo = self.vram[base + row as usize * 2];
+        let hi = self.vram[base + row as usize * 2 + 1];
+        let bit = 7 - col;
+        (((hi >> bit) & 1) << 1) | ((lo >> bit) & 1)
+    }
+
+    // 背景图中第 index 个 tile 第 row 行第 col 列像素的颜色号，以及 CGB 属性（VRAM bank 1 的同一位置）：
+    // bit0-2 调色板，bit3 tile 所在 bank，bit5/bit6 水平/垂直翻转，bit7 背景优先
+    pub fn bg_pixel(&self, index: usize, row: u8, col: u8) -> (u8, u8) {
+        let attr = if self.cgb {self.vram[0x2000 + index]} else {0};
+        let mut base = self.bg_tile_addr(self.vram[index]);
+        if attr & 0x08 != 0 {base += 0x2000;}
+        let row = if attr & 0x40 != 0 {7 - row} else {row};
+        let col = if attr & 0x20 != 0 {7 - col} else {col};
+        (self.tile_pixel(base, row, col), attr)
+    }
+
+    // 背景像素经调色板后的输出
+    pub fn bg_output(&self, color: u8, attr: u8) -> u16 {
+        if self.cgb {cgb_color(&self.bg_palette, attr & 0x07, color)} else {palette_shade(self.bgp, color) as u16}
+    }
+
+    // 精灵像素经调色板后的输出：DMG 由属性 bit4 选 OBP0/OBP1，CGB 由 bit0-2 选调色板
+    pub fn obj_output(&self, color: u8, attr: u8) -> u16 {
+        if self.cgb {return cgb_color(&self.obj_palette, attr & 0x07, color);}
+        palette_shade(if attr & 0x10 != 0 {self.obp1} else {self.obp0}, color) as u16
+    }
+
+    // 精灵 tile 数据的地址：CGB 属性 bit3 选 VRAM bank
+    pub fn obj_tile_addr(&self, tile: u8, attr: u8) -> usize {
+        let bank = if self.cgb && attr & 0x08 != 0 {0x2000} else {0};
+        bank + tile as usize * 16
+    }
+
+    // 背景颜色号 1-3 是否盖住精灵：DMG 看精灵属性 bit7；
+    // CGB 另看背景属性 bit7，且 LCDC bit0 为 0 时精灵总在上
+    pub fn bg_over_obj(&self, bg_color: u8, bg_attr: u8, obj_attr: u8) -> bool {
+        if bg_color == 0 {return false;}
+        if self.cgb {self.lcdc & 0x01 != 0 && (bg_attr | obj_attr) & 0x80 != 0} else {obj_attr & 0x80 != 0}
+    }
+
+    // 精灵之间按 OAM 序号决定优先级（CGB 且 OPRI bit0 为 0），否则按 X 坐标
+    pub fn obj_priority_by_index(&self) -> bool {
+        self.cgb && self.opri & 0x01 == 0
+    }
+
+    // 精灵高度：LCDC bit2 为 1 时为 8x16
+    pub fn sprite_height(&self) -> u8 {
+        if self.lcdc & 0x04 != 0 {16} else {8}
+    }
+
+    // OAM 扫描：按 OAM 顺序取与当前行相交的前 10 个精灵
+    pub fn scan_oam(&self) -> Vec<usize> {
+        let height = self.sprite_height() as i32;
+        (0..40)
+            .filter(|&i| {
+                let top = self.oam[i * 4] as i32 - 16;
+                (top..top + height).contains(&(self.ly as i32))
+            })
+            .take(MAX_SPRITES_PER_LINE)
+            .collect()
+    }
+
+    pub fn render_scanline(&mut self) {
+        // 每个像素的背景颜色号与 CGB 属性
+        let mut bg = [(0u8, 0u8); SCREEN_WIDTH];
+        let mut line = [0u16; SCREEN_WIDTH];
+
+        // DMG 中 LCDC bit0 为 0 时背景与窗口都显示为白色；CGB 中该位只取消背景的优先级
+        if self.cgb || self.lcdc & 0x01 != 0 {
+            let map = if self.lcdc & 0x08 != 0 {0x1c00} else {0x1800};
+            let y = self.ly.wrapping_add(self.scy);
+            for (x, pixel) in bg.iter_mut().enumerate() {
+                let px = (x as u8).wrapping_add(self.scx);
+                *pixel = self.bg_pixel(map + (y as usize / 8) * 32 + px as usize / 8, y % 8, px % 8);
+            }
+
+            if self.lcdc & 0x20 != 0 && self.wy_triggered && self.wx <= 166 {
+                let map = if self.lcdc & 0x40 != 0 {0x1c00} else {0x1800};
+                let wy = self.window_line;
+                let start = self.wx as usize;
+                for (x, pixel) in bg.iter_mut().enumerate().skip(start.saturating_sub(7)) {
+                    let wx = x + 7 - start;
+                    *pixel = self.bg_pixel(map + (wy as usize / 8) * 32 + wx / 8, wy % 8, (wx % 8) as u8);
+                }
+                self.window_line += 1;
+            }
+
+            for (out, &(color, attr)) in line.iter_mut().zip(bg.iter()) {
+                *out = self.bg_output(color, attr);
+            }
+        }
+
+        if self.lcdc & 0x02 != 0 {self.render_sprites(&bg, &mut line);}
+
+        let start = self.ly as usize * SCREEN_WIDTH;
+        self.back[start..start + SCREEN_WIDTH].copy_from_slice(&line);
+    }
+
+    // DMG 精灵优先级：X 坐标小的在上，X 相同时 OAM 序号小的在上（CGB 只看 OAM 序号）；
+    // 高优先级精灵的不透明像素即使被背景遮挡，也会挡住低优先级精灵
+    fn render_sprites(&self, bg: &[(u8, u8); SCREEN_WIDTH], line: &mut [u16; SCREEN_WIDTH]) {
+        let height = self.sprite_height();
+        let mut sprites = self.scan_oam();
+        if !self.obj_priority_by_index() {sprites.sort_by_key(|&i| (self.oam[i * 4 + 1], i));}
+
+        let mut owned = [false; SCREEN_WIDTH];
+        for i in sprites {
+            let [y, x, tile, attr] = [self.oam[i * 4], self.oam[i * 4 + 1], self.oam[i * 4 + 2], self.oam[i * 4 + 3]];
+            let mut row = (self.ly as i32 - (y as i32 - 16)) as u8;
+            if attr & 0x40 != 0 {row = height - 1 - row;}
+            let base = self.obj_tile_addr(if height == 16 {tile & 0xfe} else {tile}, attr);
+            for col in 0..8u8 {
+                let sx = x as i32 - 8 + col as i32;
+                if !(0..SCREEN_WIDTH as i32).contains(&sx) || owned[sx as usize] {continue;}
+                let color = self.tile_pixel(base, row, if attr & 0x20 != 0 {7 - col} else {col});
+                if color == 0 {continue;}
+                owned[sx as usize] = true;
+                let (bg_color, bg_attr) = bg[sx as usize];
+                if self.bg_over_obj(bg_color, bg_attr, attr) {continue;}
+                line[sx as usize] = self.obj_output(color, attr);
+            }
+        }
+    }
+}
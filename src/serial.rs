@@ -0,0 +1,418 @@
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{fs, os::unix::net::{UnixListener, UnixStream}};
+
+use pyo3::{PyTraverseError, PyVisit, prelude::*};
+
+use crate::SoC;
+
+// 内部时钟 8192 Hz：每移一位 128 个 M-cycle；CGB 的 SC bit1 选高速时钟（262144 Hz）时为 4 个
+const BIT_CYCLES: u16 = 128;
+const FAST_BIT_CYCLES: u16 = 4;
+
+// 套接字连接时检查对端报文的间隔（M-cycle）
+const POLL_CYCLES: u16 = 128;
+
+// 作为主机等待套接字对端应答的最长时间（宿主机时间），超时报错并断开连接
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+// 套接字报文均为两个字节 [类型, 数据]：主机发出的字节，以及从机的应答
+const MSG_TRANSFER: u8 = 0x01;
+const MSG_REPLY: u8 = 0x02;
+
+// 可用作串口连线的字节流
+pub trait LinkStream: Read + Write + Send + Sync {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl LinkStream for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl LinkStream for UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+// 套接字对端：非阻塞读取，收到的字节先存入 rx 再按报文拆分
+pub struct LinkSocket {
+    stream: Box<dyn LinkStream>,
+    rx: Vec<u8>,
+    // 作为主机发出字节后等待应答的截止时间
+    deadline: Option<Instant>,
+}
+
+impl LinkSocket {
+    fn new(stream: Box<dyn LinkStream>) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self { stream, rx: Vec::new(), deadline: None })
+    }
+
+    // 主动连接："unix:/path" 为 Unix 套接字，其余按 TCP 的 "host:port" 处理
+    pub fn connect(address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix:") {
+            return Self::new(Box::new(UnixStream::connect(path)?));
+        }
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        Self::new(Box::new(stream))
+    }
+
+    // 在 address 上监听，阻塞到一个对端连入
+    pub fn listen(address: &str) -> io::Result<Self> {
+        #[cfg(unix)]
+        if let Some(path) = address.strip_prefix("unix:") {
+            // 连入后监听套接字即关闭，套接字文件随之删除（accept 失败时同样删除）
+            let accepted = UnixListener::bind(path)?.accept();
+            let _ = fs::remove_file(path);
+            return Self::new(Box::new(accepted?.0));
+        }
+        let (stream, _) = TcpListener::bind(address)?.accept()?;
+        stream.set_nodelay(true)?;
+        Self::new(Box::new(stream))
+    }
+
+    fn send(&mut self, kind: u8, data: u8) -> io::Result<()> {
+        self.stream.write_all(&[kind, data])
+    }
+
+    // 作为主机发出一个字节，开始等待应答
+    fn send_transfer(&mut self, data: u8) -> io::Result<()> {
+        self.send(MSG_TRANSFER, data)?;
+        self.deadline = Some(Instant::now() + REPLY_TIMEOUT);
+        Ok(())
+    }
+
+    // 读出已到达的全部字节；对端关闭连接时报错
+    fn pump(&mut self) -> io::Result<()> {
+        let mut buf = [0; 64];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "串口对端已断开")),
+                Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {},
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn next_message(&mut self) -> Option<(u8, u8)> {
+        if self.rx.len() < 2 {return None;}
+        let message = (self.rx[0], self.rx[1]);
+        self.rx.drain(..2);
+        Some(message)
+    }
+}
+
+// 串口的连线对端
+pub enum Link {
+    // 未连接：SIN 上拉，收到 0xFF
+    Disconnected,
+    // SOUT 接回 SIN，收到自己发出的字节
+    Loopback,
+    // callback(发出的字节) 返回收到的字节，None 视为 0xFF
+    Callback(PyObject),
+    // 同一进程中的另一台 SoC，每个移位时钟交换一位
+    Soc(Py<SoC>),
+    // TCP 或 Unix 套接字另一端的模拟器，按字节交换
+    Socket(LinkSocket),
+}
+
+// 串口：0xFF01 SB 为移位寄存器，0xFF02 SC 的 bit7 为传输开始/进行中，bit0 为 1 时使用内部时钟，
+// CGB 模式下 bit1 选择高速时钟
+//
+// 内部时钟传输每 128 个 M-cycle 移出 SB 最高位、移入对端的一位，8 位移完后清除 SC bit7 并请求 Serial 中断。
+// 另一台 SoC 在每个移位时钟随之移一位；回调与套接字按字节交换：传输开始时发出字节，
+// 对端送回的字节再逐位移入，套接字的应答未到时移位时钟暂停。外部时钟传输等待对端作为主机驱动，
+// 本机 SC 不是外部时钟或 bit7 未置位时对端收到 1
+pub struct Serial {
+    pub sb: u8,
+    sc: u8,
+    pub cgb: bool,
+    // 按字节交换的对端送回、尚未移入的位；套接字应答未到时为 None
+    incoming: Option<u8>,
+    // 本次传输剩余的位数
+    bits: u8,
+    timer: u16,
+    poll: u16,
+    irq: bool,
+    pub link: Link,
+    // 作为主机发出的全部字节（测试 ROM 经串口打印的文本）；不设上限，由 clear_serial_output 清空
+    pub output: Vec<u8>,
+    // 与对端交换数据时的错误（回调异常、套接字断开或应答超时等），在下一条指令执行后抛出
+    pub error: Option<PyErr>,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Self { sb: 0, sc: 0, cgb: false, incoming: None, bits: 0, timer: 0, poll: POLL_CYCLES, irq: false, link: Link::Disconnected, output: Vec::new(), error: None }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff01 => self.sb,
+            _ => self.sc | if self.cgb {0x7c} else {0x7e},
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xff01 => self.sb = data,
+            _ => {
+                self.sc = data & if self.cgb {0x83} else {0x81};
+                self.bits = if self.sc & 0x80 != 0 {8} else {0};
+                if self.sc & 0x81 == 0x81 {self.start();}
+            }
+        }
+    }
+
+    // 作为主机开始一次传输
+    fn start(&mut self) {
+        self.output.push(self.sb);
+        self.incoming = self.exchange(self.sb);
+        self.timer = self.bit_cycles();
+    }
+
+    fn bit_cycles(&self) -> u16 {
+        if self.sc & 0x02 != 0 {FAST_BIT_CYCLES} else {BIT_CYCLES}
+    }
+
+    // 传输完成：清除 SC bit7 并请求 Serial 中断
+    fn finish(&mut self) {
+        self.bits = 0;
+        self.sc &= 0x7f;
+        self.irq = true;
+    }
+
+    // 推进一个 M-cycle；返回是否请求 Serial 中断
+    pub fn step(&mut self) -> bool {
+        if self.bits > 0 && self.sc & 0x01 != 0 {
+            self.timer -= 1;
+            if self.timer == 0 {self.clock();}
+        }
+        if let Link::Socket(_) = self.link {
+            self.poll -= 1;
+            if self.poll == 0 {
+                self.poll = POLL_CYCLES;
+                self.poll_socket();
+            }
+        }
+        std::mem::take(&mut self.irq)
+    }
+
+    // 内部时钟的一个移位时钟；对端还给不出这一位时在下一个 M-cycle 重试
+    fn clock(&mut self) {
+        let Some(bit) = self.peer_bit(self.sb >> 7) else {
+            self.timer = 1;
+            return;
+        };
+        self.sb = (self.sb << 1) | bit;
+        self.bits -= 1;
+        self.timer = self.bit_cycles();
+        if self.bits == 0 {self.finish();}
+    }
+
+    // 把本机移出的一位交给对端，返回对端移出的一位
+    fn peer_bit(&mut self, out: u8) -> Option<u8> {
+        if let Link::Soc(peer) = &self.link {
+            // 对端正被占用（如正在执行指令）时不报错，等它空闲
+            return Python::with_gil(|py| Some(peer.try_borrow_mut(py).ok()?.bus.serial.clock_external(out)));
+        }
+        if self.incoming.is_none() {self.wait_reply();}
+        let incoming = self.incoming?;
+        self.incoming = Some(incoming << 1);
+        Some(incoming >> 7)
+    }
+
+    // 对端主机的一个移位时钟：外部时钟传输进行中时移入 bit，返回移出的一位
+    pub fn clock_external(&mut self, bit: u8) -> u8 {
+        if self.sc & 0x81 != 0x80 {return 1;}
+        let out = self.sb >> 7;
+        self.sb = (self.sb << 1) | bit;
+        self.bits -= 1;
+        if self.bits == 0 {self.finish();}
+        out
+    }
+
+    // 套接字对端作为主机发来一个字节：外部时钟传输等待中则完成传输，返回本机移出的字节
+    pub fn receive(&mut self, data: u8) -> u8 {
+        if self.sc & 0x81 != 0x80 {return 0xff;}
+        let reply = self.sb;
+        self.sb = data;
+        self.finish();
+        reply
+    }
+
+    // 把 out 发给按字节交换的对端，返回对端送回的字节；套接字的应答稍后才到，此时为 None
+    fn exchange(&mut self, out: u8) -> Option<u8> {
+        let result = match &mut self.link {
+            // 另一台 SoC 逐位交换；传输中途断开时移入 1
+            Link::Disconnected | Link::Soc(_) => Ok(Some(0xff)),
+            Link::Loopback => Ok(Some(out)),
+            Link::Callback(callback) => Python::with_gil(|py| -> PyResult<Option<u8>> {
+                Ok(Some(callback.call1(py, (out,))?.extract::<Option<u8>>(py)?.unwrap_or(0xff)))
+            }),
+            Link::Socket(socket) => socket.send_transfer(out).map(|()| None).map_err(PyErr::from),
+        };
+        result.unwrap_or_else(|err| {
+            self.fail(err);
+            Some(0xff)
+        })
+    }
+
+    // 套接字应答未到：先读一次对端报文，仍未到且已超时则报错并断开；连线已断开时移入 1
+    fn wait_reply(&mut self) {
+        self.poll_socket();
+        if self.incoming.is_some() {return;}
+        match &self.link {
+            Link::Socket(LinkSocket { deadline: Some(deadline), .. }) if Instant::now() < *deadline => return,
+            Link::Socket(LinkSocket { deadline: Some(_), .. }) => {
+                self.fail(io::Error::new(ErrorKind::TimedOut, "等待串口对端应答超时").into());
+            }
+            _ => {},
+        }
+        self.incoming = Some(0xff);
+    }
+
+    // 处理套接字对端发来的报文：作为从机应答对端的传输，作为主机收下等待中的应答
+    fn poll_socket(&mut self) {
+        let Link::Socket(mut socket) = std::mem::replace(&mut self.link, Link::Disconnected) else {return;};
+        let result = (|| -> io::Result<()> {
+            socket.pump()?;
+            while let Some((kind, data)) = socket.next_message() {
+                match kind {
+                    MSG_TRANSFER => socket.send(MSG_REPLY, self.receive(data))?,
+                    MSG_REPLY if socket.deadline.take().is_some() => self.incoming = Some(data),
+                    _ => {},
+                }
+            }
+            Ok(())
+        })();
+        match result {
+            Ok(()) => self.link = Link::Socket(socket),
+            Err(err) => self.fail(err.into()),
+        }
+    }
+
+    // 记下错误；套接字出错时断开连接
+    fn fail(&mut self, err: PyErr) {
+        if let Link::Socket(_) = self.link {self.link = Link::Disconnected;}
+        self.error = Some(err);
+    }
+
+    // 供 Python 垃圾回收遍历持有的 Python 对象（两台 SoC 互连时形成引用环）
+    pub fn traverse(&self, visit: &PyVisit<'_>) -> Result<(), PyTraverseError> {
+        match &self.link {
+            Link::Callback(callback) => visit.call(callback),
+            Link::Soc(peer) => visit.call(peer),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+
+    // 内部时钟开始传输 data
+    fn start(serial: &mut Serial, data: u8) {
+        serial.write(0xff01, data);
+        serial.write(0xff02, 0x81);
+    }
+
+    // 外部时钟等待对端传输
+    fn wait(serial: &mut Serial, data: u8) {
+        serial.write(0xff01, data);
+        serial.write(0xff02, 0x80);
+    }
+
+    fn soc() -> SoC {
+        SoC::with_rom(vec![0; 0x8000])
+    }
+
+    #[test]
+    fn loopback_echoes_sb() {
+        let mut serial = Serial::new();
+        serial.link = Link::Loopback;
+        start(&mut serial, 0x5a);
+        let irqs = (0..BIT_CYCLES * 8).filter(|_| serial.step()).count();
+        assert_eq!((irqs, serial.sb, serial.read(0xff02)), (1, 0x5a, 0x7f));
+        assert_eq!(serial.output, [0x5a]);
+    }
+
+    #[test]
+    fn two_socs_exchange_a_byte() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let master = Py::new(py, soc()).unwrap();
+            let slave = Py::new(py, soc()).unwrap();
+            master.borrow_mut(py).bus.serial.link = Link::Soc(slave.clone_ref(py));
+            slave.borrow_mut(py).bus.serial.link = Link::Soc(master.clone_ref(py));
+            wait(&mut slave.borrow_mut(py).bus.serial, 0xc3);
+            start(&mut master.borrow_mut(py).bus.serial, 0x81);
+            // 两端都在推进；第 4 个移位时钟前后从机被占用（正在执行指令），主机的移位时钟暂停而不是 panic
+            let busy = BIT_CYCLES * 4 - 32..BIT_CYCLES * 4 + 32;
+            for cycle in 0..BIT_CYCLES * 8 + 64 {
+                if busy.contains(&cycle) {
+                    let mut slave = slave.borrow_mut(py);
+                    slave.bus.tick(1);
+                    master.borrow_mut(py).bus.tick(1);
+                } else {
+                    slave.borrow_mut(py).bus.tick(1);
+                    master.borrow_mut(py).bus.tick(1);
+                }
+                if cycle == busy.end - 1 {assert_eq!(master.borrow(py).bus.serial.bits, 5);}
+            }
+            let (master, slave) = (master.borrow(py), slave.borrow(py));
+            assert_eq!((master.bus.serial.sb, slave.bus.serial.sb), (0xc3, 0x81));
+            assert_eq!((master.bus.serial.read(0xff02), slave.bus.serial.read(0xff02)), (0x7f, 0x7e));
+            let serial_bit = crate::interrupt::Interrupt::Serial.bit();
+            assert_eq!((master.bus.int_flag & serial_bit, slave.bus.int_flag & serial_bit), (serial_bit, serial_bit));
+        });
+    }
+
+    #[test]
+    fn socket_exchanges_bytes_with_a_tcp_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let (ready, waiting) = mpsc::channel();
+        let peer = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut message = [0; 2];
+            // 本机作为主机发来 0x99，应答 0x42
+            stream.read_exact(&mut message).unwrap();
+            stream.write_all(&[MSG_REPLY, 0x42]).unwrap();
+            let sent = message;
+            // 本机进入外部时钟等待后，对端作为主机发出 0x11，收到本机的应答
+            waiting.recv().unwrap();
+            stream.write_all(&[MSG_TRANSFER, 0x11]).unwrap();
+            stream.read_exact(&mut message).unwrap();
+            (sent, message)
+        });
+
+        let mut serial = Serial::new();
+        serial.link = Link::Socket(LinkSocket::connect(&address).unwrap());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        start(&mut serial, 0x99);
+        while serial.read(0xff02) & 0x80 != 0 && Instant::now() < deadline {serial.step();}
+        assert_eq!(serial.sb, 0x42);
+        wait(&mut serial, 0x24);
+        ready.send(()).unwrap();
+        while serial.read(0xff02) & 0x80 != 0 && Instant::now() < deadline {serial.step();}
+        assert_eq!(serial.sb, 0x11);
+        assert!(serial.error.is_none());
+        assert_eq!(peer.join().unwrap(), ([MSG_TRANSFER, 0x99], [MSG_REPLY, 0x24]));
+    }
+}
@@ -0,0 +1,156 @@
+// 可编程定时器：0xFF04 DIV、0xFF05 TIMA、0xFF06 TMA、0xFF07 TAC
+//
+// DIV 是内部 16 位分频计数器（按 T-cycle 计数）的高 8 位；TIMA 在 TAC 选中的分频位
+// 与使能位相与后的信号出现下降沿时加 1，因此复位 DIV 或修改 TAC 也可能让 TIMA 加 1。
+// TIMA 溢出后先保持 0x00 一个 M-cycle，下一个 M-cycle 才载入 TMA 并请求 Timer 中断。
+pub struct Timer {
+    pub div: u16,
+    pub tima: u8,
+    pub tma: u8,
+    pub tac: u8,
+    // TIMA 已溢出，等待下一个 M-cycle 载入 TMA
+    overflow: bool,
+    // 本 M-cycle 正在载入 TMA：写 TIMA 被忽略，写 TMA 同时写入 TIMA
+    reloading: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self { div: 0, tima: 0, tma: 0, tac: 0, overflow: false, reloading: false }
+    }
+
+    // TAC 选中的分频位与使能位相与后的信号
+    fn signal(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0b00 => 9,
+            0b01 => 3,
+            0b10 => 5,
+            _    => 7,
+        };
+        self.tac & 0x04 != 0 && self.div & (1 << bit) != 0
+    }
+
+    fn inc_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        self.overflow = overflow;
+    }
+
+    // 推进一个 M-cycle，返回是否请求 Timer 中断
+    pub fn step(&mut self) -> bool {
+        self.reloading = false;
+        let mut irq = false;
+        if self.overflow {
+            self.overflow = false;
+            self.tima = self.tma;
+            self.reloading = true;
+            irq = true;
+        }
+        let prev = self.signal();
+        self.div = self.div.wrapping_add(4);
+        if prev && !self.signal() {self.inc_tima();}
+        irq
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0xff04 => (self.div >> 8) as u8,
+            0xff05 => self.tima,
+            0xff06 => self.tma,
+            _      => self.tac | 0xf8,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        let prev = self.signal();
+        match addr {
+            0xff04 => self.div = 0,
+            0xff05 => if !self.reloading {
+                // 溢出后的等待期间写 TIMA 会取消这次载入与中断
+                self.tima = data;
+                self.overflow = false;
+            },
+            0xff06 => {
+                self.tma = data;
+                if self.reloading {self.tima = data;}
+            }
+            _ => self.tac = data & 0x07,
+        }
+        if prev && !self.signal() {self.inc_tima();}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 推进 n 个 M-cycle，返回请求中断的次数
+    fn run(timer: &mut Timer, n: usize) -> usize {
+        (0..n).filter(|_| timer.step()).count()
+    }
+
+    #[test]
+    fn div_counts_m_cycles() {
+        let mut timer = Timer::new();
+        run(&mut timer, 64);
+        assert_eq!(timer.read(0xff04), 1);
+        timer.write(0xff04, 0x55);
+        assert_eq!(timer.read(0xff04), 0);
+        assert_eq!(timer.read(0xff07), 0xf8);
+    }
+
+    #[test]
+    fn tima_rate_follows_tac() {
+        let mut timer = Timer::new();
+        timer.write(0xff07, 0x05);
+        run(&mut timer, 40);
+        assert_eq!(timer.tima, 10);
+        timer.write(0xff07, 0x04);
+        timer.tima = 0;
+        run(&mut timer, 1024);
+        assert_eq!(timer.tima, 4);
+    }
+
+    #[test]
+    fn div_reset_falling_edge_increments_tima() {
+        let mut timer = Timer::new();
+        timer.write(0xff07, 0x05);
+        run(&mut timer, 2);
+        assert_eq!((timer.tima, timer.div & 0x08), (0, 0x08));
+        timer.write(0xff04, 0);
+        assert_eq!(timer.tima, 1);
+        // 关闭定时器时选中位为 1 同样构成下降沿
+        run(&mut timer, 2);
+        timer.write(0xff07, 0x01);
+        assert_eq!(timer.tima, 2);
+    }
+
+    #[test]
+    fn overflow_reloads_tma_one_cycle_later() {
+        let mut timer = Timer::new();
+        timer.write(0xff06, 0xab);
+        timer.write(0xff07, 0x05);
+        timer.tima = 0xff;
+        assert_eq!(run(&mut timer, 4), 0);
+        assert_eq!(timer.tima, 0x00);
+        assert!(timer.step());
+        assert_eq!(timer.tima, 0xab);
+        // 载入当拍写 TIMA 被忽略，写 TMA 同时写入 TIMA
+        timer.write(0xff05, 0x11);
+        assert_eq!(timer.tima, 0xab);
+        timer.write(0xff06, 0xcd);
+        assert_eq!(timer.tima, 0xcd);
+    }
+
+    #[test]
+    fn write_during_overflow_cancels_reload() {
+        let mut timer = Timer::new();
+        timer.write(0xff06, 0xab);
+        timer.write(0xff07, 0x05);
+        timer.tima = 0xff;
+        run(&mut timer, 4);
+        timer.write(0xff05, 0x42);
+        assert!(!timer.step());
+        assert_eq!(timer.tima, 0x42);
+    }
+}